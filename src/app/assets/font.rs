@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+/// Where a character is in a font's atlas and how it sits on the line, in atlas pixels
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    /// Top left corner in the atlas
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// From the pen position on the top of the line to the glyph's top left corner, y down
+    pub x_offset: f32,
+    pub y_offset: f32,
+    /// How far the pen moves after drawing the glyph
+    pub x_advance: f32,
+}
+
+/// A signed distance field font, its glyphs packed into one atlas texture.
+/// Read from the text format of AngelCode BMFont files.
+#[derive(Debug, Clone)]
+pub struct Font {
+    /// File name of the atlas, relative to the font file
+    pub page: String,
+    /// Texture asset of the atlas, with the distance to each glyph's edge in its alpha channel.
+    /// Set once the font is loaded.
+    pub atlas: usize,
+    /// Distance between lines, in atlas pixels
+    pub line_height: f32,
+    /// Size of the atlas in pixels
+    pub atlas_width: f32,
+    pub atlas_height: f32,
+    glyphs: HashMap<char, Glyph>,
+    /// Extra space between a pair of characters, usually negative
+    kerning: HashMap<(char, char), f32>,
+}
+
+/// A glyph placed by `Font::layout`
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedGlyph {
+    /// Left, top, right and bottom edges in line heights from the top left of the text, y down
+    pub rect: [f32; 4],
+    /// Left, top, right and bottom edges in the atlas, from 0 to 1
+    pub uv: [f32; 4],
+}
+
+/// Glyphs of a string placed line by line
+#[derive(Debug, Clone, Default)]
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Width of the longest line, in line heights
+    pub width: f32,
+    /// Number of lines
+    pub height: f32,
+}
+
+impl Font {
+    pub fn parse(source: &str) -> Result<Font, &'static str> {
+        let mut font = Font {
+            page: String::new(),
+            atlas: 0,
+            line_height: 0.0,
+            atlas_width: 0.0,
+            atlas_height: 0.0,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+        };
+
+        for line in source.lines() {
+            let (tag, pairs) = parse_line(line);
+            let get = |key: &str| pairs.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+            let number_or = |key: &str, or: f32| match get(key) {
+                Some(v) => v.parse::<f32>().map_err(|_| "Font values must be numbers"),
+                None => Ok(or),
+            };
+            let number = |key: &str| match get(key) {
+                Some(_) => number_or(key, 0.0),
+                None => Err("Font is missing a value"),
+            };
+            let character = |key: &str| {
+                char::from_u32(number(key)? as u32).ok_or("Font characters must be unicode")
+            };
+
+            match tag {
+                "common" => {
+                    font.line_height = number("lineHeight")?;
+                    font.atlas_width = number("scaleW")?;
+                    font.atlas_height = number("scaleH")?;
+                    if number_or("pages", 1.0)? > 1.0 {
+                        log::warn!(
+                            "Fonts can only have one atlas, glyphs on other pages are skipped"
+                        );
+                    }
+                }
+                "page" if number("id")? == 0.0 => {
+                    font.page = get("file").ok_or("Font page needs a file")?.to_string();
+                }
+                "char" if number_or("page", 0.0)? == 0.0 => {
+                    font.glyphs.insert(
+                        character("id")?,
+                        Glyph {
+                            x: number("x")?,
+                            y: number("y")?,
+                            width: number("width")?,
+                            height: number("height")?,
+                            x_offset: number("xoffset")?,
+                            y_offset: number("yoffset")?,
+                            x_advance: number("xadvance")?,
+                        },
+                    );
+                }
+                "kerning" => {
+                    font.kerning.insert(
+                        (character("first")?, character("second")?),
+                        number("amount")?,
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        if font.page.is_empty() {
+            return Err("Font has no atlas page");
+        }
+        if font.line_height <= 0.0 || font.atlas_width <= 0.0 || font.atlas_height <= 0.0 {
+            return Err("Font needs a line height and atlas size");
+        }
+
+        Ok(font)
+    }
+
+    /// Place the glyphs of `text`, starting a new line at each `\n`.
+    /// Characters the font lacks are drawn as `?`, or skipped if it lacks that too.
+    pub fn layout(&self, text: &str) -> TextLayout {
+        let mut layout = TextLayout {
+            height: 1.0,
+            ..Default::default()
+        };
+        let mut pen = 0.0;
+        let mut previous = None;
+
+        for c in text.chars() {
+            if c == '\n' {
+                layout.width = layout.width.max(pen);
+                layout.height += 1.0;
+                pen = 0.0;
+                previous = None;
+                continue;
+            }
+
+            let glyph = match self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?')) {
+                Some(g) => g,
+                None => continue,
+            };
+            if let Some(p) = previous {
+                pen += self.kerning.get(&(p, c)).copied().unwrap_or(0.0);
+            }
+
+            // Spaces only move the pen
+            if glyph.width > 0.0 && glyph.height > 0.0 {
+                let left = pen + glyph.x_offset;
+                let top = (layout.height - 1.0) * self.line_height + glyph.y_offset;
+
+                layout.glyphs.push(PlacedGlyph {
+                    rect: [
+                        left / self.line_height,
+                        top / self.line_height,
+                        (left + glyph.width) / self.line_height,
+                        (top + glyph.height) / self.line_height,
+                    ],
+                    uv: [
+                        glyph.x / self.atlas_width,
+                        glyph.y / self.atlas_height,
+                        (glyph.x + glyph.width) / self.atlas_width,
+                        (glyph.y + glyph.height) / self.atlas_height,
+                    ],
+                });
+            }
+
+            pen += glyph.x_advance;
+            previous = Some(c);
+        }
+
+        layout.width = layout.width.max(pen) / self.line_height;
+        layout
+    }
+}
+
+/// Split a line of a BMFont file into its tag and `key=value` pairs, unquoting values
+fn parse_line(line: &str) -> (&str, Vec<(&str, &str)>) {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+
+    let mut pairs = Vec::new();
+    while let Some((key, after)) = rest.trim_start().split_once('=') {
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(' ').unwrap_or((after, "")),
+        };
+        pairs.push((key.trim(), value));
+        rest = after;
+    }
+
+    (tag, pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two letters with a kerning pair, the fallback `?` and a space
+    static FIXTURE: &str = r#"info face="Test Font" size=32
+common lineHeight=32 base=26 scaleW=256 scaleH=128 pages=1
+page id=0 file="test font.png"
+chars count=4
+char id=65 x=0 y=0 width=16 height=20 xoffset=1 yoffset=6 xadvance=18 page=0 chnl=15
+char id=66 x=16 y=0 width=16 height=20 xoffset=1 yoffset=6 xadvance=16 page=0 chnl=15
+char id=63 x=32 y=64 width=12 height=20 xoffset=2 yoffset=6 xadvance=14 page=0 chnl=15
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=8 page=0 chnl=15
+kernings count=1
+kerning first=65 second=66 amount=-2
+"#;
+
+    fn font() -> Font {
+        Font::parse(FIXTURE).unwrap()
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn parses_quoted_values_and_sizes() {
+        let font = font();
+        assert_eq!(font.page, "test font.png");
+        assert_eq!(font.line_height, 32.0);
+        assert_eq!((font.atlas_width, font.atlas_height), (256.0, 128.0));
+    }
+
+    #[test]
+    fn missing_values_are_errors() {
+        let source = FIXTURE.replace(" xadvance=16", "");
+        assert!(Font::parse(&source).is_err());
+
+        let source = FIXTURE.replace("lineHeight=32 ", "");
+        assert!(Font::parse(&source).is_err());
+    }
+
+    #[test]
+    fn kerning_pulls_pairs_together() {
+        let layout = font().layout("AB");
+        assert_eq!(layout.glyphs.len(), 2);
+
+        // B starts after A's advance less the kerning, plus its own offset
+        let [left, top, right, bottom] = layout.glyphs[1].rect;
+        assert!(close(left, (18.0 - 2.0 + 1.0) / 32.0));
+        assert!(close(top, 6.0 / 32.0));
+        assert!(close(right - left, 16.0 / 32.0));
+        assert!(close(bottom - top, 20.0 / 32.0));
+
+        // Width and height are in line heights, for anchoring
+        assert!(close(layout.width, (18.0 - 2.0 + 16.0) / 32.0));
+        assert_eq!(layout.height, 1.0);
+    }
+
+    #[test]
+    fn line_breaks_start_a_new_line() {
+        let layout = font().layout("AA\nB");
+        assert_eq!(layout.height, 2.0);
+        assert!(close(layout.width, 36.0 / 32.0));
+
+        // No kerning carries over the break
+        let [left, top, ..] = layout.glyphs[2].rect;
+        assert!(close(left, 1.0 / 32.0));
+        assert!(close(top, (32.0 + 6.0) / 32.0));
+    }
+
+    #[test]
+    fn unknown_characters_fall_back_to_question_mark() {
+        let layout = font().layout("Z");
+        assert_eq!(layout.glyphs.len(), 1);
+        assert_eq!(
+            layout.glyphs[0].uv,
+            [32.0 / 256.0, 64.0 / 128.0, 44.0 / 256.0, 84.0 / 128.0]
+        );
+    }
+
+    #[test]
+    fn spaces_only_move_the_pen() {
+        let layout = font().layout(" A");
+        assert_eq!(layout.glyphs.len(), 1);
+        assert!(close(layout.glyphs[0].rect[0], (8.0 + 1.0) / 32.0));
+        assert!(close(layout.width, (8.0 + 18.0) / 32.0));
+    }
+}
@@ -1,8 +1,9 @@
 use gltf::{
     buffer::{self, Data},
     image::Source,
-    Document, Error,
+    Document, Error, Node, Texture,
 };
+use nalgebra::Matrix4;
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
@@ -11,12 +12,120 @@ use web_sys::WebGl2RenderingContext;
 
 use crate::{fetch, render::rgl::texture::Tex};
 
-use super::render::material::{Mat, Uniform};
+pub mod font;
+use self::font::Font;
+
+use super::render::material::{AlphaMode, Mat, PbrMaterial};
+use super::store::animation::{AnimationClip, Skin, Trs};
 
 pub struct GltfMesh {
     pub doc: Document,
     pub buffers: Vec<Data>,
+    /// Local transform of each node, indexed by node
+    pub rest_pose: Vec<Trs>,
+    /// Default morph target weights of each node, from the node or else its mesh
+    pub rest_morph_weights: Vec<Vec<f32>>,
+    /// Nodes of the scene paired with their parents, parents before children
+    scene_order: Vec<(usize, Option<usize>)>,
+    /// Transform of each node relative to the scene root in the rest pose, indexed by node.
+    /// Nodes outside the scene are left as identity.
+    pub world_transforms: Vec<Matrix4<f32>>,
+    /// Nodes of the scene that have a mesh, parents before children
+    pub mesh_nodes: Vec<usize>,
+    pub animations: Vec<AnimationClip>,
+    pub skins: Vec<Skin>,
+}
+
+impl GltfMesh {
+    pub fn new(doc: Document, buffers: Vec<Data>) -> GltfMesh {
+        let rest_pose = doc.nodes().map(|n| Trs::from_gltf(&n)).collect();
+        let rest_morph_weights = doc
+            .nodes()
+            .map(|n| match (n.weights(), n.mesh()) {
+                (Some(w), _) => w.to_vec(),
+                (None, Some(m)) => match m.weights() {
+                    Some(w) => w.to_vec(),
+                    None => {
+                        let targets = m.primitives().map(|p| p.morph_targets().len()).max();
+                        vec![0.0; targets.unwrap_or(0)]
+                    }
+                },
+                (None, None) => vec![],
+            })
+            .collect();
+
+        // Use the default scene, or the first one. Without scenes every parentless node is a root.
+        let roots: Vec<Node> = match doc.default_scene().or_else(|| doc.scenes().next()) {
+            Some(scene) => scene.nodes().collect(),
+            None => {
+                let children: HashSet<usize> = doc
+                    .nodes()
+                    .flat_map(|n| n.children().map(|c| c.index()))
+                    .collect();
+                doc.nodes()
+                    .filter(|n| !children.contains(&n.index()))
+                    .collect()
+            }
+        };
+
+        let mut scene_order = Vec::new();
+        let mut mesh_nodes = Vec::new();
+        let mut stack: Vec<(Node, Option<usize>)> =
+            roots.into_iter().rev().map(|n| (n, None)).collect();
+
+        while let Some((node, parent)) = stack.pop() {
+            scene_order.push((node.index(), parent));
+
+            if node.mesh().is_some() {
+                mesh_nodes.push(node.index());
+            }
+            for child in node.children().collect::<Vec<_>>().into_iter().rev() {
+                stack.push((child, Some(node.index())));
+            }
+        }
+
+        let animations = doc
+            .animations()
+            .map(|a| AnimationClip::from_gltf(&a, &buffers))
+            .collect();
+        let skins = doc.skins().map(|s| Skin::from_gltf(&s, &buffers)).collect();
+
+        let mut gltf = GltfMesh {
+            doc,
+            buffers,
+            rest_pose,
+            rest_morph_weights,
+            scene_order,
+            world_transforms: vec![],
+            mesh_nodes,
+            animations,
+            skins,
+        };
+        gltf.world_transforms = gltf.pose_world_transforms(&gltf.rest_pose);
+        gltf
+    }
+
+    /// Compose local node transforms down the scene hierarchy
+    pub fn pose_world_transforms(&self, pose: &[Trs]) -> Vec<Matrix4<f32>> {
+        let mut world = vec![Matrix4::identity(); pose.len()];
+
+        for &(node, parent) in &self.scene_order {
+            let local = pose[node].matrix();
+            world[node] = match parent {
+                Some(p) => world[p] * local,
+                None => local,
+            };
+        }
+        world
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&AnimationClip> {
+        self.animations
+            .iter()
+            .find(|a| a.name.as_deref() == Some(name))
+    }
 }
+
 struct AssetStore<T> {
     assets: Vec<Option<T>>,
     asset_indexes: HashMap<String, usize>,
@@ -36,6 +145,13 @@ impl<T> AssetStore<T> {
             id
         }
     }
+    /// Assign an index to an asset that will be loaded by hand rather than fetched
+    pub fn reserve(&mut self, asset_name: String) -> usize {
+        let id = self.assets.len();
+        self.asset_indexes.insert(asset_name, id);
+        self.assets.push(None);
+        id
+    }
     ///Loads a registered texture
     pub fn load(&mut self, asset_name: &str, asset: T) {
         self.assets[self.asset_indexes[asset_name]] = Some(asset);
@@ -74,15 +190,19 @@ pub struct Assets {
     textures: AssetStore<std::rc::Rc<Tex>>,
     gltf: AssetStore<GltfMesh>,
     materials: AssetStore<Mat>,
+    fonts: AssetStore<Font>,
 
     error_tex: Option<std::rc::Rc<Tex>>,
+    white_tex: usize,
+    flat_normal_tex: usize,
+    default_material: Mat,
 }
 
 impl Display for Assets {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "Assets:[textures: {}, meshes: {}, mats: {}]",
-            self.textures, self.gltf, self.materials
+            "Assets:[textures: {}, meshes: {}, mats: {}, fonts: {}]",
+            self.textures, self.gltf, self.materials, self.fonts
         ))
     }
 }
@@ -98,11 +218,22 @@ impl<T> Display for AssetStore<T> {
 
 impl Assets {
     pub fn new() -> Assets {
+        let mut textures = AssetStore::default();
+        let white_tex = textures.reserve("::white".to_owned());
+        let flat_normal_tex = textures.reserve("::flat_normal".to_owned());
+
+        let mut default_material = PbrMaterial::new(white_tex, flat_normal_tex);
+        default_material.metallic_factor = 0.0;
+
         Assets {
-            textures: Default::default(),
+            textures,
             gltf: Default::default(),
             materials: AssetStore::<Mat>::default(),
+            fonts: Default::default(),
             error_tex: None,
+            white_tex,
+            flat_normal_tex,
+            default_material: default_material.into(),
         }
     }
 
@@ -120,13 +251,35 @@ impl Assets {
                     .await
                     .unwrap();
 
-                assets.borrow_mut().load_gltf(
-                    &gltf,
-                    GltfMesh {
-                        doc: doc.document,
-                        buffers,
-                    },
-                );
+                assets
+                    .borrow_mut()
+                    .load_gltf(&gltf, GltfMesh::new(doc.document, buffers));
+            }
+        }
+
+        // Fonts first, as they require their atlas textures
+        let l = assets.borrow_mut().fonts.consume_loading();
+
+        for font in l {
+            let data = match fetch::fetch(&font).await {
+                Ok(data) => data,
+                Err(_) => {
+                    log::error!("Could not fetch font {}", font);
+                    continue;
+                }
+            };
+
+            match Font::parse(&String::from_utf8_lossy(&data)) {
+                Ok(mut f) => {
+                    let atlas = match font.rsplit_once('/') {
+                        Some((dir, _)) => format!("{}/{}", dir, f.page),
+                        None => f.page.clone(),
+                    };
+                    let mut assets = assets.borrow_mut();
+                    f.atlas = assets.require_texture(atlas);
+                    assets.fonts.load(&font, f);
+                }
+                Err(e) => log::error!("Could not read font {}: {}", font, e),
             }
         }
 
@@ -139,6 +292,9 @@ impl Assets {
 
     pub fn load(&mut self, gl: &WebGl2RenderingContext) {
         self.error_tex = Some(std::rc::Rc::new(Tex::new_error(gl)));
+
+        self.register_tex("::white", Tex::new_solid(gl, [255; 4]));
+        self.register_tex("::flat_normal", Tex::new_solid(gl, [128, 128, 255, 255]));
     }
 
     /// Import the buffer data referenced by a glTF document.
@@ -181,6 +337,11 @@ impl Assets {
         self.textures.require(tex)
     }
 
+    /// Fetch a BMFont file, along with the atlas it names
+    pub fn require_font(&mut self, font: String) -> usize {
+        self.fonts.require(font)
+    }
+
     pub fn require_material(&mut self, mat: String) -> usize {
         self.materials.require(mat)
     }
@@ -194,6 +355,10 @@ impl Assets {
 
         self.materials.assets[index] = Some(mat);
     }
+    /// Plain white non-metal, for primitives without a material and as the base of script materials
+    pub fn default_material(&self) -> &Mat {
+        &self.default_material
+    }
     pub fn get_material(&self, mat: usize) -> Option<&Mat> {
         match self.materials.assets.get(mat) {
             Some(m) => m.as_ref(),
@@ -207,58 +372,97 @@ impl Assets {
 
     pub fn get_tex(&self, tex_name: usize) -> std::rc::Rc<Tex> {
         if let Some(t) = self.textures.get(tex_name) {
-            return t.clone();
+            t.clone()
         } else {
             // Return the error texture
             //log::warn!("{} is not a loaded texture", tex_name);
-            return self.error_tex.clone().expect("Error texture not loaded!");
+            self.error_tex.clone().expect("Error texture not loaded!")
         }
     }
 
+    /// Whether a texture has finished loading, rather than `get_tex` returning the error texture
+    pub fn tex_loaded(&self, tex: usize) -> bool {
+        self.textures.get(tex).is_some()
+    }
+
+    pub fn get_font(&self, font: usize) -> Option<&Font> {
+        self.fonts.get(font)
+    }
+
     pub fn get_gltf(&self, gltf_name: usize) -> Option<&GltfMesh> {
         self.gltf.get(gltf_name)
     }
-    pub fn require_mesh_textures(&mut self) {
-        let mut uris = Vec::<(usize, (String, String))>::new();
 
-        for (path, gltf_name) in &self.gltf.asset_indexes {
-            let path_head = "/assets/textures/".to_owned();
-            //TODO: This is a bad way of loading materials
+    /// Create a material for every material in the loaded glTF files, requiring their textures
+    pub fn require_mesh_textures(&mut self) {
+        let mut mats = Vec::<(usize, PbrMaterial, [Option<String>; 5])>::new();
 
+        for gltf_name in self.gltf.asset_indexes.values() {
             if let Some(m) = self.get_gltf(*gltf_name) {
                 for mat in m.doc.materials() {
-                    if let Some(Source::Uri { uri, mime_type }) = mat
-                        .pbr_metallic_roughness()
-                        .base_color_texture()
-                        .map(|m| m.texture().source().source())
-                    {
-                        let mut u_col = path_head.clone();
-                        u_col.push_str(uri);
-
-                        if let Some(Source::Uri { uri, mime_type }) =
-                            mat.normal_texture().map(|m| m.texture().source().source())
-                        {
-                            let mut u_norm = path_head.clone();
-                            u_norm.push_str(uri);
-
-                            uris.push((mat.index().unwrap(), (u_col, u_norm)));
+                    let index = match mat.index() {
+                        Some(i) => i,
+                        None => continue,
+                    };
+                    let pbr = mat.pbr_metallic_roughness();
+
+                    let mut p = PbrMaterial::new(self.white_tex, self.flat_normal_tex);
+                    p.base_color_factor = pbr.base_color_factor();
+                    p.metallic_factor = pbr.metallic_factor();
+                    p.roughness_factor = pbr.roughness_factor();
+                    p.emissive_factor = mat.emissive_factor();
+                    p.alpha_mode = match mat.alpha_mode() {
+                        gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                        gltf::material::AlphaMode::Mask => {
+                            AlphaMode::Mask(mat.alpha_cutoff().unwrap_or(0.5))
                         }
+                        gltf::material::AlphaMode::Blend => AlphaMode::Blend,
                     };
+                    if let Some(o) = mat.occlusion_texture() {
+                        p.occlusion_strength = o.strength();
+                    }
+
+                    let uris = [
+                        pbr.base_color_texture()
+                            .and_then(|t| texture_uri(t.texture())),
+                        pbr.metallic_roughness_texture()
+                            .and_then(|t| texture_uri(t.texture())),
+                        mat.normal_texture().and_then(|t| texture_uri(t.texture())),
+                        mat.occlusion_texture()
+                            .and_then(|t| texture_uri(t.texture())),
+                        mat.emissive_texture()
+                            .and_then(|t| texture_uri(t.texture())),
+                    ];
+
+                    mats.push((index, p, uris));
                 }
             }
         }
         //TODO: This will break with more than one gltf with materials
-        for (id, (uri_col, uri_norm)) in uris {
-            let tex = self.require_texture(uri_col);
-            let normal = self.require_texture(uri_norm);
-
-            self.load_material(
-                id,
-                Mat::filled(vec![
-                    ("meshTexture".to_owned(), Uniform::Tex(tex)),
-                    ("meshNormal".to_owned(), Uniform::Tex(normal)),
-                ]),
-            );
+        for (id, mut mat, uris) in mats {
+            let [base_color, metallic_roughness, normal, occlusion, emissive] =
+                uris.map(|u| u.map(|u| self.require_texture(u)));
+
+            mat.base_color_texture = base_color.unwrap_or(mat.base_color_texture);
+            mat.metallic_roughness_texture =
+                metallic_roughness.unwrap_or(mat.metallic_roughness_texture);
+            mat.normal_texture = normal.unwrap_or(mat.normal_texture);
+            mat.occlusion_texture = occlusion.unwrap_or(mat.occlusion_texture);
+            mat.emissive_texture = emissive.unwrap_or(mat.emissive_texture);
+
+            self.load_material(id, mat.into());
+        }
+    }
+}
+
+/// Where to fetch a glTF texture from
+fn texture_uri(texture: Texture) -> Option<String> {
+    //TODO: Allow paths dependant on where to model is located
+    match texture.source().source() {
+        Source::Uri { uri, .. } => Some(format!("/assets/textures/{}", uri)),
+        Source::View { .. } => {
+            log::warn!("Textures embedded in glTF buffers are not supported");
+            None
         }
     }
 }
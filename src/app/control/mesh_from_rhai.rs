@@ -1,6 +1,9 @@
 use nalgebra::{ArrayStorage, Vector3};
 
-use crate::app::{store::Mesh, Assets};
+use crate::app::{
+    store::{animation::Animator, Mesh},
+    Assets,
+};
 
 use super::from_rhai::FromRhai;
 
@@ -45,15 +48,35 @@ impl FromRhai for Mesh {
             .get("update")
             .map(|f| f.clone().cast::<rhai::FnPtr>().fn_name().to_owned());
 
+        let mut animator = Animator::default();
+        if let Some(clip) = map.get("animation") {
+            let clip = clip.clone().into_string()?;
+            animator.play(assets.get_gltf(mesh), &clip, true, 1.0, 1.0, 0.0);
+        }
+        if let Some(weights) = map.get("morph_weights") {
+            let weights = weights.clone().into_array()?;
+            animator.morph_override = Some(
+                weights
+                    .iter()
+                    .map(super::to_f32)
+                    .collect::<Result<Vec<f32>, _>>()?,
+            );
+        }
+
         log::info!("Position: {:?}", pos);
 
         Ok(super::Mesh {
+            name: match map.get("name") {
+                Some(n) => Some(n.clone().into_string()?),
+                None => None,
+            },
             mesh,
             mat,
             scale: Vector3::from_array_storage(ArrayStorage([scale])),
             position: Vector3::from_array_storage(ArrayStorage([pos])),
             rotation: Vector3::from_array_storage(ArrayStorage([rot])),
             update,
+            animator,
         })
     }
 }
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use rhai::{Dynamic, Engine, ParseError, Scope, AST};
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, ParseError, Scope, AST};
 
 pub mod from_rhai;
 mod mesh_from_rhai;
@@ -10,9 +10,26 @@ use crate::app::render::material::mat::Uniform;
 use self::from_rhai::FromRhai;
 
 use super::{
-    render::material::mat::Mat,
-    store::{entity::Entity, Mesh},
-    Assets, LuaMsg, Store,
+    render::{material::mat::Mat, mesh::MAX_MORPH_TARGETS},
+    store::{
+        animation::{Animator, DEFAULT_FADE_SECONDS},
+        camera::{CameraMode, MouseLook, Projection, ProjectionKind},
+        camera_path::{CameraKey, CameraPath},
+        debug::{DebugDraw, DebugView, DEFAULT_DEBUG_COLOR},
+        entity::Entity,
+        fog::Fog,
+        input::{Binding, InputMap},
+        post::{
+            BloomSettings, GradingSettings, PostEffect, PostSettings, ToneMappingSettings,
+            VignetteSettings,
+        },
+        skybox::{Skybox, SkyboxSource},
+        sun::{ShadowSettings, Sun},
+        text::{Text, TextSpace},
+        touch::Gesture,
+        CameraPathRequest, CameraPathSource, Mesh,
+    },
+    Assets, LuaMsg, Msg, Store,
 };
 pub struct Control {
     engine: Engine,
@@ -20,6 +37,21 @@ pub struct Control {
     on_load: AST,
 }
 
+/// Like `bool_or`, but failing instead of panicking if the value is not a bool
+pub fn try_bool_or(e: &rhai::Map, s: &str, or: bool) -> Result<bool, &'static str> {
+    match e.get(s) {
+        Some(v) => v.as_bool(),
+        None => Ok(or),
+    }
+}
+/// Like `f32_or`, but failing instead of panicking if the value is not a number
+pub fn try_f32_or(e: &rhai::Map, s: &str, or: f32) -> Result<f32, &'static str> {
+    match e.get(s) {
+        Some(v) => to_f32(v),
+        None => Ok(or),
+    }
+}
+
 pub fn bool_or(e: &rhai::Map, s: &str, or: bool) -> bool {
     match e.get(s) {
         Some(v) => v.as_bool().unwrap(),
@@ -36,9 +68,9 @@ pub fn f32_or(e: &rhai::Map, s: &str, or: f32) -> f32 {
 pub fn to_f32(d: &Dynamic) -> Result<f32, &'static str> {
     match d.as_float() {
         Ok(f) => Ok(f as f32),
-        Err(e) => match d.as_int() {
+        Err(_) => match d.as_int() {
             Ok(i) => Ok(i as f32),
-            Err(e) => Err("Non-numeric integer type found"),
+            Err(_) => Err("Non-numeric integer type found"),
         },
     }
 }
@@ -52,6 +84,378 @@ pub fn to_vec3(d: &Dynamic) -> Result<[f32; 3], &'static str> {
     }
 }
 
+pub fn to_camera_path(e: &rhai::Map) -> Result<CameraPath, &'static str> {
+    let keys = e
+        .get("keys")
+        .ok_or("Camera path needs keys or a gltf")?
+        .clone()
+        .into_array()?;
+
+    let mut path = Vec::new();
+    for k in keys {
+        let k = k
+            .try_cast::<rhai::Map>()
+            .ok_or("Camera key must be a map")?;
+
+        path.push(CameraKey {
+            time: f32_or(&k, "time", 0.0),
+            position: to_vec3(k.get("position").ok_or("Camera key needs a position")?)?.into(),
+            target: match k.get("target") {
+                Some(t) => to_vec3(t)?.into(),
+                None => [0.0; 3].into(),
+            },
+        });
+    }
+
+    Ok(CameraPath::new(path, bool_or(e, "loop", false)))
+}
+
+/// A binding name, or an array of them
+pub fn to_bindings(d: &Dynamic) -> Result<Vec<Binding>, &'static str> {
+    let names = match d.clone().into_array() {
+        Ok(a) => a,
+        Err(_) => vec![d.clone()],
+    };
+
+    names
+        .into_iter()
+        .map(|n| Binding::from_name(&n.into_string()?).ok_or("Unknown input binding"))
+        .collect()
+}
+
+pub fn to_projection(e: &rhai::Map) -> Result<Projection, &'static str> {
+    let default = Projection::default();
+
+    let kind = match e.get("projection") {
+        Some(p) => p.clone().into_string()?,
+        None => "perspective".to_owned(),
+    };
+
+    let kind = match kind.as_str() {
+        "perspective" => ProjectionKind::Perspective {
+            fovy: f32_or(e, "fov", 57.3).to_radians(),
+        },
+        "orthographic" => ProjectionKind::Orthographic {
+            height: f32_or(e, "height", 20.0),
+        },
+        _ => return Err("Unknown projection, expected perspective or orthographic"),
+    };
+
+    Ok(Projection {
+        kind,
+        near: f32_or(e, "near", default.near),
+        far: f32_or(e, "far", default.far),
+    })
+}
+
+pub fn to_camera_mode(e: &rhai::Map) -> Result<CameraMode, &'static str> {
+    match e.get("mode") {
+        None => Ok(CameraMode::Orbit),
+        Some(m) => match m.clone().into_string()?.as_str() {
+            "orbit" => Ok(CameraMode::Orbit),
+            "fps" => Ok(CameraMode::Fps),
+            _ => Err("Unknown camera mode, expected orbit or fps"),
+        },
+    }
+}
+
+pub fn to_mouse_look(e: &rhai::Map) -> MouseLook {
+    let default = MouseLook::default();
+
+    MouseLook {
+        sensitivity: f32_or(e, "sensitivity", default.sensitivity),
+        invert_y: bool_or(e, "invert_y", default.invert_y),
+        pointer_lock: bool_or(e, "pointer_lock", default.pointer_lock),
+    }
+}
+
+pub fn to_sun(e: &rhai::Map) -> Result<Sun, &'static str> {
+    let default = Sun::default();
+
+    let direction = match e.get("direction") {
+        Some(d) => nalgebra::Vector3::from(to_vec3(d)?),
+        None => default.direction,
+    };
+    if direction.norm() == 0.0 {
+        return Err("Sun direction cannot be zero");
+    }
+
+    let resolution = match e.get("shadow_resolution") {
+        Some(r) => r.as_int()?,
+        None => default.shadows.resolution,
+    };
+    if resolution <= 0 {
+        return Err("Shadow resolution must be positive");
+    }
+
+    Ok(Sun {
+        direction: direction.normalize(),
+        shadows: ShadowSettings {
+            enabled: bool_or(e, "shadows", default.shadows.enabled),
+            resolution,
+            bias: f32_or(e, "shadow_bias", default.shadows.bias),
+            extent: f32_or(e, "shadow_extent", default.shadows.extent),
+        },
+    })
+}
+
+pub fn to_fog(e: &rhai::Map) -> Result<Fog, &'static str> {
+    let default = Fog::default();
+
+    let color = match e.get("color") {
+        Some(c) => to_vec3(c)?,
+        None => default.color,
+    };
+
+    Ok(Fog {
+        enabled: bool_or(e, "enabled", true),
+        density: f32_or(e, "density", default.density),
+        height_falloff: f32_or(e, "height_falloff", default.height_falloff),
+        height: f32_or(e, "height", default.height),
+        color,
+    })
+}
+
+/// Post effects, each enabled by a key of its name with its settings alongside
+pub fn to_post(e: &rhai::Map) -> Result<PostSettings, &'static str> {
+    let default = PostSettings::default();
+
+    let tint = match e.get("tint") {
+        Some(t) => to_vec3(t)?,
+        None => default.grading.tint,
+    };
+
+    let msaa = match e.get("msaa") {
+        Some(m) => m.as_int()?,
+        None => default.msaa,
+    };
+    if msaa <= 0 {
+        return Err("MSAA samples must be positive");
+    }
+
+    Ok(PostSettings {
+        msaa,
+        hdr: bool_or(e, "hdr", default.hdr),
+        bloom: BloomSettings {
+            enabled: bool_or(e, "bloom", default.bloom.enabled),
+            threshold: f32_or(e, "bloom_threshold", default.bloom.threshold),
+            strength: f32_or(e, "bloom_strength", default.bloom.strength),
+        },
+        tone_mapping: ToneMappingSettings {
+            enabled: bool_or(e, "tone_mapping", default.tone_mapping.enabled),
+            exposure: f32_or(e, "exposure", default.tone_mapping.exposure),
+        },
+        grading: GradingSettings {
+            enabled: bool_or(e, "colour_grading", default.grading.enabled),
+            saturation: f32_or(e, "saturation", default.grading.saturation),
+            contrast: f32_or(e, "contrast", default.grading.contrast),
+            tint,
+        },
+        fxaa: bool_or(e, "fxaa", default.fxaa),
+        vignette: VignetteSettings {
+            enabled: bool_or(e, "vignette", default.vignette.enabled),
+            strength: f32_or(e, "vignette_strength", default.vignette.strength),
+            radius: f32_or(e, "vignette_radius", default.vignette.radius),
+        },
+    })
+}
+
+/// A skybox from either six `faces`, ordered +x -x +y -y +z -z, or one `equirect` panorama
+pub fn to_skybox(e: &rhai::Map, assets: &mut Assets) -> Result<Skybox, &'static str> {
+    let source = match (e.get("faces"), e.get("equirect")) {
+        (Some(faces), _) => {
+            let faces = faces.clone().into_array()?;
+            if faces.len() != 6 {
+                return Err("Skybox faces must list 6 images, +x -x +y -y +z -z");
+            }
+
+            let mut textures = [0; 6];
+            for (t, f) in textures.iter_mut().zip(faces) {
+                *t = assets.require_texture(f.into_string()?);
+            }
+            SkyboxSource::Faces(textures)
+        }
+        (None, Some(equirect)) => {
+            SkyboxSource::Equirect(assets.require_texture(equirect.clone().into_string()?))
+        }
+        (None, None) => return Err("Skybox needs either faces or an equirect image"),
+    };
+
+    let resolution = match e.get("resolution") {
+        Some(r) => r.as_int()?,
+        None => 512,
+    };
+    if resolution <= 0 {
+        return Err("Skybox resolution must be positive");
+    }
+
+    Ok(Skybox {
+        source,
+        resolution,
+        strength: f32_or(e, "strength", 1.0),
+    })
+}
+
+/// Text in a signed distance field font, on the screen if given a `screen` position in
+/// pixels, otherwise in the world at `position`, relative to the entity named by `follow`
+pub fn to_text(e: &rhai::Map, assets: &mut Assets) -> Result<Text, &'static str> {
+    let font = e
+        .get("font")
+        .ok_or("Text needs a font")?
+        .clone()
+        .into_string()?;
+
+    let numbers = |key: &str| -> Result<Option<Vec<f32>>, &'static str> {
+        match e.get(key) {
+            Some(v) => Ok(Some(
+                v.clone()
+                    .into_array()?
+                    .iter()
+                    .map(to_f32)
+                    .collect::<Result<_, _>>()?,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    let (space, size, anchor) = match numbers("screen")?.as_deref() {
+        Some(&[x, y]) => (TextSpace::Screen([x, y]), 32.0, [0.0, 0.0]),
+        Some(_) => return Err("Text screen position must be 2 numbers"),
+        // Labels sit centred above their position
+        None => (
+            TextSpace::World {
+                position: match e.get("position") {
+                    Some(p) => nalgebra::Vector3::from(to_vec3(p)?),
+                    None => nalgebra::Vector3::zeros(),
+                },
+                follow: match e.get("follow") {
+                    Some(f) => Some(f.clone().into_string()?),
+                    None => None,
+                },
+            },
+            0.5,
+            [0.5, 1.0],
+        ),
+    };
+
+    let color = match numbers("color")?.as_deref() {
+        Some(&[r, g, b]) => [r, g, b, 1.0],
+        Some(&[r, g, b, a]) => [r, g, b, a],
+        Some(_) => return Err("Text color must be 3 or 4 numbers"),
+        None => [1.0; 4],
+    };
+    let anchor = match numbers("anchor")?.as_deref() {
+        Some(&[x, y]) => [x, y],
+        Some(_) => return Err("Text anchor must be 2 numbers"),
+        None => anchor,
+    };
+
+    Ok(Text {
+        name: match e.get("name") {
+            Some(n) => Some(n.clone().into_string()?),
+            None => None,
+        },
+        text: match e.get("text") {
+            Some(t) => t.clone().into_string()?,
+            None => String::new(),
+        },
+        font: assets.require_font(font),
+        size: f32_or(e, "size", size),
+        color,
+        anchor,
+        space,
+    })
+}
+
+/// Pass a camera path message to the store, warning instead if it is in use
+fn send_msg(store: &RefCell<Store>, msg: Msg) {
+    match store.try_borrow_mut() {
+        Ok(mut s) => s.msg(&msg),
+        Err(_) => log::warn!("Could not control the camera path while the store is in use"),
+    }
+}
+
+/// Start a clip on the entity with this name, checking its glTF has the clip if it has loaded
+fn play_clip(
+    store: &RefCell<Store>,
+    assets: &RefCell<Assets>,
+    entity: &str,
+    clip: &str,
+    options: &rhai::Map,
+) {
+    let (looping, speed, weight, fade) = match (
+        try_bool_or(options, "loop", true),
+        try_f32_or(options, "speed", 1.0),
+        try_f32_or(options, "weight", 1.0),
+        try_f32_or(options, "fade", DEFAULT_FADE_SECONDS),
+    ) {
+        (Ok(l), Ok(s), Ok(w), Ok(f)) => (l, s, w, f),
+        _ => {
+            log::warn!(
+                "Could not play {}: loop must be a bool and speed, weight and fade numbers",
+                clip
+            );
+            return;
+        }
+    };
+
+    let assets = assets.try_borrow().ok();
+    let gltf = match (store.try_borrow(), &assets) {
+        (Ok(s), Some(assets)) => s
+            .state
+            .find_entity(entity)
+            .and_then(|e| e.try_borrow().ok().and_then(|e| e.gltf(assets))),
+        _ => None,
+    };
+
+    with_animator(store, entity, |a| {
+        a.play(gltf, clip, looping, speed, weight, fade)
+    });
+}
+
+/// Run `f` on the animator of the entity with this name, warning if there is none
+fn with_animator(store: &RefCell<Store>, entity: &str, f: impl FnOnce(&mut Animator)) {
+    let e = match store.try_borrow() {
+        Ok(s) => s.state.find_entity(entity),
+        Err(_) => {
+            log::warn!("Could not animate {} while the store is in use", entity);
+            return;
+        }
+    };
+
+    match e.as_ref().map(|e| e.try_borrow_mut()) {
+        Some(Ok(mut e)) => match e.animator_mut() {
+            Some(a) => f(a),
+            None => log::warn!("Entity {} cannot be animated", entity),
+        },
+        Some(Err(_)) => log::warn!("Could not animate {} while it is in use", entity),
+        None => log::warn!("No entity named {}", entity),
+    };
+}
+
+/// Height of debug labels drawn from scripts, in world units
+static DEBUG_TEXT_SIZE: f32 = 0.25;
+
+/// Add to this frame's debug shapes, warning if an argument is not a number or vector
+/// or the store is in use
+fn with_debug_draw(
+    store: &RefCell<Store>,
+    f: impl FnOnce(&mut DebugDraw) -> Result<(), &'static str>,
+) {
+    match store.try_borrow_mut() {
+        Ok(mut s) => {
+            if let Err(e) = f(&mut s.state.debug_draw) {
+                log::warn!("Could not draw debug shape: {}", e);
+            }
+        }
+        Err(_) => log::warn!("Could not draw debug shapes while the store is in use"),
+    }
+}
+
+fn to_vector3(d: &Dynamic) -> Result<nalgebra::Vector3<f32>, &'static str> {
+    to_vec3(d).map(nalgebra::Vector3::from)
+}
+
 impl Control {
     pub fn lua_msg(
         &mut self,
@@ -62,10 +466,13 @@ impl Control {
         match msg {
             LuaMsg::Load(str) => {
                 log::info!("Compiling...");
-                match self.compile_on_load(str) {
-                    Err(e) => return Err(format!("{:?}", e)),
-                    Ok(()) => (),
-                };
+                if let Err(e) = self.compile_on_load(str) {
+                    return Err(format!("{:?}", e));
+                }
+
+                // Bindings belong to the script that made them, so start the new one from the
+                // defaults before its body gets the chance to bind its own
+                state.borrow_mut().state.input.map = InputMap::default();
 
                 log::info!("Compiled, Running...");
                 let data = self.run_on_load().map_err(|e| e.to_string())?;
@@ -75,7 +482,7 @@ impl Control {
         }
     }
 
-    pub fn new(assets: Rc<RefCell<Assets>>) -> Self {
+    pub fn new(assets: Rc<RefCell<Assets>>, store: Rc<RefCell<Store>>) -> Self {
         let mut engine = Engine::new();
         engine.on_print(|x| log::info!("{}", x));
 
@@ -116,8 +523,287 @@ impl Control {
                 },
             );
 
+        let a = assets.clone();
         engine.register_fn("tex", move |name: String| {
-            Uniform::Tex(assets.borrow_mut().require_texture(name))
+            Uniform::Tex(a.borrow_mut().require_texture(name))
+        });
+
+        // Input actions. The store may already be borrowed if a script runs mid-update,
+        // in which case actions read as released and bindings are dropped with a warning
+        let s = store.clone();
+        engine.register_fn("action_pressed", move |action: &str| match s.try_borrow() {
+            Ok(s) => s.state.input.pressed(action),
+            Err(_) => false,
+        });
+        let s = store.clone();
+        engine.register_fn("action_value", move |action: &str| match s.try_borrow() {
+            Ok(s) => s.state.input.value(action) as rhai::FLOAT,
+            Err(_) => 0.0,
+        });
+        let s = store.clone();
+        engine.register_fn("bind_action", move |action: &str, binding: &str| {
+            match (Binding::from_name(binding), s.try_borrow_mut()) {
+                (Some(b), Ok(mut s)) => s.state.input.map.bind(action, b),
+                (None, _) => log::warn!("Unknown input {}", binding),
+                (_, Err(_)) => log::warn!("Could not bind {} while the store is in use", action),
+            }
+        });
+
+        // Animation clips, on mesh entities given a `name`
+        let s = store.clone();
+        let a = assets.clone();
+        engine.register_fn("play_animation", move |entity: &str, clip: &str| {
+            play_clip(&s, &a, entity, clip, &rhai::Map::new());
+        });
+        let s = store.clone();
+        let a = assets;
+        engine.register_fn(
+            "play_animation",
+            move |entity: &str, clip: &str, options: rhai::Map| {
+                play_clip(&s, &a, entity, clip, &options);
+            },
+        );
+        let s = store.clone();
+        engine.register_fn("stop_animation", move |entity: &str, clip: &str| {
+            with_animator(&s, entity, |a| a.stop(clip, DEFAULT_FADE_SECONDS));
+        });
+        let s = store.clone();
+        engine.register_fn(
+            "stop_animation",
+            move |entity: &str, clip: &str, fade: rhai::FLOAT| {
+                with_animator(&s, entity, |a| a.stop(clip, fade as f32));
+            },
+        );
+        let s = store.clone();
+        engine.register_fn(
+            "set_animation_weight",
+            move |entity: &str, clip: &str, weight: rhai::FLOAT| {
+                with_animator(&s, entity, |a| {
+                    a.set_weight(clip, weight as f32, DEFAULT_FADE_SECONDS)
+                });
+            },
+        );
+
+        // Morph target weights, overriding any from animation until cleared
+        let s = store.clone();
+        engine.register_fn(
+            "set_morph_weights",
+            move |entity: &str, weights: rhai::Array| match weights
+                .iter()
+                .map(to_f32)
+                .collect::<Result<Vec<f32>, _>>()
+            {
+                Ok(w) => with_animator(&s, entity, |a| a.morph_override = Some(w)),
+                Err(e) => log::warn!("{}", e),
+            },
+        );
+        let s = store.clone();
+        engine.register_fn(
+            "set_morph_weight",
+            move |entity: &str, target: rhai::INT, weight: rhai::FLOAT| {
+                if target < 0 || target as usize >= MAX_MORPH_TARGETS {
+                    log::warn!(
+                        "Morph target {} is out of range, meshes have at most {}",
+                        target,
+                        MAX_MORPH_TARGETS
+                    );
+                    return;
+                }
+                with_animator(&s, entity, |a| {
+                    let w = a.morph_override.get_or_insert_with(Vec::new);
+                    if w.len() <= target as usize {
+                        w.resize(target as usize + 1, 0.0);
+                    }
+                    w[target as usize] = weight as f32;
+                });
+            },
+        );
+        let s = store.clone();
+        engine.register_fn("clear_morph_weights", move |entity: &str| {
+            with_animator(&s, entity, |a| a.morph_override = None);
+        });
+
+        // Camera paths defined by the loaded script, matching the page's controls
+        let s = store.clone();
+        engine.register_fn("play_camera_path", move |name: &str| {
+            send_msg(&s, Msg::PlayCameraPath(name.to_string()));
+        });
+        let s = store.clone();
+        engine.register_fn("pause_camera_path", move || {
+            send_msg(&s, Msg::PauseCameraPath);
+        });
+        let s = store.clone();
+        engine.register_fn("resume_camera_path", move || {
+            send_msg(&s, Msg::ResumeCameraPath);
+        });
+        let s = store.clone();
+        engine.register_fn("scrub_camera_path", move |seconds: rhai::FLOAT| {
+            send_msg(&s, Msg::ScrubCameraPath(seconds as f32));
+        });
+        let s = store.clone();
+        engine.register_fn("stop_camera_path", move || {
+            send_msg(&s, Msg::StopCameraPath);
+        });
+
+        // Debug views, off until a script or the page turns them on
+        let s = store.clone();
+        engine.register_fn("set_debug_view", move |view: &str, enabled: bool| {
+            match (DebugView::from_name(view), s.try_borrow_mut()) {
+                (Some(v), Ok(mut s)) => s.state.debug.set_enabled(v, enabled),
+                (None, _) => log::warn!("Unknown debug view {}", view),
+                (_, Err(_)) => log::warn!("Could not set {} while the store is in use", view),
+            }
+        });
+
+        // Debug shapes, drawn for the frame they are added in, so called from `on_update`.
+        // Positions and colours are arrays of 3 numbers, colours defaulting to red.
+        let s = store.clone();
+        engine.register_fn("debug_line", move |from: Dynamic, to: Dynamic| {
+            with_debug_draw(&s, |d| {
+                d.line(to_vector3(&from)?, to_vector3(&to)?, DEFAULT_DEBUG_COLOR);
+                Ok(())
+            });
+        });
+        let s = store.clone();
+        engine.register_fn(
+            "debug_line",
+            move |from: Dynamic, to: Dynamic, color: Dynamic| {
+                with_debug_draw(&s, |d| {
+                    d.line(to_vector3(&from)?, to_vector3(&to)?, to_vec3(&color)?);
+                    Ok(())
+                });
+            },
+        );
+        let s = store.clone();
+        engine.register_fn("debug_box", move |centre: Dynamic, extents: Dynamic| {
+            with_debug_draw(&s, |d| {
+                d.aabb(
+                    to_vector3(&centre)?,
+                    to_vector3(&extents)?,
+                    DEFAULT_DEBUG_COLOR,
+                );
+                Ok(())
+            });
+        });
+        let s = store.clone();
+        engine.register_fn(
+            "debug_box",
+            move |centre: Dynamic, extents: Dynamic, color: Dynamic| {
+                with_debug_draw(&s, |d| {
+                    d.aabb(
+                        to_vector3(&centre)?,
+                        to_vector3(&extents)?,
+                        to_vec3(&color)?,
+                    );
+                    Ok(())
+                });
+            },
+        );
+        let s = store.clone();
+        engine.register_fn("debug_sphere", move |centre: Dynamic, radius: Dynamic| {
+            with_debug_draw(&s, |d| {
+                d.sphere(to_vector3(&centre)?, to_f32(&radius)?, DEFAULT_DEBUG_COLOR);
+                Ok(())
+            });
+        });
+        let s = store.clone();
+        engine.register_fn(
+            "debug_sphere",
+            move |centre: Dynamic, radius: Dynamic, color: Dynamic| {
+                with_debug_draw(&s, |d| {
+                    d.sphere(to_vector3(&centre)?, to_f32(&radius)?, to_vec3(&color)?);
+                    Ok(())
+                });
+            },
+        );
+        let s = store.clone();
+        engine.register_fn("debug_axes", move |origin: Dynamic| {
+            with_debug_draw(&s, |d| {
+                d.axes(to_vector3(&origin)?, 1.0);
+                Ok(())
+            });
+        });
+        let s = store.clone();
+        engine.register_fn("debug_axes", move |origin: Dynamic, size: Dynamic| {
+            with_debug_draw(&s, |d| {
+                d.axes(to_vector3(&origin)?, to_f32(&size)?);
+                Ok(())
+            });
+        });
+        let s = store.clone();
+        engine.register_fn("debug_text", move |position: Dynamic, text: &str| {
+            with_debug_draw(&s, |d| {
+                d.text(
+                    to_vector3(&position)?,
+                    text,
+                    DEBUG_TEXT_SIZE,
+                    DEFAULT_DEBUG_COLOR,
+                );
+                Ok(())
+            });
+        });
+        let s = store.clone();
+        engine.register_fn(
+            "debug_text",
+            move |position: Dynamic, text: &str, color: Dynamic| {
+                with_debug_draw(&s, |d| {
+                    d.text(
+                        to_vector3(&position)?,
+                        text,
+                        DEBUG_TEXT_SIZE,
+                        to_vec3(&color)?,
+                    );
+                    Ok(())
+                });
+            },
+        );
+
+        // Text entities given a `name`
+        let s = store.clone();
+        engine.register_fn("set_text", move |entity: &str, text: &str| {
+            let e = match s.try_borrow() {
+                Ok(s) => s.state.find_entity(entity),
+                Err(_) => {
+                    log::warn!(
+                        "Could not set the text of {} while the store is in use",
+                        entity
+                    );
+                    return;
+                }
+            };
+
+            match e.as_ref().map(|e| e.try_borrow_mut()) {
+                Some(Ok(mut e)) => match e.text_mut() {
+                    Some(t) => t.text = text.to_string(),
+                    None => log::warn!("Entity {} is not text", entity),
+                },
+                Some(Err(_)) => {
+                    log::warn!("Could not set the text of {} while it is in use", entity)
+                }
+                None => log::warn!("No entity named {}", entity),
+            };
+        });
+
+        // Post effects, by the names used in a `post` entity
+        let s = store.clone();
+        engine.register_fn(
+            "set_post_effect",
+            move |effect: &str, enabled: bool| match (
+                PostEffect::from_name(effect),
+                s.try_borrow_mut(),
+            ) {
+                (Some(e), Ok(mut s)) => s.state.post.set_enabled(e, enabled),
+                (None, _) => log::warn!("Unknown post effect {}", effect),
+                (_, Err(_)) => log::warn!("Could not set {} while the store is in use", effect),
+            },
+        );
+
+        let s = store;
+        engine.register_fn("unbind_action", move |action: &str| {
+            match s.try_borrow_mut() {
+                Ok(mut s) => s.state.input.map.unbind(action),
+                Err(_) => log::warn!("Could not unbind {} while the store is in use", action),
+            }
         });
 
         Control {
@@ -141,6 +827,7 @@ impl Control {
             .eval_ast_with_scope(&mut self.scope, &self.on_load)
     }
 
+    #[allow(dead_code)]
     pub fn run_func(&mut self, func: &str, entity: Rc<RefCell<dyn Entity>>) -> Dynamic {
         // ensure entity is borrowable, before we fail within the function
         entity.borrow_mut();
@@ -150,6 +837,73 @@ impl Control {
             .unwrap()
     }
 
+    /// Call a function in the loaded script if it defines one with a matching name and arity,
+    /// without re-running the script body
+    pub fn call_handler(&mut self, func: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        let mut arg_values = Vec::new();
+        args.parse(&mut arg_values);
+
+        if !self
+            .on_load
+            .iter_functions()
+            .any(|f| f.name == func && f.params.len() == arg_values.len())
+        {
+            return None;
+        }
+
+        match self.engine.call_fn_with_options(
+            CallFnOptions::new().eval_ast(false),
+            &mut self.scope,
+            &self.on_load,
+            func,
+            arg_values,
+        ) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                log::error!("Error in {}: {}", func, e);
+                None
+            }
+        }
+    }
+
+    /// Call the script's `on_update(dt)` function, with `dt` in milliseconds
+    pub fn on_update(&mut self, dt: f32) {
+        self.call_handler("on_update", (dt as rhai::FLOAT,));
+    }
+
+    /// Pass a gesture to the script's `on_gesture(gesture)` function
+    pub fn on_gesture(&mut self, gesture: &Gesture) {
+        let mut map = rhai::Map::new();
+        let mut set = |k: &str, v: Dynamic| {
+            map.insert(k.into(), v);
+        };
+        let float = |f: f32| Dynamic::from_float(f as rhai::FLOAT);
+
+        match *gesture {
+            Gesture::Pinch(zoom) => {
+                set("type", "pinch".into());
+                set("zoom", float(zoom));
+            }
+            Gesture::Pan(dx, dy) => {
+                set("type", "pan".into());
+                set("dx", float(dx));
+                set("dy", float(dy));
+            }
+            Gesture::DoubleTap(x, y) => {
+                set("type", "double_tap".into());
+                set("x", Dynamic::from_int(x));
+                set("y", Dynamic::from_int(y));
+            }
+            Gesture::LongPress(x, y) => {
+                set("type", "long_press".into());
+                set("x", Dynamic::from_int(x));
+                set("y", Dynamic::from_int(y));
+            }
+        }
+
+        self.call_handler("on_gesture", (map,));
+    }
+
     pub fn load(
         &mut self,
         state: Rc<RefCell<Store>>,
@@ -160,9 +914,18 @@ impl Control {
 
         //web_sys::console::log_1(&format!("{:?}", data).into());
 
-        let default_pos = [0f32, 0f32, 0f32];
-
         state.borrow_mut().state.entities.clear();
+        state.borrow_mut().state.clear_camera_paths();
+        {
+            let mut store = state.borrow_mut();
+            let camera = store.state.camera_mut();
+            camera.set_projection(Projection::default());
+            camera.set_mode(CameraMode::Orbit);
+            camera.mouse_look = MouseLook::default();
+            store.state.sun = Sun::default();
+            store.state.fog = Fog::default();
+            store.state.post = PostSettings::default();
+        }
 
         for dyn_entity in data {
             let entity = dyn_entity.cast::<rhai::Map>();
@@ -201,6 +964,75 @@ impl Control {
                         }));
                     state.borrow_mut().state.entities.push(e)
                 }
+                "camera" => {
+                    let projection = to_projection(&entity)?;
+                    let mode = to_camera_mode(&entity)?;
+
+                    let mut store = state.borrow_mut();
+                    let camera = store.state.camera_mut();
+                    camera.set_projection(projection);
+                    camera.set_mode(mode);
+                    camera.mouse_look = to_mouse_look(&entity);
+                }
+                "sun" => {
+                    state.borrow_mut().state.sun = to_sun(&entity)?;
+                }
+                "fog" => {
+                    state.borrow_mut().state.fog = to_fog(&entity)?;
+                }
+                "post" => {
+                    state.borrow_mut().state.post = to_post(&entity)?;
+                }
+                "text" => {
+                    let text = to_text(&entity, &mut assets.borrow_mut())?;
+
+                    let e: Rc<RefCell<dyn Entity>> = Rc::new(RefCell::new(text));
+                    state.borrow_mut().state.entities.push(e)
+                }
+                "skybox" => {
+                    let skybox = to_skybox(&entity, &mut assets.borrow_mut())?;
+
+                    let e: Rc<RefCell<dyn Entity>> = Rc::new(RefCell::new(skybox));
+                    state.borrow_mut().state.entities.push(e)
+                }
+                "bindings" => {
+                    // Every other key names an action, replacing its default bindings
+                    let mut store = state.borrow_mut();
+                    for (action, bindings) in entity.iter().filter(|(k, _)| k.as_str() != "type") {
+                        store
+                            .state
+                            .input
+                            .map
+                            .set_bindings(action, to_bindings(bindings)?);
+                    }
+                }
+                "camera_path" => {
+                    let name = entity["name"].clone().into_string()?;
+
+                    let source = match entity.get("gltf") {
+                        Some(gltf) => CameraPathSource::Gltf {
+                            gltf: assets
+                                .borrow_mut()
+                                .require_gltf(gltf.clone().into_string()?),
+                            animation: match entity.get("animation") {
+                                Some(a) => Some(a.clone().into_string()?),
+                                None => None,
+                            },
+                            looping: bool_or(&entity, "loop", false),
+                        },
+                        None => CameraPathSource::Keys(to_camera_path(&entity)?),
+                    };
+
+                    state
+                        .borrow_mut()
+                        .state
+                        .camera_path_requests
+                        .push(CameraPathRequest {
+                            name,
+                            source,
+                            autoplay: bool_or(&entity, "autoplay", false),
+                        });
+                }
                 _ => return Err("Unknown Entity Type"),
             };
             //log::info!("{:?}", e);
@@ -0,0 +1,94 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Transform all 8 corners and fit a new box around them, so rotated boxes stay conservative
+    pub fn transform(&self, mat: &Matrix4<f32>) -> Aabb {
+        let mut min = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Point3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        for c in self.corners() {
+            let p = mat.transform_point(&c);
+            min = min.inf(&p);
+            max = max.sup(&p);
+        }
+
+        Aabb { min, max }
+    }
+
+    /// Smallest box holding both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+}
+
+/// Six clip planes of a camera, each stored as (normal, distance) with the normal facing inwards
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from a combined projection * view matrix (Gribb & Hartmann)
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Frustum {
+        let m = view_projection;
+        let row = |i: usize| -> Vector4<f32> { m.row(i).transpose() };
+
+        let planes = [
+            row(3) + row(0), // Left
+            row(3) - row(0), // Right
+            row(3) + row(1), // Bottom
+            row(3) - row(1), // Top
+            row(3) + row(2), // Near
+            row(3) - row(2), // Far
+        ];
+
+        Frustum {
+            planes: planes.map(|p| p / p.xyz().norm()),
+        }
+    }
+
+    /// Conservative test - may return true for boxes just outside the corners of the frustum
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        for p in &self.planes {
+            // The corner furthest along the plane normal
+            let positive = Vector3::new(
+                if p.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if p.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if p.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+
+            if p.xyz().dot(&positive) + p.w < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 mod control;
+pub mod frustum;
 pub mod ray;
 pub mod render;
 pub use self::control::*;
@@ -23,10 +24,11 @@ impl App {
     /// Create a new instance of our WebGL Water application
     pub fn new() -> App {
         let assets = Rc::new(RefCell::new(Assets::new()));
+        let store = Rc::new(RefCell::new(Store::new()));
         App {
-            control: Rc::new(RefCell::new(Control::new(assets.clone()))),
+            control: Rc::new(RefCell::new(Control::new(assets.clone(), store.clone()))),
             assets,
-            store: Rc::new(RefCell::new(Store::new())),
+            store,
         }
     }
 }
@@ -1,12 +1,17 @@
 use nalgebra::{Matrix4, Point3, Vector3};
 
 /// Half line ray representation
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
     origin: Point3<f32>,
     direction: Vector3<f32>,
 }
+#[allow(dead_code)]
 impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Ray {
+        Ray { origin, direction }
+    }
     ///create a new ray firing in the z direction
     pub fn forward() -> Ray {
         Ray {
@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+
+use nalgebra::{Matrix4, Vector3};
+use web_sys::WebGl2RenderingContext as GL;
+use web_sys::WebGlBuffer;
+
+use super::rgl::shader::ShaderKind;
+use super::rgl::uniform_buffer::UniformBuffer;
+use super::rgl::vao::Vao;
+use super::{stream_data, CameraData, WebRenderer};
+use crate::app::store::debug::{DebugDraw, DebugText, DebugVertex};
+use crate::app::State;
+
+/// Floats in each `DebugVertex`
+static VERTEX_FLOATS: i32 = 6;
+
+/// The vertex array debug lines are streamed through, refilled every frame they are drawn
+pub struct DebugLines {
+    vao: Vao,
+    buffer: WebGlBuffer,
+    /// Shapes the renderer adds itself while drawing a frame, such as bounding boxes
+    pub queued: RefCell<DebugDraw>,
+}
+
+impl DebugLines {
+    pub fn new(gl: &GL) -> DebugLines {
+        let vao = Vao::new(gl);
+        vao.bind(gl);
+
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer));
+
+        // Position then colour, matching the locations in `wireframe-vertex.glsl`
+        let stride = VERTEX_FLOATS * 4;
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_with_i32(0, 3, GL::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(1);
+        gl.vertex_attrib_pointer_with_i32(1, 3, GL::FLOAT, false, stride, 12);
+
+        gl.bind_vertex_array(None);
+
+        DebugLines {
+            vao,
+            buffer,
+            queued: RefCell::new(DebugDraw::default()),
+        }
+    }
+}
+
+impl WebRenderer {
+    /// Draw the state's debug shapes and those queued while drawing this frame in one call,
+    /// with labels facing the camera `view` looks from
+    pub(in crate::app::render) fn draw_debug(
+        &self,
+        gl: &GL,
+        camera: &UniformBuffer<CameraData>,
+        view: &Matrix4<f32>,
+        state: &State,
+    ) {
+        let mut queued = self.debug_lines.queued.borrow_mut();
+        let draw = &state.debug_draw;
+        if draw.is_empty() && queued.is_empty() {
+            return;
+        }
+
+        let mut vertices = Vec::with_capacity(draw.vertices.len() + queued.vertices.len());
+        vertices.extend_from_slice(&draw.vertices);
+        vertices.extend_from_slice(&queued.vertices);
+
+        // The rows of the view's rotation are the camera's axes in world space
+        let right = Vector3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+        let up = Vector3::new(view[(1, 0)], view[(1, 1)], view[(1, 2)]);
+        for text in draw.texts.iter().chain(&queued.texts) {
+            text_lines(text, right, up, &mut vertices);
+        }
+        queued.clear();
+
+        self.debug_lines.vao.bind(gl);
+        stream_data(gl, &self.debug_lines.buffer, &vertices);
+
+        let shader = self.shader_sys.get_shader(&ShaderKind::WireFrame).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::WireFrame);
+        let block_index = shader.get_uniform_block_index(gl, "Camera");
+        camera.bind_base(gl, shader, block_index, 2);
+
+        gl.draw_arrays(GL::LINES, 0, vertices.len() as i32);
+        self.count_draw_call();
+    }
+}
+
+/// Turn a label into lines, each character a glyph from `glyph_strokes` laid out along `right`
+fn text_lines(text: &DebugText, right: Vector3<f32>, up: Vector3<f32>, out: &mut Vec<DebugVertex>) {
+    // Glyphs are half as wide as they are tall, with a quarter of their height between them
+    let advance = text.size * 0.75;
+    let width = text.text.chars().count() as f32 * advance - text.size * 0.25;
+    let start = text.position - right * (width * 0.5) - up * (text.size * 0.5);
+
+    // Grid points are numbered like a keypad, 1 at the bottom left and 9 at the top right
+    let point = |origin: Vector3<f32>, p: u8| {
+        let i = (p - b'1') as f32;
+        origin + right * ((i % 3.0) * text.size * 0.25) + up * ((i / 3.0).floor() * text.size * 0.5)
+    };
+
+    for (i, c) in text.text.chars().enumerate() {
+        let origin = start + right * (i as f32 * advance);
+
+        for stroke in glyph_strokes(c).split_whitespace() {
+            let stroke = stroke.as_bytes();
+            for p in [stroke[0], stroke[1]] {
+                out.push(DebugVertex {
+                    position: point(origin, p).into(),
+                    color: text.color,
+                });
+            }
+        }
+    }
+}
+
+/// Strokes between the points of a 3 by 3 grid, numbered like a keypad. Lower case letters
+/// are drawn as upper case and anything unknown is left blank.
+fn glyph_strokes(c: char) -> &'static str {
+    match c.to_ascii_uppercase() {
+        '0' => "17 79 93 31 19",
+        '1' => "28 78 13",
+        '2' => "79 96 64 41 13",
+        '3' => "79 93 31 46",
+        '4' => "74 46 93",
+        '5' => "97 74 46 63 31",
+        '6' => "97 71 13 36 64",
+        '7' => "79 92",
+        '8' => "79 93 31 17 46",
+        '9' => "64 47 79 93 31",
+        'A' => "17 79 93 46",
+        'B' => "17 78 86 64 63 31",
+        'C' => "97 71 13",
+        'D' => "17 78 86 62 21",
+        'E' => "97 71 13 45",
+        'F' => "97 71 45",
+        'G' => "97 71 13 36 65",
+        'H' => "17 39 46",
+        'I' => "79 82 13",
+        'J' => "79 93 31 14",
+        'K' => "17 49 43",
+        'L' => "71 13",
+        'M' => "17 75 59 93",
+        'N' => "17 73 39",
+        'O' => "17 79 93 31",
+        'P' => "17 79 96 64",
+        'Q' => "17 79 93 31 53",
+        'R' => "17 79 96 64 43",
+        'S' => "97 74 46 63 31",
+        'T' => "79 82",
+        'U' => "71 13 39",
+        'V' => "72 29",
+        'W' => "71 15 53 39",
+        'X' => "73 91",
+        'Y' => "75 95 52",
+        'Z' => "79 91 13",
+        '-' => "46",
+        '+' => "46 82",
+        '=' => "46 13",
+        '/' => "19",
+        '_' => "13",
+        _ => "",
+    }
+}
@@ -0,0 +1,214 @@
+use gltf::{buffer::Data, Primitive};
+use nalgebra::{Matrix4, Vector3};
+use web_sys::WebGl2RenderingContext as GL;
+
+use super::graph::RenderPass;
+use super::material::AlphaMode;
+use super::rgl::shader::ShaderKind;
+use super::rgl::uniform_buffer::UniformBuffer;
+use super::MAX_INSTANCES;
+use super::{BufferedMesh, CameraData, MeshRenderOpts, NonSkinnedGltfMesh, Render, WebRenderer};
+use crate::app::store::debug::DEFAULT_DEBUG_COLOR;
+use crate::app::Assets;
+use crate::app::State;
+
+/// A glTF primitive ready to draw, collected instead of drawn straight away so a pass
+/// can order its draws to share programs, materials and vertex arrays
+pub struct DrawItem<'a> {
+    pub shader: ShaderKind,
+    /// Asset index of the material, `None` for depth only draws that are not alpha tested
+    pub material: Option<usize>,
+    pub alpha_mode: AlphaMode,
+    /// Squared distance from the eye, for drawing blended primitives back to front
+    pub depth: f32,
+    /// Key of the primitive's vertex array in the renderer's cache
+    pub vao: String,
+    pub primitive: Primitive<'a>,
+    pub buffers: &'a Vec<Data>,
+    pub opts: MeshRenderOpts,
+    /// Joint matrices of skinned primitives
+    pub joints: Option<Vec<Matrix4<f32>>>,
+    /// Placement of each copy after `opts.model_mat()`, empty to draw a single copy
+    pub instances: Vec<Matrix4<f32>>,
+}
+
+/// Everything a pass draws from glTF meshes
+#[derive(Default)]
+pub struct DrawList<'a> {
+    items: Vec<DrawItem<'a>>,
+    /// (centre, half extents) of bounding boxes, outlined in passes that draw gizmos
+    gizmos: Vec<(Vector3<f32>, Vector3<f32>)>,
+}
+
+impl<'a> DrawList<'a> {
+    pub fn push(&mut self, item: DrawItem<'a>) {
+        self.items.push(item);
+    }
+
+    pub fn push_gizmo(&mut self, centre: Vector3<f32>, extents: Vector3<f32>) {
+        self.gizmos.push((centre, extents));
+    }
+}
+
+impl WebRenderer {
+    /// Draw the list sorted by program, then material, then vertex array, only changing
+    /// state between draws that differ
+    pub fn draw_list(
+        &self,
+        gl: &GL,
+        mut list: DrawList,
+        camera: &UniformBuffer<CameraData>,
+        pass: &RenderPass,
+        state: &State,
+        assets: &Assets,
+    ) {
+        if pass.transparent {
+            // Blending needs what is behind drawn first, whatever state that costs
+            list.items.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+        } else {
+            list.items.sort_by(|a, b| {
+                (a.shader, a.material, &a.vao).cmp(&(b.shader, b.material, &b.vao))
+            });
+        }
+
+        // Vertex arrays are built with this shader, the rest share its attribute locations
+        let non_skinned_shader = self
+            .shader_sys
+            .get_shader(&ShaderKind::NonSkinnedMesh)
+            .unwrap();
+
+        let mut program: Option<ShaderKind> = None;
+        let mut material: Option<usize> = None;
+        let mut vao: Option<(String, BufferedMesh)> = None;
+        let mut blend: Option<AlphaMode> = None;
+
+        let debug = &state.debug;
+        let wireframe = debug.wireframe && !pass.depth_only;
+        let overdraw = debug.overdraw && !pass.depth_only;
+
+        if overdraw {
+            // Every surface adds to the pixel, hidden or not
+            gl.enable(GL::BLEND);
+            gl.blend_func(GL::ONE, GL::ONE);
+            gl.disable(GL::DEPTH_TEST);
+        } else if pass.transparent {
+            gl.enable(GL::BLEND);
+            // Blended surfaces do not hide each other, only opaque ones behind
+            gl.depth_mask(false);
+        }
+
+        for item in &list.items {
+            let shader = self.shader_sys.get_shader(&item.shader).unwrap();
+
+            if program != Some(item.shader) {
+                self.shader_sys.use_program(gl, item.shader);
+                self.count_state_change();
+
+                // Uniforms belong to the program, so a new one needs them all again
+                let block_index = shader.get_uniform_block_index(gl, "Camera");
+                camera.bind_base(gl, shader, block_index, 2);
+                if !pass.depth_only {
+                    self.bind_sun(gl, shader, state);
+                    self.bind_ambient(gl, shader);
+                    gl.uniform1i(
+                        shader.get_uniform_location(gl, "debugView").as_ref(),
+                        debug.mesh_view(),
+                    );
+                } else {
+                    // Unmasked items sort first and cut nothing out, sampling plain white
+                    assets
+                        .default_material()
+                        .uniform_alpha_test(gl, shader, assets);
+                }
+                program = Some(item.shader);
+                material = None;
+            }
+
+            if item.material.is_some() && item.material != material {
+                let mat = item.material.and_then(|m| assets.get_material(m));
+                let mat = mat.unwrap_or_else(|| assets.default_material());
+                if pass.depth_only {
+                    mat.uniform_alpha_test(gl, shader, assets);
+                } else {
+                    mat.uniform(gl, shader, assets);
+                }
+                self.count_state_change();
+                material = item.material;
+            }
+
+            if pass.transparent && !overdraw && blend != Some(item.alpha_mode) {
+                match item.alpha_mode {
+                    AlphaMode::Additive => gl.blend_func(GL::SRC_ALPHA, GL::ONE),
+                    _ => gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA),
+                }
+                self.count_state_change();
+                blend = Some(item.alpha_mode);
+            }
+
+            let mut opts = item.opts;
+            opts.wireframe = wireframe;
+            let meshdata = NonSkinnedGltfMesh {
+                mesh: &item.primitive,
+                buffers: item.buffers,
+                opts: &opts,
+            };
+
+            // Wireframes index the same vertices with lines, so need their own vertex array
+            let key = match wireframe {
+                true => format!("{}-wireframe", item.vao),
+                false => item.vao.clone(),
+            };
+            let b = match &vao {
+                Some((k, b)) if *k == key => *b,
+                _ => {
+                    let b = self.prepare_for_render(gl, &meshdata, non_skinned_shader, &key, state);
+                    self.count_state_change();
+                    vao = Some((key, b));
+                    b
+                }
+            };
+
+            if let Some(joints) = &item.joints {
+                self.bind_joints(gl, shader, joints);
+            }
+
+            if item.instances.is_empty() {
+                if item.joints.is_none() {
+                    self.bind_instances(gl, shader, &[]);
+                }
+                meshdata.render(gl, &b, shader, self, camera, state);
+                self.count_draw_call();
+                continue;
+            }
+
+            for batch in item.instances.chunks(MAX_INSTANCES) {
+                self.bind_instances(gl, shader, batch);
+
+                opts.instances = batch.len() as i32;
+                let meshdata = NonSkinnedGltfMesh {
+                    mesh: &item.primitive,
+                    buffers: item.buffers,
+                    opts: &opts,
+                };
+                meshdata.render(gl, &b, shader, self, camera, state);
+                self.count_draw_call();
+            }
+        }
+
+        if overdraw {
+            gl.disable(GL::BLEND);
+            gl.enable(GL::DEPTH_TEST);
+        } else if pass.transparent {
+            gl.disable(GL::BLEND);
+            gl.depth_mask(true);
+        }
+
+        // Drawn with the rest of the frame's debug lines once the pass is done
+        if pass.gizmos && debug.bounds {
+            let mut queued = self.debug_lines.queued.borrow_mut();
+            for (centre, extents) in list.gizmos {
+                queued.aabb(centre, extents, DEFAULT_DEBUG_COLOR);
+            }
+        }
+    }
+}
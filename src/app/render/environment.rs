@@ -0,0 +1,281 @@
+use web_sys::WebGl2RenderingContext as GL;
+use web_sys::WebGlFramebuffer;
+
+use super::rgl::shader::{Shader, ShaderKind, ShaderSystem};
+use super::rgl::texture::{ColorFormat, CubeTex, Tex, TexFilter, TexUnit};
+use super::rgl::vao::Vao;
+use super::WebRenderer;
+use crate::app::store::skybox::SkyboxSource;
+use crate::app::Assets;
+
+/// Texture unit the cube map shaders read their source from
+static SOURCE_UNIT: u32 = 9;
+/// Texture units lit shaders read the image based lighting maps from
+pub static IRRADIANCE_UNIT: u32 = 16;
+pub static PREFILTERED_UNIT: u32 = 17;
+pub static BRDF_LUT_UNIT: u32 = 18;
+
+/// Diffuse lighting varies slowly with direction, so needs very few texels
+static IRRADIANCE_SIZE: i32 = 32;
+static PREFILTERED_SIZE: i32 = 128;
+/// Mip levels of the prefiltered map, from smooth to fully rough
+pub static PREFILTERED_LEVELS: i32 = 5;
+static BRDF_LUT_SIZE: i32 = 128;
+
+/// Cube maps of the sky, shared by the skybox and everything lit by it
+pub struct Environment {
+    source: SkyboxSource,
+    /// Scale of the light from the environment, set by the skybox every frame
+    pub strength: f32,
+    pub cube: CubeTex,
+    /// Light arriving from the hemisphere around each direction, for diffuse lighting
+    pub irradiance: CubeTex,
+    /// Sky blurred for increasing roughness down the mip levels, for specular lighting
+    pub prefiltered: CubeTex,
+}
+
+impl Environment {
+    fn delete(&self, gl: &GL) {
+        self.cube.delete(gl);
+        self.irradiance.delete(gl);
+        self.prefiltered.delete(gl);
+    }
+}
+
+impl WebRenderer {
+    /// Light this frame with a skybox, drawing its textures into the environment maps once
+    /// they have all loaded. The maps are only redrawn when the source changes.
+    pub fn use_environment(
+        &self,
+        gl: &GL,
+        source: &SkyboxSource,
+        resolution: i32,
+        strength: f32,
+        assets: &Assets,
+    ) {
+        self.environment_used.set(true);
+
+        if let Some(env) = &mut *self.environment.borrow_mut() {
+            if env.source == *source && env.cube.size == resolution {
+                env.strength = strength;
+                return;
+            }
+        }
+        if !source.textures().iter().all(|t| assets.tex_loaded(*t)) {
+            return;
+        }
+
+        let unit = TexUnit::new(gl, SOURCE_UNIT);
+
+        // The triangle is made up in the vertex shader, so no attributes are enabled
+        let vao = Vao::new(gl);
+        vao.bind(gl);
+
+        let framebuffer = gl.create_framebuffer();
+        gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
+
+        // Sky
+        let cube = CubeTex::new_color(gl, resolution, CubeTex::full_levels(resolution));
+
+        let shader = self.shader_sys.get_shader(&ShaderKind::CubeFace).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::CubeFace);
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "source").as_ref(),
+            unit.uniti(),
+        );
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "equirect").as_ref(),
+            matches!(source, SkyboxSource::Equirect(_)) as i32,
+        );
+
+        draw_cube_faces(gl, shader, &cube, 0, |face| {
+            let tex = match source {
+                SkyboxSource::Faces(faces) => faces[face as usize],
+                SkyboxSource::Equirect(tex) => *tex,
+            };
+            assets.get_tex(tex).bind_at(gl, &unit);
+        });
+        cube.generate_mipmap(gl);
+        cube.bind_at(gl, &unit);
+
+        // Diffuse
+        let irradiance = CubeTex::new_color(gl, IRRADIANCE_SIZE, 1);
+
+        let shader = self.shader_sys.get_shader(&ShaderKind::Irradiance).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::Irradiance);
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "source").as_ref(),
+            unit.uniti(),
+        );
+        gl.uniform1f(
+            shader.get_uniform_location(gl, "sourceLod").as_ref(),
+            ((resolution / IRRADIANCE_SIZE).max(1) as f32).log2(),
+        );
+        draw_cube_faces(gl, shader, &irradiance, 0, |_| ());
+
+        // Specular
+        let prefiltered = CubeTex::new_color(gl, PREFILTERED_SIZE, PREFILTERED_LEVELS);
+
+        let shader = self.shader_sys.get_shader(&ShaderKind::Prefilter).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::Prefilter);
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "source").as_ref(),
+            unit.uniti(),
+        );
+        gl.uniform1f(
+            shader.get_uniform_location(gl, "sourceSize").as_ref(),
+            resolution as f32,
+        );
+        for level in 0..PREFILTERED_LEVELS {
+            gl.uniform1f(
+                shader.get_uniform_location(gl, "roughness").as_ref(),
+                level as f32 / (PREFILTERED_LEVELS - 1) as f32,
+            );
+            draw_cube_faces(gl, shader, &prefiltered, level, |_| ());
+        }
+
+        finish_offscreen(gl, framebuffer, vao);
+
+        let old = self.environment.replace(Some(Environment {
+            source: source.clone(),
+            strength,
+            cube,
+            irradiance,
+            prefiltered,
+        }));
+        if let Some(old) = old {
+            old.delete(gl);
+        }
+    }
+
+    /// Forget the environment if no skybox used it this frame, such as after loading a scene
+    /// without one. Called after every entity is prepared.
+    pub(in crate::app::render) fn release_unused_environment(&self, gl: &GL) {
+        if !self.environment_used.replace(false) {
+            if let Some(old) = self.environment.replace(None) {
+                old.delete(gl);
+            }
+        }
+    }
+
+    /// Bind the environment cube map, returning false if there is none yet
+    pub fn bind_environment(&self, gl: &GL, unit: &TexUnit) -> bool {
+        match &*self.environment.borrow() {
+            Some(env) => {
+                env.cube.bind_at(gl, unit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the image based lighting maps of a lit shader, which must be in use.
+    /// Without an environment the shader falls back to a flat ambient colour.
+    pub fn bind_ambient(&self, gl: &GL, shader: &Shader) {
+        let irradiance_unit = TexUnit::new(gl, IRRADIANCE_UNIT);
+        let prefiltered_unit = TexUnit::new(gl, PREFILTERED_UNIT);
+        let brdf_lut_unit = TexUnit::new(gl, BRDF_LUT_UNIT);
+
+        let strength = match &*self.environment.borrow() {
+            Some(env) => {
+                env.irradiance.bind_at(gl, &irradiance_unit);
+                env.prefiltered.bind_at(gl, &prefiltered_unit);
+                env.strength
+            }
+            None => 0.0,
+        };
+        self.brdf_lut.bind_at(gl, &brdf_lut_unit);
+
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "irradianceMap").as_ref(),
+            irradiance_unit.uniti(),
+        );
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "prefilteredMap").as_ref(),
+            prefiltered_unit.uniti(),
+        );
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "brdfLut").as_ref(),
+            brdf_lut_unit.uniti(),
+        );
+        gl.uniform2f(
+            shader
+                .get_uniform_location(gl, "environmentParams")
+                .as_ref(),
+            strength,
+            (PREFILTERED_LEVELS - 1) as f32,
+        );
+    }
+
+    /// Draw the split sum lookup table, which depends on nothing in the scene
+    pub fn create_brdf_lut(gl: &GL, shader_sys: &ShaderSystem) -> Tex {
+        let lut = Tex::new_color(
+            gl,
+            BRDF_LUT_SIZE,
+            BRDF_LUT_SIZE,
+            TexFilter::Linear,
+            ColorFormat::Rgba8,
+        )
+        .unwrap();
+        lut.clamp_to_edge(gl);
+
+        let vao = Vao::new(gl);
+        vao.bind(gl);
+
+        let framebuffer = gl.create_framebuffer();
+        gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            GL::TEXTURE_2D,
+            lut.texture(),
+            0,
+        );
+        gl.viewport(0, 0, BRDF_LUT_SIZE, BRDF_LUT_SIZE);
+
+        shader_sys.use_program(gl, ShaderKind::BrdfLut);
+        gl.draw_arrays(GL::TRIANGLES, 0, 3);
+
+        finish_offscreen(gl, framebuffer, vao);
+
+        lut
+    }
+}
+
+/// Draw every face of one mip level of a cube map with the program in use, which reads
+/// the face from its `face` uniform. `before_face` can bind anything the face needs.
+fn draw_cube_faces(
+    gl: &GL,
+    shader: &Shader,
+    cube: &CubeTex,
+    level: i32,
+    mut before_face: impl FnMut(u32),
+) {
+    let size = (cube.size >> level).max(1);
+    gl.viewport(0, 0, size, size);
+
+    for face in 0..6 {
+        before_face(face);
+
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            CubeTex::face_target(face),
+            cube.texture(),
+            level,
+        );
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "face").as_ref(),
+            face as i32,
+        );
+
+        gl.draw_arrays(GL::TRIANGLES, 0, 3);
+    }
+}
+
+/// Go back to drawing on screen and free what drawing offscreen needed
+fn finish_offscreen(gl: &GL, framebuffer: Option<WebGlFramebuffer>, vao: Vao) {
+    gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+    gl.delete_framebuffer(framebuffer.as_ref());
+    vao.delete(gl);
+}
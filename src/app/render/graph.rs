@@ -0,0 +1,344 @@
+use std::collections::HashSet;
+use std::ops::BitOr;
+use std::rc::Rc;
+
+use super::rgl::framebuffer::*;
+use super::rgl::Framebuffer;
+use super::{CameraData, WebRenderer, WATER_TILE_Y_POS};
+use crate::app::Assets;
+use crate::app::State;
+use web_sys::WebGl2RenderingContext as GL;
+
+pub const SHADOW: &str = "shadow";
+pub const REFLECTION: &str = "reflection";
+pub const REFRACTION: &str = "refraction";
+pub const WATER: &str = "water";
+pub const OPAQUES: &str = "opaques";
+pub const TRANSPARENT: &str = "transparent";
+pub const LABELS: &str = "labels";
+pub const HUD: &str = "hud";
+
+/// Bit mask of the kinds of entity a pass draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderLayers(pub u32);
+
+impl RenderLayers {
+    pub const OPAQUE: RenderLayers = RenderLayers(1);
+    pub const WATER: RenderLayers = RenderLayers(1 << 1);
+    pub const SKY: RenderLayers = RenderLayers(1 << 2);
+    /// Text placed in the scene
+    pub const LABEL: RenderLayers = RenderLayers(1 << 3);
+    /// Text placed on the screen
+    pub const HUD: RenderLayers = RenderLayers(1 << 4);
+
+    pub fn intersects(self, other: RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for RenderLayers {
+    type Output = RenderLayers;
+
+    fn bitor(self, rhs: RenderLayers) -> RenderLayers {
+        RenderLayers(self.0 | rhs.0)
+    }
+}
+
+pub enum PassOutput {
+    /// The scene target, which post effects then draw to the canvas
+    Screen,
+    /// The canvas itself, drawn over the scene once post effects have run
+    Canvas,
+    /// The renderer's depth only shadow map, sized by the sun's shadow settings
+    ShadowMap,
+    Framebuffer {
+        framebuffer: Rc<Framebuffer>,
+        width: i32,
+        height: i32,
+    },
+}
+
+/// Which camera a pass draws from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassView {
+    Camera,
+    /// The camera with fog turned off, for passes whose output is fogged again where it is drawn
+    UnfoggedCamera,
+    /// The camera mirrored in the water plane, for reflections
+    MirroredY,
+    /// Looking along the sun's direction, for shadows
+    Sun,
+}
+
+pub struct RenderPass {
+    /// Also the name other passes use to read this pass's output
+    pub name: &'static str,
+    /// Passes whose output this one may sample. They must come earlier in the graph.
+    pub inputs: Vec<&'static str>,
+    pub output: PassOutput,
+    /// Colour to clear to before drawing, along with depth. `None` draws over what is there.
+    pub clear: Option<[f32; 4]>,
+    /// Only entities on one of these layers are drawn
+    pub filter: RenderLayers,
+    pub view: PassView,
+    /// (normal, distance) plane that geometry on its negative side is clipped against
+    pub clip_plane: [f32; 4],
+    /// Draw debug gizmos such as bounding boxes, and the frame's debug lines
+    pub gizmos: bool,
+    /// Entities write depth only, with no materials or lighting
+    pub depth_only: bool,
+    /// Draws only alpha blended primitives, back to front, where other passes draw the rest
+    pub transparent: bool,
+    /// Skip the pass unless this returns true
+    pub condition: Option<fn(&State) -> bool>,
+}
+
+/// Ordered list of passes making up a frame
+#[derive(Default)]
+pub struct RenderGraph {
+    passes: Vec<RenderPass>,
+}
+
+impl RenderGraph {
+    /// Shadows from the sun, reflection and refraction into the water's framebuffers,
+    /// then the water, everything opaque, everything blended and text in the scene.
+    /// Text on the screen is drawn last, after post effects.
+    pub fn new(refraction: Rc<Framebuffer>, reflection: Rc<Framebuffer>) -> RenderGraph {
+        let sky = [0.53, 0.8, 0.98, 1.];
+        // Position is positive instead of negative for.. mathematical reasons..
+        let no_clip = [0., 1., 0., 1000000.0];
+
+        let mut graph = RenderGraph::default();
+
+        graph.add_pass(RenderPass {
+            name: SHADOW,
+            inputs: vec![],
+            output: PassOutput::ShadowMap,
+            clear: Some([1., 1., 1., 1.]),
+            filter: RenderLayers::OPAQUE,
+            view: PassView::Sun,
+            clip_plane: no_clip,
+            gizmos: false,
+            depth_only: true,
+            transparent: false,
+            condition: Some(|state| state.sun.shadows.enabled),
+        });
+
+        graph.add_pass(RenderPass {
+            name: REFRACTION,
+            inputs: vec![SHADOW],
+            output: PassOutput::Framebuffer {
+                framebuffer: refraction,
+                width: REFRACTION_TEXTURE_WIDTH,
+                height: REFRACTION_TEXTURE_HEIGHT,
+            },
+            clear: Some(sky),
+            filter: RenderLayers::OPAQUE,
+            // The water is fogged over what it refracts
+            view: PassView::UnfoggedCamera,
+            clip_plane: [0., -1., 0., WATER_TILE_Y_POS],
+            gizmos: false,
+            depth_only: false,
+            transparent: false,
+            condition: None,
+        });
+        graph.add_pass(RenderPass {
+            name: REFLECTION,
+            inputs: vec![SHADOW],
+            output: PassOutput::Framebuffer {
+                framebuffer: reflection,
+                width: REFLECTION_TEXTURE_WIDTH,
+                height: REFLECTION_TEXTURE_HEIGHT,
+            },
+            clear: Some(sky),
+            filter: RenderLayers::OPAQUE | RenderLayers::SKY,
+            view: PassView::MirroredY,
+            clip_plane: [0., 1., 0., -WATER_TILE_Y_POS],
+            gizmos: false,
+            depth_only: false,
+            transparent: false,
+            condition: None,
+        });
+        graph.add_pass(RenderPass {
+            name: WATER,
+            inputs: vec![REFRACTION, REFLECTION],
+            output: PassOutput::Screen,
+            clear: Some(sky),
+            filter: RenderLayers::WATER,
+            view: PassView::Camera,
+            clip_plane: no_clip,
+            gizmos: false,
+            depth_only: false,
+            transparent: false,
+            condition: None,
+        });
+        graph.add_pass(RenderPass {
+            name: OPAQUES,
+            inputs: vec![SHADOW],
+            output: PassOutput::Screen,
+            clear: None,
+            filter: RenderLayers::OPAQUE | RenderLayers::SKY,
+            view: PassView::Camera,
+            clip_plane: no_clip,
+            gizmos: true,
+            depth_only: false,
+            transparent: false,
+            condition: None,
+        });
+        graph.add_pass(RenderPass {
+            name: TRANSPARENT,
+            inputs: vec![SHADOW],
+            output: PassOutput::Screen,
+            clear: None,
+            filter: RenderLayers::OPAQUE,
+            view: PassView::Camera,
+            clip_plane: no_clip,
+            gizmos: false,
+            depth_only: false,
+            transparent: true,
+            condition: None,
+        });
+        graph.add_pass(RenderPass {
+            name: LABELS,
+            inputs: vec![],
+            output: PassOutput::Screen,
+            clear: None,
+            filter: RenderLayers::LABEL,
+            view: PassView::Camera,
+            clip_plane: no_clip,
+            gizmos: false,
+            depth_only: false,
+            transparent: true,
+            condition: None,
+        });
+        graph.add_pass(RenderPass {
+            name: HUD,
+            inputs: vec![],
+            output: PassOutput::Canvas,
+            clear: None,
+            filter: RenderLayers::HUD,
+            view: PassView::Camera,
+            clip_plane: no_clip,
+            gizmos: false,
+            depth_only: false,
+            transparent: true,
+            condition: None,
+        });
+
+        graph
+    }
+
+    /// Append a pass to the end of the frame
+    pub fn add_pass(&mut self, pass: RenderPass) {
+        for input in &pass.inputs {
+            if !self.passes.iter().any(|p| p.name == *input) {
+                log::error!(
+                    "Render pass {} reads {} before it is drawn",
+                    pass.name,
+                    input
+                );
+            }
+        }
+        self.passes.push(pass);
+    }
+
+    /// The passes to run this frame, in order. Passes drawing to the screen or canvas always run,
+    /// offscreen passes only when an entity drawn by a later pass reads their output.
+    /// Either can be skipped by their `condition`.
+    pub fn schedule(&self, state: &State) -> Vec<&RenderPass> {
+        let mut needed: HashSet<&str> = HashSet::new();
+        let mut live = Vec::new();
+
+        for pass in self.passes.iter().rev() {
+            let is_live = match pass.output {
+                PassOutput::Screen | PassOutput::Canvas => true,
+                _ => needed.contains(pass.name),
+            } && pass.condition.map_or(true, |c| c(state));
+            if !is_live {
+                continue;
+            }
+
+            for e in &state.entities {
+                let e = e.borrow();
+                if e.layers().intersects(pass.filter) {
+                    needed.extend(e.inputs().into_iter().filter(|i| pass.inputs.contains(i)));
+                }
+            }
+            live.push(pass);
+        }
+
+        live.reverse();
+        live
+    }
+}
+
+impl WebRenderer {
+    #[allow(clippy::too_many_arguments)]
+    pub(in crate::app::render) fn run_pass(
+        &self,
+        gl: &GL,
+        pass: &RenderPass,
+        camera: &CameraData,
+        mirrored_camera: &CameraData,
+        sun_camera: &CameraData,
+        state: &State,
+        assets: &Assets,
+    ) {
+        match &pass.output {
+            PassOutput::Screen => {
+                let scene = &self.post_targets.borrow().scene;
+                scene
+                    .framebuffer
+                    .set_samples(gl, scene.width, scene.height, state.post.msaa);
+                scene.framebuffer.bind_draw(gl);
+                gl.viewport(0, 0, scene.width, scene.height);
+            }
+            PassOutput::Framebuffer {
+                framebuffer,
+                width,
+                height,
+            } => {
+                framebuffer.set_samples(gl, *width, *height, state.post.msaa);
+                framebuffer.bind_draw(gl);
+                gl.viewport(0, 0, *width, *height);
+            }
+            PassOutput::Canvas => {
+                gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+                gl.viewport(
+                    0,
+                    0,
+                    state.display.width as i32,
+                    state.display.height as i32,
+                );
+            }
+            PassOutput::ShadowMap => {
+                let resolution = state.sun.shadows.resolution;
+                let shadow_map = self.shadow_map(gl, resolution);
+                gl.bind_framebuffer(GL::FRAMEBUFFER, shadow_map.framebuffer.as_ref());
+                gl.viewport(0, 0, resolution, resolution);
+            }
+        }
+
+        if let Some([r, g, b, a]) = pass.clear {
+            gl.clear_color(r, g, b, a);
+            gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+        }
+
+        let (buffer, data) = match pass.view {
+            PassView::Camera => (&self.camera_buffer, camera),
+            PassView::UnfoggedCamera => (&self.unfogged_camera_buffer, camera),
+            PassView::MirroredY => (&self.flipped_y_camera_buffer, mirrored_camera),
+            PassView::Sun => (&self.sun_camera_buffer, sun_camera),
+        };
+
+        self.render_entities(gl, state, assets, buffer, &data.frustum(), pass);
+        if pass.gizmos {
+            self.draw_debug(gl, buffer, &data.view, state);
+        }
+
+        // Screen passes share the scene target, which is resolved once before post processing
+        if let PassOutput::Framebuffer { framebuffer, .. } = &pass.output {
+            framebuffer.resolve(gl);
+        }
+    }
+}
@@ -3,24 +3,138 @@ use web_sys::WebGl2RenderingContext;
 use crate::app::{
     from_rhai::FromRhai,
     render::rgl::{shader::Shader, texture::TexUnit},
-    Assets,
+    to_f32, Assets,
 };
 #[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
 pub enum Uniform {
     Float(f32),
     Int(i32),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
     Tex(usize),
 }
 
+/// How a material's base colour alpha is used
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha is ignored
+    Opaque,
+    /// Fragments with alpha below the cutoff are discarded, the rest are opaque
+    Mask(f32),
+    /// Blended over what is behind in the transparent pass
+    Blend,
+    /// Added to what is behind in the transparent pass, scaled by alpha
+    Additive,
+}
+
+impl AlphaMode {
+    /// Whether the material is drawn in the sorted transparent pass rather than with opaques
+    pub fn is_blended(self) -> bool {
+        matches!(self, AlphaMode::Blend | AlphaMode::Additive)
+    }
+
+    /// Parse a glTF alpha mode name, or `additive`
+    pub fn from_name(name: &str, cutoff: f32) -> Option<AlphaMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "opaque" => Some(AlphaMode::Opaque),
+            "mask" => Some(AlphaMode::Mask(cutoff)),
+            "blend" => Some(AlphaMode::Blend),
+            "additive" => Some(AlphaMode::Additive),
+            _ => None,
+        }
+    }
+}
+
+/// glTF metallic-roughness material, as the uniforms the mesh shader reads.
+/// Texture slots a material leaves empty use textures that leave the factor unchanged.
+#[derive(Clone, Debug)]
+pub struct PbrMaterial {
+    pub base_color_texture: usize,
+    pub base_color_factor: [f32; 4],
+    /// Roughness in green, metallic in blue
+    pub metallic_roughness_texture: usize,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub normal_texture: usize,
+    /// Ambient occlusion in red
+    pub occlusion_texture: usize,
+    pub occlusion_strength: f32,
+    pub emissive_texture: usize,
+    pub emissive_factor: [f32; 3],
+    pub alpha_mode: AlphaMode,
+}
+
+impl PbrMaterial {
+    /// The glTF defaults - a fully metallic, fully rough white surface
+    pub fn new(white_tex: usize, flat_normal_tex: usize) -> Self {
+        Self {
+            base_color_texture: white_tex,
+            base_color_factor: [1.0; 4],
+            metallic_roughness_texture: white_tex,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            normal_texture: flat_normal_tex,
+            occlusion_texture: white_tex,
+            occlusion_strength: 1.0,
+            emissive_texture: white_tex,
+            emissive_factor: [0.0; 3],
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+impl From<PbrMaterial> for Mat {
+    fn from(m: PbrMaterial) -> Self {
+        let mut mat = Mat::filled(vec![
+            ("meshTexture".to_owned(), Uniform::Tex(m.base_color_texture)),
+            (
+                "baseColorFactor".to_owned(),
+                Uniform::Vec4(m.base_color_factor),
+            ),
+            (
+                "metallicRoughnessTexture".to_owned(),
+                Uniform::Tex(m.metallic_roughness_texture),
+            ),
+            (
+                "metallicFactor".to_owned(),
+                Uniform::Float(m.metallic_factor),
+            ),
+            (
+                "roughnessFactor".to_owned(),
+                Uniform::Float(m.roughness_factor),
+            ),
+            ("meshNormal".to_owned(), Uniform::Tex(m.normal_texture)),
+            (
+                "occlusionTexture".to_owned(),
+                Uniform::Tex(m.occlusion_texture),
+            ),
+            (
+                "occlusionStrength".to_owned(),
+                Uniform::Float(m.occlusion_strength),
+            ),
+            (
+                "emissiveTexture".to_owned(),
+                Uniform::Tex(m.emissive_texture),
+            ),
+            (
+                "emissiveFactor".to_owned(),
+                Uniform::Vec3(m.emissive_factor),
+            ),
+        ]);
+        mat.alpha_mode = m.alpha_mode;
+        mat
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Mat {
     uniforms: Vec<(String, Uniform)>,
+    pub alpha_mode: AlphaMode,
 }
 impl Default for Mat {
     fn default() -> Self {
-        Self {
-            uniforms: Vec::new(),
-        }
+        Self::new()
     }
 }
 
@@ -28,10 +142,22 @@ impl Mat {
     pub const fn new() -> Self {
         Self {
             uniforms: Vec::new(),
+            alpha_mode: AlphaMode::Opaque,
         }
     }
     pub fn filled(uniforms: Vec<(String, Uniform)>) -> Self {
-        Self { uniforms }
+        Self {
+            uniforms,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+
+    /// Set a uniform, replacing any earlier value with the same name
+    pub fn set(&mut self, name: String, uniform: Uniform) {
+        match self.uniforms.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, u)) => *u = uniform,
+            None => self.uniforms.push((name, uniform)),
+        }
     }
 
     pub fn uniform(&self, gl: &WebGl2RenderingContext, shader: &Shader, assets: &Assets) {
@@ -42,6 +168,8 @@ impl Mat {
             match uniform {
                 Uniform::Float(v) => gl.uniform1f(loc.as_ref(), *v),
                 Uniform::Int(v) => gl.uniform1i(loc.as_ref(), *v),
+                Uniform::Vec3([x, y, z]) => gl.uniform3f(loc.as_ref(), *x, *y, *z),
+                Uniform::Vec4([x, y, z, w]) => gl.uniform4f(loc.as_ref(), *x, *y, *z, *w),
                 Uniform::Tex(v) => {
                     let u = TexUnit::new(gl, i);
                     assets.get_tex(*v).bind_at(gl, &u);
@@ -51,6 +179,51 @@ impl Mat {
                 }
             }
         }
+
+        let (mode, cutoff) = match self.alpha_mode {
+            AlphaMode::Opaque => (0, 0.0),
+            AlphaMode::Mask(cutoff) => (1, cutoff),
+            AlphaMode::Blend | AlphaMode::Additive => (2, 0.0),
+        };
+        gl.uniform1i(shader.get_uniform_location(gl, "alphaMode").as_ref(), mode);
+        gl.uniform1f(
+            shader.get_uniform_location(gl, "alphaCutoff").as_ref(),
+            cutoff,
+        );
+    }
+
+    /// Set only what the shadow shader reads to cut out masked materials
+    pub fn uniform_alpha_test(
+        &self,
+        gl: &WebGl2RenderingContext,
+        shader: &Shader,
+        assets: &Assets,
+    ) {
+        for (name, uniform) in &self.uniforms {
+            let loc = match name.as_str() {
+                "meshTexture" | "baseColorFactor" => shader.get_uniform_location(gl, name),
+                _ => continue,
+            };
+
+            match uniform {
+                Uniform::Vec4([x, y, z, w]) => gl.uniform4f(loc.as_ref(), *x, *y, *z, *w),
+                Uniform::Tex(v) => {
+                    let u = TexUnit::new(gl, 10);
+                    assets.get_tex(*v).bind_at(gl, &u);
+                    gl.uniform1i(loc.as_ref(), u.unit() as i32);
+                }
+                _ => (),
+            }
+        }
+
+        let cutoff = match self.alpha_mode {
+            AlphaMode::Mask(cutoff) => cutoff,
+            _ => 0.0,
+        };
+        gl.uniform1f(
+            shader.get_uniform_location(gl, "alphaCutoff").as_ref(),
+            cutoff,
+        );
     }
 }
 
@@ -59,13 +232,39 @@ impl FromRhai for Mat {
     where
         Self: Sized,
     {
-        let mut uniforms = Vec::new();
+        // Start from the default material so every uniform the mesh shader reads is set
+        let mut mat = assets.default_material().clone();
+
+        // glTF's default cutoff, for masks without their own
+        let cutoff = match map.get("alpha_cutoff") {
+            Some(c) => to_f32(c)?,
+            None => 0.5,
+        };
 
         for (k, v) in map.iter() {
-            if v.is_variant() {
-                uniforms.push((k.to_owned().to_string(), v.clone().cast()));
+            let name = k.to_owned().to_string();
+
+            if name == "alpha_mode" {
+                let mode = v.clone().into_string()?;
+                mat.alpha_mode = AlphaMode::from_name(&mode, cutoff)
+                    .ok_or("alpha_mode must be opaque, mask, blend or additive")?;
+            } else if name == "alpha_cutoff" {
+                continue;
+            } else if v.is_variant() {
+                mat.set(name, v.clone().cast());
+            } else if v.is_float() || v.is_int() {
+                mat.set(name, Uniform::Float(to_f32(v)?));
+            } else if v.is_array() {
+                let a = v.clone().into_array()?;
+                let a = a.iter().map(to_f32).collect::<Result<Vec<f32>, _>>()?;
+
+                match a[..] {
+                    [x, y, z] => mat.set(name, Uniform::Vec3([x, y, z])),
+                    [x, y, z, w] => mat.set(name, Uniform::Vec4([x, y, z, w])),
+                    _ => return Err("Material vectors must have 3 or 4 components"),
+                }
             }
         }
-        Ok(Mat { uniforms })
+        Ok(mat)
     }
 }
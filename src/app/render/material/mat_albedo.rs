@@ -18,7 +18,7 @@ impl Material for MatAlbedo {
         camera: &crate::app::render::rgl::uniform_buffer::UniformBuffer<
             crate::app::render::CameraData,
         >,
-        state: &crate::app::store::State,
+        _state: &crate::app::store::State,
     ) {
         let mesh_texture_uni = self.shader.get_uniform_location(gl, "meshTexture");
         let mesh_normal_uni = self.shader.get_uniform_location(gl, "meshNormal");
@@ -3,7 +3,9 @@ pub mod mat_albedo;
 pub mod mat_textured_quad;
 pub mod mat_water;
 
+pub use mat::AlphaMode;
 pub use mat::Mat;
+pub use mat::PbrMaterial;
 pub use mat::Uniform;
 pub use mat_albedo::MatAlbedo;
 pub use mat_water::MatWater;
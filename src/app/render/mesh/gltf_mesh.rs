@@ -12,12 +12,18 @@ use crate::app::State;
 use gltf::mesh::util::ReadIndices;
 use gltf::mesh::util::ReadTexCoords;
 use gltf::{buffer::Data, Primitive};
-use nalgebra;
-use nalgebra::Isometry3;
-use nalgebra::Scale3;
 use web_sys::WebGl2RenderingContext as GL;
 use web_sys::*;
 
+/// Locations of the joint indices and weights in `mesh-skinned-vertex.glsl`
+pub const JOINTS_ATTRIB: u32 = 4;
+pub const WEIGHTS_ATTRIB: u32 = 5;
+/// Locations of the first morph target's position and normal offsets, the rest follow on
+pub const MORPH_POSITION_ATTRIB: u32 = 6;
+pub const MORPH_NORMAL_ATTRIB: u32 = 10;
+/// Morph targets past this many are ignored, as each takes two vertex attributes
+pub const MAX_MORPH_TARGETS: usize = 4;
+
 pub struct NonSkinnedGltfMesh<'a> {
     pub mesh: &'a Primitive<'a>,
     pub buffers: &'a Vec<Data>,
@@ -25,7 +31,7 @@ pub struct NonSkinnedGltfMesh<'a> {
 }
 
 impl<'a> Render for NonSkinnedGltfMesh<'a> {
-    fn buffer_attributes(&self, gl: &GL, shader: &Shader, state: &State) -> BufferedMesh {
+    fn buffer_attributes(&self, gl: &GL, shader: &Shader, _state: &State) -> BufferedMesh {
         let mesh: &gltf::Primitive<'a> = self.mesh;
 
         let pos_attrib = gl.get_attrib_location(&shader.program, "position");
@@ -38,14 +44,14 @@ impl<'a> Render for NonSkinnedGltfMesh<'a> {
             let verts: Vec<[f32; 3]> = iter.collect();
 
             gl.enable_vertex_attrib_array(pos_attrib as u32);
-            buffer_sf32_data(&gl, &verts[..], pos_attrib as u32);
+            buffer_sf32_data(gl, &verts[..], pos_attrib as u32);
         }
 
         if let Some(iter) = reader.read_normals() {
             let norms: Vec<[f32; 3]> = iter.collect();
 
             gl.enable_vertex_attrib_array(normal_attrib as u32);
-            buffer_sf32_data(&gl, &norms[..], normal_attrib as u32);
+            buffer_sf32_data(gl, &norms[..], normal_attrib as u32);
         }
 
         if let Some(iter) = reader.read_tangents() {
@@ -53,7 +59,7 @@ impl<'a> Render for NonSkinnedGltfMesh<'a> {
             // W is bi-tangent sign. bitangent = cross(normal, tangent.xyz) * tangent.w
 
             gl.enable_vertex_attrib_array(tangent_attrib as u32);
-            buffer_sf32_data(&gl, &tangents[..], tangent_attrib as u32);
+            buffer_sf32_data(gl, &tangents[..], tangent_attrib as u32);
         } else {
             log::warn!("Primitive {} has no tangent data", mesh.index());
         }
@@ -63,26 +69,73 @@ impl<'a> Render for NonSkinnedGltfMesh<'a> {
 
             gl.enable_vertex_attrib_array(uv_attrib as u32);
 
-            buffer_sf32_data(&gl, &uvs[..], uv_attrib as u32);
+            buffer_sf32_data(gl, &uvs[..], uv_attrib as u32);
+        }
+
+        // Skinning attributes have fixed locations, as the shader used to buffer may not skin
+        if let (Some(joints), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
+            let joints: Vec<[f32; 4]> = joints.into_u16().map(|j| j.map(|i| i as f32)).collect();
+            let weights: Vec<[f32; 4]> = weights.into_f32().collect();
+
+            gl.enable_vertex_attrib_array(JOINTS_ATTRIB);
+            buffer_sf32_data(gl, &joints[..], JOINTS_ATTRIB);
+            gl.enable_vertex_attrib_array(WEIGHTS_ATTRIB);
+            buffer_sf32_data(gl, &weights[..], WEIGHTS_ATTRIB);
+        }
+
+        let targets = reader.read_morph_targets();
+        if mesh.morph_targets().len() > MAX_MORPH_TARGETS {
+            log::warn!(
+                "Primitive {} has {} morph targets, only the first {} are used",
+                mesh.index(),
+                mesh.morph_targets().len(),
+                MAX_MORPH_TARGETS
+            );
+        }
+        for (i, (positions, normals, _)) in targets.take(MAX_MORPH_TARGETS).enumerate() {
+            if let Some(iter) = positions {
+                let attrib = MORPH_POSITION_ATTRIB + i as u32;
+                let deltas: Vec<[f32; 3]> = iter.collect();
+
+                gl.enable_vertex_attrib_array(attrib);
+                buffer_sf32_data(gl, &deltas[..], attrib);
+            }
+            if let Some(iter) = normals {
+                let attrib = MORPH_NORMAL_ATTRIB + i as u32;
+                let deltas: Vec<[f32; 3]> = iter.collect();
+
+                gl.enable_vertex_attrib_array(attrib);
+                buffer_sf32_data(gl, &deltas[..], attrib);
+            }
         }
 
         //
         //
         match reader.read_indices() {
             Some(ReadIndices::U16(iter)) => {
-                let indicies: Vec<u16> = iter.collect();
+                let mut indicies: Vec<u16> = iter.collect();
+                if self.opts.wireframe {
+                    indicies = triangle_edges(&indicies);
+                }
 
-                buffer_u16_indices(&gl, &indicies[..]);
+                buffer_u16_indices(gl, &indicies[..]);
 
                 BufferedMesh {
                     tri_size: GL::UNSIGNED_SHORT,
                 }
             }
             Some(ReadIndices::U32(iter)) => {
-                let indicies: Vec<u32> = iter.collect();
+                let mut indicies: Vec<u32> = iter.collect();
+                if self.opts.wireframe {
+                    indicies = triangle_edges(&indicies);
+                }
 
-                if let Some(_) = gl.get_extension("OES_element_index_uint").unwrap() {
-                    buffer_u32_indices(&gl, &indicies[..]);
+                if gl
+                    .get_extension("OES_element_index_uint")
+                    .unwrap()
+                    .is_some()
+                {
+                    buffer_u32_indices(gl, &indicies[..]);
 
                     BufferedMesh {
                         tri_size: GL::UNSIGNED_INT,
@@ -100,9 +153,9 @@ impl<'a> Render for NonSkinnedGltfMesh<'a> {
         gl: &WebGl2RenderingContext,
         buffer: &BufferedMesh,
         shader: &Shader,
-        renderer: &WebRenderer,
-        camera: &UniformBuffer<CameraData>,
-        state: &State,
+        _renderer: &WebRenderer,
+        _camera: &UniformBuffer<CameraData>,
+        _state: &State,
     ) {
         let mesh = self.mesh;
         let opts = self.opts;
@@ -113,28 +166,36 @@ impl<'a> Render for NonSkinnedGltfMesh<'a> {
         //let perspective_uni = shader.get_uniform_location(gl, "perspective");
         let clip_plane_uni = shader.get_uniform_location(gl, "clipPlane");
 
-        gl.uniform4fv_with_f32_array(clip_plane_uni.as_ref(), &mut opts.clip_plane.clone()[..]);
+        gl.uniform4fv_with_f32_array(clip_plane_uni.as_ref(), &opts.clip_plane[..]);
 
-        let model = Isometry3::new(opts.pos, opts.rot);
+        let morph_weights_uni = shader.get_uniform_location(gl, "morphWeights");
+        gl.uniform1fv_with_f32_array(morph_weights_uni.as_ref(), &opts.morph_weights);
 
         let mut model_array = [0.; 16];
-        model_array.copy_from_slice(
-            (model.to_homogeneous() * Scale3::from(opts.scale).to_homogeneous()).as_slice(),
-        );
-        gl.uniform_matrix4fv_with_f32_array(model_uni.as_ref(), false, &mut model_array);
-
-        let block_index = shader.get_uniform_block_index(gl, "Camera");
-        camera.bind_base(gl, &shader, block_index, 2);
-
-        gl.draw_elements_with_i32(
-            GL::TRIANGLES,
-            mesh.indices().unwrap().count() as i32,
-            buffer.tri_size,
-            0,
-        );
+        model_array.copy_from_slice(opts.model_mat().as_slice());
+        gl.uniform_matrix4fv_with_f32_array(model_uni.as_ref(), false, &model_array);
+
+        let (mode, count) = match opts.wireframe {
+            // Two indices for each of a triangle's three edges
+            true => (GL::LINES, mesh.indices().unwrap().count() as i32 * 2),
+            false => (GL::TRIANGLES, mesh.indices().unwrap().count() as i32),
+        };
+        if opts.instances > 1 {
+            gl.draw_elements_instanced_with_i32(mode, count, buffer.tri_size, 0, opts.instances);
+        } else {
+            gl.draw_elements_with_i32(mode, count, buffer.tri_size, 0);
+        }
     }
 
     fn render_in_water(&self) -> bool {
         true
     }
 }
+
+/// Line indices for each edge of each triangle, for drawing a wireframe
+fn triangle_edges<T: Copy>(triangles: &[T]) -> Vec<T> {
+    triangles
+        .chunks_exact(3)
+        .flat_map(|t| [t[0], t[1], t[1], t[2], t[2], t[0]])
+        .collect()
+}
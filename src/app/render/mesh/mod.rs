@@ -1,10 +1,10 @@
-pub mod cube;
 pub mod gltf_mesh;
 // mod non_skinned_mesh;
 // mod skinned_mesh;
 
-use nalgebra::Vector3;
+use nalgebra::{Isometry3, Matrix4, Scale3, Vector3};
 
+pub mod skybox;
 pub mod textured_quad;
 pub mod water_tile;
 
@@ -12,10 +12,27 @@ pub use self::gltf_mesh::*;
 // pub use self::non_skinned_mesh::*;
 // pub use self::skinned_mesh::*;
 
+#[derive(Clone, Copy)]
 pub struct MeshRenderOpts {
     pub pos: Vector3<f32>,
     pub rot: Vector3<f32>,
     pub scale: Vector3<f32>,
+    /// The glTF node's transform within its scene, applied before the entity's own
+    pub node: Matrix4<f32>,
+    /// Weights of the primitive's morph targets, zero for those it does not have
+    pub morph_weights: [f32; MAX_MORPH_TARGETS],
     pub clip_plane: [f32; 4],
     pub flip_camera_y: bool,
+    /// Copies drawn in one call, each placed by its transform in the bound `Instances` block
+    pub instances: i32,
+    /// Draw the edges of the triangles instead of filling them
+    pub wireframe: bool,
+}
+
+impl MeshRenderOpts {
+    pub fn model_mat(&self) -> Matrix4<f32> {
+        Isometry3::new(self.pos, self.rot).to_homogeneous()
+            * Scale3::from(self.scale).to_homogeneous()
+            * self.node
+    }
 }
@@ -0,0 +1,80 @@
+use crate::app::render::buffer_sf32_data;
+use crate::app::render::buffer_u16_indices;
+use crate::app::render::rgl::shader::Shader;
+use crate::app::render::rgl::uniform_buffer::UniformBuffer;
+use crate::app::render::BufferedMesh;
+use crate::app::render::CameraData;
+use crate::app::render::Render;
+use crate::app::render::WebRenderer;
+use crate::app::render::ENVIRONMENT_UNIT;
+use crate::app::store::skybox::Skybox;
+use crate::app::State;
+use web_sys::WebGl2RenderingContext as GL;
+use web_sys::*;
+
+impl Render for Skybox {
+    fn buffer_attributes(&self, gl: &GL, shader: &Shader, _state: &State) -> BufferedMesh {
+        let pos_attrib = gl.get_attrib_location(&shader.program, "position");
+        gl.enable_vertex_attrib_array(pos_attrib as u32);
+
+        // Corners of a cube around the camera, the vertex shader pushes it to the far plane
+        let vertices: [[f32; 3]; 8] = [
+            [-1.0, -1.0, 1.0],
+            [1.0, -1.0, 1.0],
+            [1.0, -1.0, -1.0],
+            [-1.0, -1.0, -1.0],
+            [-1.0, 1.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [1.0, 1.0, -1.0],
+            [-1.0, 1.0, -1.0],
+        ];
+
+        // Wound to face inwards, as it is only seen from inside
+        let indices: [u16; 36] = [
+            0, 1, 2, 0, 2, 3, // bottom
+            4, 6, 5, 4, 7, 6, // top
+            0, 5, 1, 0, 4, 5, // front
+            2, 7, 3, 2, 6, 7, // back
+            3, 4, 0, 3, 7, 4, // left
+            1, 6, 2, 1, 5, 6, // right
+        ];
+
+        buffer_sf32_data(gl, &vertices, pos_attrib as u32);
+        buffer_u16_indices(gl, &indices);
+
+        BufferedMesh {
+            tri_size: GL::UNSIGNED_SHORT,
+        }
+    }
+
+    fn render(
+        &self,
+        gl: &WebGl2RenderingContext,
+        buffer: &BufferedMesh,
+        shader: &Shader,
+        _renderer: &WebRenderer,
+        camera: &UniformBuffer<CameraData>,
+        _state: &State,
+    ) {
+        let block_index = shader.get_uniform_block_index(gl, "Camera");
+        camera.bind_base(gl, shader, block_index, 2);
+
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "skybox").as_ref(),
+            ENVIRONMENT_UNIT as i32,
+        );
+
+        // The sky sits exactly on the far plane, and should never hide anything
+        gl.depth_func(GL::LEQUAL);
+        gl.depth_mask(false);
+
+        gl.draw_elements_with_i32(GL::TRIANGLES, 36, buffer.tri_size, 0);
+
+        gl.depth_mask(true);
+        gl.depth_func(GL::LESS);
+    }
+
+    fn render_in_water(&self) -> bool {
+        false
+    }
+}
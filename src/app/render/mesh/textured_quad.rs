@@ -1,3 +1,4 @@
+use crate::app::display::Display;
 use crate::app::render::buffer_f32_data;
 use crate::app::render::WebRenderer;
 use crate::app::State;
@@ -31,7 +32,7 @@ impl TexturedQuad {
         width: u16,
         height: u16,
         texture_unit: TexUnit,
-        shader: Rc<Shader>,
+        _shader: Rc<Shader>,
     ) -> TexturedQuad {
         TexturedQuad {
             left,
@@ -43,6 +44,23 @@ impl TexturedQuad {
     }
 }
 
+impl TexturedQuad {
+    /// Covers the whole viewport, whatever its size, for full screen passes
+    pub fn full_screen(display: &Display, texture_unit: TexUnit) -> TexturedQuad {
+        let width = display.width as u16;
+        let height = display.height as u16;
+
+        // The quad hangs below its top edge, so is raised a whole screen to cover it
+        TexturedQuad {
+            left: 0,
+            top: height * 2,
+            width,
+            height,
+            texture_unit,
+        }
+    }
+}
+
 impl Render for TexturedQuad {
     fn buffer_attributes(&self, gl: &GL, shader: &Shader, state: &State) -> BufferedMesh {
         let vertex_data =
@@ -51,7 +69,7 @@ impl Render for TexturedQuad {
         let vertex_data_attrib = gl.get_attrib_location(&shader.program, "vertexData");
         gl.enable_vertex_attrib_array(vertex_data_attrib as u32);
 
-        buffer_f32_data(&gl, &vertex_data[..], vertex_data_attrib as u32, 4);
+        buffer_f32_data(gl, &vertex_data[..], vertex_data_attrib as u32, 4);
 
         BufferedMesh { tri_size: 0 }
     }
@@ -61,8 +79,8 @@ impl Render for TexturedQuad {
         gl: &WebGl2RenderingContext,
         _: &BufferedMesh,
         shader: &Shader,
-        renderer: &WebRenderer,
-        camera: &UniformBuffer<CameraData>,
+        _renderer: &WebRenderer,
+        _camera: &UniformBuffer<CameraData>,
         state: &State,
     ) {
         gl.uniform1i(
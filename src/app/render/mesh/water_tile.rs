@@ -14,7 +14,7 @@ use web_sys::WebGl2RenderingContext as GL;
 use web_sys::*;
 
 impl Render for Water {
-    fn buffer_attributes(&self, gl: &GL, shader: &Shader, state: &State) -> BufferedMesh {
+    fn buffer_attributes(&self, gl: &GL, shader: &Shader, _state: &State) -> BufferedMesh {
         let pos_attrib = gl.get_attrib_location(&shader.program, "position");
         gl.enable_vertex_attrib_array(pos_attrib as u32);
 
@@ -28,10 +28,10 @@ impl Render for Water {
             [-30.5, -30.5], // Top Left
         ];
 
-        let mut indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
 
-        buffer_sf32_data(&gl, &vertices, pos_attrib as u32);
-        buffer_u16_indices(&gl, &mut indices);
+        buffer_sf32_data(gl, &vertices, pos_attrib as u32);
+        buffer_u16_indices(gl, &indices);
 
         BufferedMesh {
             tri_size: GL::UNSIGNED_SHORT,
@@ -43,9 +43,9 @@ impl Render for Water {
         gl: &WebGl2RenderingContext,
         buffer: &BufferedMesh,
         shader: &Shader,
-        renderer: &WebRenderer,
-        camera: &UniformBuffer<CameraData>,
-        state: &State,
+        _renderer: &WebRenderer,
+        _camera: &UniformBuffer<CameraData>,
+        _state: &State,
     ) {
         let model_uni = shader.get_uniform_location(gl, "model");
 
@@ -60,7 +60,7 @@ impl Render for Water {
         let model = model * scale;
         let mut model_array = [0.; 16];
         model_array.copy_from_slice(model.as_slice());
-        gl.uniform_matrix4fv_with_f32_array(model_uni.as_ref(), false, &mut model_array);
+        gl.uniform_matrix4fv_with_f32_array(model_uni.as_ref(), false, &model_array);
 
         gl.enable(GL::BLEND);
         gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA);
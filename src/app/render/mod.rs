@@ -1,10 +1,13 @@
 pub(self) use self::mesh::*;
 pub(self) use self::render_trait::*;
-use self::rgl::framebuffer::*;
-use self::rgl::texture::TexUnit;
+use self::rgl::framebuffer::ShadowMap;
+use self::rgl::texture::{Tex, TexUnit};
 use self::rgl::uniform_buffer::UniformBuffer;
 use self::rgl::Framebuffer;
-use crate::app::store::water::Water;
+use crate::app::frustum::Frustum;
+use crate::app::store::camera::Camera;
+use crate::app::store::fog::Fog;
+use crate::app::store::sun::Sun;
 use crate::app::Assets;
 use crate::app::State;
 //use crate::canvas::{CANVAS_HEIGHT, CANVAS_WIDTH};
@@ -13,21 +16,41 @@ use crate::app::render::rgl::shader::ShaderSystem;
 use crate::app::render::textured_quad::TexturedQuad;
 use js_sys::WebAssembly;
 use nalgebra::Matrix4;
+use nalgebra::Point3;
 use nalgebra::Point4;
+use nalgebra::Vector4;
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::WebGl2RenderingContext as GL;
 use web_sys::*;
 
 pub static WATER_TILE_Y_POS: f32 = 0.0;
-
+/// Texture unit lit shaders read the sun's shadow map from
+pub static SHADOW_MAP_UNIT: u32 = 7;
+/// Texture unit the sky's cube map is read from
+pub static ENVIRONMENT_UNIT: u32 = 8;
+
+pub mod debug_draw;
+pub mod draw_list;
+pub mod environment;
+pub mod graph;
 pub mod material;
 pub mod mesh;
+pub mod post;
 pub mod render_meshes;
 pub mod render_trait;
 pub mod rgl;
+pub mod shadow;
+pub mod text;
+use debug_draw::DebugLines;
+use environment::Environment;
+use graph::{PassOutput, PassView, RenderGraph};
+use post::PostTargets;
 use rgl::vao::Vao;
+use text::TextQuads;
 
 struct VaoExtension {
     vaos: RefCell<HashMap<String, (Vao, BufferedMesh)>>,
@@ -37,22 +60,106 @@ struct VaoExtension {
 ///```
 ///layout(std140) uniform MatrixBlock
 ///{
-///		mat4 projection;
-///		mat4 view;
-///		vec4 pos;
+///        mat4 projection;
+///        mat4 view;
+///        vec4 pos;
+///        vec4 clip; // near, far, orthographic, unused
+///        vec4 fog; // density (0 without fog), height falloff, height, unused
+///        vec4 fogColor;
 ///} camera;
 ///```
+#[repr(C)]
 pub struct CameraData {
     pub projection: Matrix4<f32>,
     pub view: Matrix4<f32>,
     pub pos: Point4<f32>, // vec4 for padding reasons
+    pub clip: Vector4<f32>,
+    pub fog: Vector4<f32>,
+    pub fog_color: Vector4<f32>,
+}
+
+impl CameraData {
+    pub fn new(camera: &Camera, view: Matrix4<f32>, fog: &Fog) -> CameraData {
+        let p = camera.get_eye_pos();
+        let [r, g, b] = fog.color;
+
+        CameraData {
+            view,
+            projection: *camera.projection_mat(),
+            pos: Point4::new(p.x, p.y, p.z, 0.0),
+            clip: camera.clip_data(),
+            fog: Vector4::new(
+                if fog.enabled { fog.density } else { 0.0 },
+                fog.height_falloff,
+                fog.height,
+                0.0,
+            ),
+            fog_color: Vector4::new(r, g, b, 1.0),
+        }
+    }
+
+    /// Orthographic view along the sun's direction, centred on `center`
+    pub fn for_sun(sun: &Sun, center: Point3<f32>) -> CameraData {
+        let eye = sun.eye(center);
+        let far = sun.shadows.extent * 4.0;
+
+        CameraData {
+            view: sun.view_mat(center),
+            projection: sun.projection_mat(),
+            pos: Point4::new(eye.x, eye.y, eye.z, 0.0),
+            clip: Vector4::new(0.1, far, 1.0, 0.0),
+            fog: Vector4::zeros(),
+            fog_color: Vector4::zeros(),
+        }
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&(self.projection * self.view))
+    }
+}
+
+/// Most joints a skin can have, matching the array in `mesh-skinned-vertex.glsl`
+pub const MAX_JOINTS: usize = 128;
+
+/// Mirrors the glsl:
+///```
+///layout(std140) uniform Joints
+///{
+///        mat4 matrices[128];
+///} joints;
+///```
+#[repr(C)]
+pub struct JointData {
+    pub matrices: [Matrix4<f32>; MAX_JOINTS],
+}
+
+/// Most copies of a mesh drawn in one call, matching the array in `mesh-non-skinned-vertex.glsl`
+pub const MAX_INSTANCES: usize = 128;
+
+/// Mirrors the glsl:
+///```
+///layout(std140) uniform Instances
+///{
+///        mat4 models[128];
+///} instances;
+///```
+#[repr(C)]
+pub struct InstanceData {
+    pub models: [Matrix4<f32>; MAX_INSTANCES],
 }
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum RenderStage {
-    Refraction,
-    Reflection,
-    Water,
-    Opaques,
+
+/// Per frame counters, reset at the start of every `WebRenderer::render`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    /// Primitives that passed frustum culling, summed over every pass
+    pub drawn: u32,
+    /// Primitives skipped by frustum culling, summed over every pass
+    pub culled: u32,
+    /// Calls to `drawElements` and `drawElementsInstanced`
+    pub draw_calls: u32,
+    /// Program switches, material uploads and vertex array binds between mesh draws
+    pub state_changes: u32,
 }
 pub struct WebRenderer {
     pub shader_sys: ShaderSystem,
@@ -62,12 +169,30 @@ pub struct WebRenderer {
     pub reflection_framebuffer: std::rc::Rc<Framebuffer>,
     vao_ext: VaoExtension,
     pub camera_buffer: UniformBuffer<CameraData>,
+    pub unfogged_camera_buffer: UniformBuffer<CameraData>,
     pub flipped_y_camera_buffer: UniformBuffer<CameraData>,
+    pub sun_camera_buffer: UniformBuffer<CameraData>,
+    joint_buffer: UniformBuffer<JointData>,
+    instance_buffer: UniformBuffer<InstanceData>,
+    /// A single identity transform, for drawing one copy without reuploading `instance_buffer`
+    single_instance_buffer: UniformBuffer<InstanceData>,
+    shadow_map: RefCell<ShadowMap>,
+    environment: RefCell<Option<Environment>>,
+    /// Whether a skybox has used the environment since it was last checked
+    environment_used: Cell<bool>,
+    brdf_lut: Tex,
+    post_targets: RefCell<PostTargets>,
+    /// `EXT_color_buffer_float` is enabled, so float textures can be drawn into
+    float_targets: bool,
+    debug_lines: DebugLines,
+    text_quads: TextQuads,
+    pub graph: RenderGraph,
+    pub stats: Cell<RenderStats>,
 }
 
 impl WebRenderer {
     pub fn new(gl: &WebGl2RenderingContext) -> WebRenderer {
-        let shader_sys = ShaderSystem::new(&gl);
+        let shader_sys = ShaderSystem::new(gl);
 
         //let depth_texture_ext = gl
         //    .get_extension("WEBGL_depth_texture")
@@ -78,18 +203,59 @@ impl WebRenderer {
         };
 
         let refraction_framebuffer =
-            std::rc::Rc::new(WebRenderer::create_refraction_framebuffer(&gl).unwrap());
+            std::rc::Rc::new(WebRenderer::create_refraction_framebuffer(gl).unwrap());
         let reflection_framebuffer =
-            std::rc::Rc::new(WebRenderer::create_reflection_framebuffer(&gl).unwrap());
+            std::rc::Rc::new(WebRenderer::create_reflection_framebuffer(gl).unwrap());
+
+        let shadow_map =
+            WebRenderer::create_shadow_map(gl, Sun::default().shadows.resolution).unwrap();
+
+        let graph = RenderGraph::new(
+            refraction_framebuffer.clone(),
+            reflection_framebuffer.clone(),
+        );
+
+        let single_instance_buffer = UniformBuffer::new(gl);
+        single_instance_buffer.buffer(
+            gl,
+            &InstanceData {
+                models: [Matrix4::identity(); MAX_INSTANCES],
+            },
+        );
+
+        let brdf_lut = WebRenderer::create_brdf_lut(gl, &shader_sys);
+
+        let float_targets = matches!(gl.get_extension("EXT_color_buffer_float"), Ok(Some(_)));
+        if !float_targets {
+            log::warn!("EXT_color_buffer_float is not supported, drawing without HDR");
+        }
+
+        // Sized to the canvas on the first frame
+        let post_targets = WebRenderer::create_post_targets(gl, 1, 1, false).unwrap();
 
         WebRenderer {
             //    depth_texture_ext,
             shader_sys,
             refraction_framebuffer,
             reflection_framebuffer,
+            graph,
             vao_ext,
             camera_buffer: UniformBuffer::new(gl),
+            unfogged_camera_buffer: UniformBuffer::new(gl),
             flipped_y_camera_buffer: UniformBuffer::new(gl),
+            sun_camera_buffer: UniformBuffer::new(gl),
+            joint_buffer: UniformBuffer::new(gl),
+            instance_buffer: UniformBuffer::new(gl),
+            single_instance_buffer,
+            shadow_map: RefCell::new(shadow_map),
+            environment: RefCell::new(None),
+            environment_used: Cell::new(false),
+            brdf_lut,
+            post_targets: RefCell::new(post_targets),
+            float_targets,
+            debug_lines: DebugLines::new(gl),
+            text_quads: TextQuads::new(gl),
+            stats: Cell::new(RenderStats::default()),
         }
     }
 
@@ -103,127 +269,102 @@ impl WebRenderer {
         //     }
         // }
 
-        gl.clear_color(0.53, 0.8, 0.98, 1.);
-        gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
-
-        if state.display.changed_this_frame {
-            // This will be the first viewport call of the render cycle
-            // We call it in case the display has changed size
-            gl.viewport(
-                0,
-                0,
-                state.display.width as i32,
-                state.display.height as i32,
-            );
-        }
-        let p = state.camera().get_eye_pos();
-
-        let camera = CameraData {
-            view: state.camera().view_mat(),
-            projection: state.camera().projection_mat().clone(),
-            pos: Point4::new(p.x, p.y, p.z, 0.0),
-        };
+        self.stats.set(RenderStats::default());
 
+        let camera = CameraData::new(state.camera(), state.camera().view_mat(), &state.fog);
         self.camera_buffer.buffer(gl, &camera);
 
-        let above = 1000000.0;
-        // Position is positive instead of negative for.. mathematical reasons..
-        let clip_plane = [0., 1., 0., above];
-
-        self.render_entities(
-            gl,
-            state,
-            assets,
-            &self.camera_buffer,
-            clip_plane,
-            RenderStage::Water,
-        );
-        self.render_entities(
-            gl,
-            state,
-            assets,
-            &self.camera_buffer,
-            clip_plane,
-            RenderStage::Opaques,
-        );
+        for e in &state.entities {
+            e.borrow().prepare(gl, self, assets);
+        }
+        self.release_unused_environment(gl);
 
-        //DEBUG: Display 30 loaded textures
-        self.render_refraction_visual(gl, &self.camera_buffer, state, assets);
-        self.render_reflection_visual(gl, &self.camera_buffer, state, assets);
+        let passes = self.graph.schedule(state);
 
-        let u = TexUnit::new(gl, 10);
-        for i in 0..30 {
-            assets.get_tex(i).bind_at(gl, &u);
-            self.render_visual(gl, &self.camera_buffer, state, assets, u, 70 * i as u16, 70);
+        let mirrored_camera = CameraData::new(
+            state.camera(),
+            state.camera().view_flipped_y_mat(),
+            &state.fog,
+        );
+        if passes.iter().any(|p| p.view == PassView::MirroredY) {
+            self.flipped_y_camera_buffer.buffer(gl, &mirrored_camera);
+        }
+        if passes.iter().any(|p| p.view == PassView::UnfoggedCamera) {
+            let unfogged_camera = CameraData::new(
+                state.camera(),
+                state.camera().view_mat(),
+                &Fog {
+                    enabled: false,
+                    ..state.fog
+                },
+            );
+            self.unfogged_camera_buffer.buffer(gl, &unfogged_camera);
         }
-    }
 
-    pub fn render_refraction_fbo(
-        &self,
-        gl: &WebGl2RenderingContext,
-        water: &Water,
-        camera: &UniformBuffer<CameraData>,
-        state: &State,
-        assets: &Assets,
-        water_tile_y: f32,
-    ) {
-        if water.use_refraction {
-            let framebuffer = &self.refraction_framebuffer.framebuffer;
-            gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
+        // Always buffered, as lit shaders read the sun's matrices even when shadows are off
+        let sun_camera = CameraData::for_sun(&state.sun, state.camera().get_target());
+        self.sun_camera_buffer.buffer(gl, &sun_camera);
 
-            gl.viewport(0, 0, REFRACTION_TEXTURE_WIDTH, REFRACTION_TEXTURE_HEIGHT);
+        self.post_targets(
+            gl,
+            state.display.width as i32,
+            state.display.height as i32,
+            state.post.hdr,
+        );
 
-            gl.clear_color(0.53, 0.8, 0.98, 1.);
-            gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+        // Passes drawing to the canvas go over the finished scene, so wait for post effects
+        let (canvas_passes, scene_passes): (Vec<_>, Vec<_>) = passes
+            .into_iter()
+            .partition(|p| matches!(p.output, PassOutput::Canvas));
 
-            let clip_plane = [0., -1., 0., water_tile_y];
-            self.render_entities(
+        for pass in scene_passes {
+            self.run_pass(
                 gl,
+                pass,
+                &camera,
+                &mirrored_camera,
+                &sun_camera,
                 state,
                 assets,
-                camera,
-                clip_plane,
-                RenderStage::Refraction,
             );
         }
-    }
-
-    pub fn render_reflection_fbo(
-        &self,
-        gl: &WebGl2RenderingContext,
-        water: &Water,
-        camera: &UniformBuffer<CameraData>,
-        state: &State,
-        assets: &Assets,
-        water_tile_y: f32,
-    ) {
-        if water.use_reflection {
-            let framebuffer = &self.reflection_framebuffer.framebuffer;
-            gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
-
-            gl.viewport(0, 0, REFLECTION_TEXTURE_WIDTH, REFLECTION_TEXTURE_HEIGHT);
 
-            gl.clear_color(0.53, 0.8, 0.98, 1.);
-            gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+        self.run_post(gl, state);
 
-            let clip_plane = [0., 1., 0., -water_tile_y];
-            self.render_entities(
+        for pass in canvas_passes {
+            self.run_pass(
                 gl,
+                pass,
+                &camera,
+                &mirrored_camera,
+                &sun_camera,
                 state,
                 assets,
-                camera,
-                clip_plane,
-                RenderStage::Reflection,
             );
         }
+
+        if state.debug.framebuffers {
+            self.render_refraction_visual(gl, &self.camera_buffer, state, assets);
+            self.render_reflection_visual(gl, &self.camera_buffer, state, assets);
+        }
+
+        if state.debug.textures {
+            // The first 30 loaded textures
+            let u = TexUnit::new(gl, 10);
+            for i in 0..30 {
+                assets.get_tex(i).bind_at(gl, &u);
+                self.render_visual(gl, &self.camera_buffer, state, assets, u, 70 * i as u16, 70);
+            }
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn render_visual(
         &self,
         gl: &WebGl2RenderingContext,
         camera: &UniformBuffer<CameraData>,
         state: &State,
-        assets: &Assets,
+        _assets: &Assets,
         tex_unit: TexUnit,
         x: u16,
         y: u16,
@@ -237,7 +378,7 @@ impl WebRenderer {
         let textured_quad = TexturedQuad::new(x, y, 35, 35, tex_unit, quad_shader.clone());
 
         let b = self.prepare_for_render(gl, &textured_quad, quad_shader, "VisualMesh", state);
-        textured_quad.render(gl, &b, quad_shader, &self, camera, state);
+        textured_quad.render(gl, &b, quad_shader, self, camera, state);
     }
 
     fn render_refraction_visual(
@@ -260,6 +401,122 @@ impl WebRenderer {
         self.render_visual(gl, camera, state, assets, TexUnit::new(gl, 5), 140, 140);
     }
 
+    /// Record a primitive as either drawn or culled for this frame's `RenderStats`
+    pub fn count_primitive(&self, drawn: bool) {
+        let mut stats = self.stats.get();
+        if drawn {
+            stats.drawn += 1;
+        } else {
+            stats.culled += 1;
+        }
+        self.stats.set(stats);
+    }
+
+    pub fn count_draw_call(&self) {
+        let mut stats = self.stats.get();
+        stats.draw_calls += 1;
+        self.stats.set(stats);
+    }
+
+    pub fn count_state_change(&self) {
+        let mut stats = self.stats.get();
+        stats.state_changes += 1;
+        self.stats.set(stats);
+    }
+
+    /// Set the sun direction and shadow map uniforms of a lit shader, which must be in use
+    pub fn bind_sun(
+        &self,
+        gl: &WebGl2RenderingContext,
+        shader: &rgl::shader::Shader,
+        state: &State,
+    ) {
+        let sun = &state.sun;
+        let unit = TexUnit::new(gl, SHADOW_MAP_UNIT);
+        self.shadow_map(gl, sun.shadows.resolution)
+            .bind_to_unit(gl, GL::DEPTH_ATTACHMENT, &unit);
+
+        let sun_camera = CameraData::for_sun(sun, state.camera().get_target());
+        let mut light_space = [0.; 16];
+        light_space.copy_from_slice((sun_camera.projection * sun_camera.view).as_slice());
+
+        let strength = if sun.shadows.enabled { 1.0 } else { 0.0 };
+
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "shadowMap").as_ref(),
+            unit.uniti(),
+        );
+        gl.uniform_matrix4fv_with_f32_array(
+            shader.get_uniform_location(gl, "lightSpace").as_ref(),
+            false,
+            &light_space,
+        );
+        gl.uniform3f(
+            shader.get_uniform_location(gl, "sunDirection").as_ref(),
+            sun.direction.x,
+            sun.direction.y,
+            sun.direction.z,
+        );
+        gl.uniform3f(
+            shader.get_uniform_location(gl, "shadowParams").as_ref(),
+            sun.shadows.bias,
+            1.0 / sun.shadows.resolution as f32,
+            strength,
+        );
+    }
+
+    /// Upload a skin's joint matrices and bind them to a skinned shader, which must be in use
+    pub fn bind_joints(
+        &self,
+        gl: &WebGl2RenderingContext,
+        shader: &rgl::shader::Shader,
+        joints: &[Matrix4<f32>],
+    ) {
+        if joints.len() > MAX_JOINTS {
+            log::warn!(
+                "Skin has {} joints, only the first {} are used",
+                joints.len(),
+                MAX_JOINTS
+            );
+        }
+
+        let mut data = JointData {
+            matrices: [Matrix4::identity(); MAX_JOINTS],
+        };
+        for (m, j) in data.matrices.iter_mut().zip(joints) {
+            *m = *j;
+        }
+        self.joint_buffer.buffer(gl, &data);
+
+        let block_index = shader.get_uniform_block_index(gl, "Joints");
+        self.joint_buffer.bind_base(gl, shader, block_index, 3);
+    }
+
+    /// Upload the transforms of each copy of a mesh and bind them to a non-skinned shader,
+    /// which must be in use. An empty slice binds a single identity transform.
+    pub fn bind_instances(
+        &self,
+        gl: &WebGl2RenderingContext,
+        shader: &rgl::shader::Shader,
+        models: &[Matrix4<f32>],
+    ) {
+        let buffer = if models.is_empty() {
+            &self.single_instance_buffer
+        } else {
+            let mut data = InstanceData {
+                models: [Matrix4::identity(); MAX_INSTANCES],
+            };
+            for (m, i) in data.models.iter_mut().zip(models) {
+                *m = *i;
+            }
+            self.instance_buffer.buffer(gl, &data);
+            &self.instance_buffer
+        };
+
+        let block_index = shader.get_uniform_block_index(gl, "Instances");
+        buffer.bind_base(gl, shader, block_index, 4);
+    }
+
     pub fn prepare_for_render(
         &self,
         gl: &WebGl2RenderingContext,
@@ -325,7 +582,26 @@ pub fn buffer_sf32_data<const S: usize>(gl: &GL, data: &[[f32; S]], attrib: u32)
     gl.vertex_attrib_pointer_with_i32(attrib, S as i32, GL::FLOAT, false, 0, 0);
 }
 
+/// Replace the contents of an existing array buffer, for vertices rebuilt every frame
+pub fn stream_data<T>(gl: &GL, buffer: &WebGlBuffer, data: &[T]) {
+    let memory_buffer = wasm_bindgen::memory()
+        .dyn_into::<WebAssembly::Memory>()
+        .unwrap()
+        .buffer();
+
+    let data_location = data.as_ptr() as u32;
+
+    let data_array = js_sys::Uint8Array::new(&memory_buffer).subarray(
+        data_location,
+        data_location + std::mem::size_of_val(data) as u32,
+    );
+
+    gl.bind_buffer(GL::ARRAY_BUFFER, Some(buffer));
+    gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &data_array, GL::DYNAMIC_DRAW);
+}
+
 ///buffer
+#[allow(dead_code)]
 pub fn buffer_u8_data(gl: &GL, data: &[u8], attrib: u32, size: i32) {
     let memory_buffer = wasm_bindgen::memory()
         .dyn_into::<WebAssembly::Memory>()
@@ -0,0 +1,248 @@
+use std::cell::Ref;
+
+use web_sys::WebGl2RenderingContext as GL;
+
+use super::rgl::framebuffer::RenderTarget;
+use super::rgl::shader::{Shader, ShaderKind};
+use super::rgl::texture::{ColorFormat, TexUnit};
+use super::textured_quad::TexturedQuad;
+use super::{Render, WebRenderer};
+use crate::app::store::post::PostEffect;
+use crate::app::State;
+
+/// Texture unit post effects read the previous stage's output from
+static POST_SOURCE_UNIT: u32 = 19;
+/// Texture unit bloom is composited from
+static POST_BLOOM_UNIT: u32 = 20;
+
+/// Everything the scene and its post effects draw into, sized to the canvas
+pub struct PostTargets {
+    /// Where passes drawing to the screen end up, with depth
+    pub scene: RenderTarget,
+    /// Effects alternate between these, each reading the other's output
+    ping_pong: [RenderTarget; 2],
+    /// Half size targets bloom is blurred between
+    bloom: [RenderTarget; 2],
+    /// Colour is stored as floats, so can go past 1
+    hdr: bool,
+}
+
+impl PostTargets {
+    fn delete(&self, gl: &GL) {
+        let [a, b] = &self.ping_pong;
+        let [c, d] = &self.bloom;
+        for target in [&self.scene, a, b, c, d] {
+            target.framebuffer.delete(gl);
+        }
+    }
+}
+
+impl WebRenderer {
+    pub(in crate::app::render) fn create_post_targets(
+        gl: &GL,
+        width: i32,
+        height: i32,
+        hdr: bool,
+    ) -> Result<PostTargets, wasm_bindgen::JsValue> {
+        let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+
+        // Bloom only needs colour, so can use the smaller float format
+        let (color, bloom) = if hdr {
+            (ColorFormat::Rgba16F, ColorFormat::R11fG11fB10f)
+        } else {
+            (ColorFormat::Rgba8, ColorFormat::Rgba8)
+        };
+
+        Ok(PostTargets {
+            scene: WebRenderer::create_render_target(gl, width, height, color, true)?,
+            ping_pong: [
+                WebRenderer::create_render_target(gl, width, height, color, false)?,
+                WebRenderer::create_render_target(gl, width, height, color, false)?,
+            ],
+            bloom: [
+                WebRenderer::create_render_target(gl, half_width, half_height, bloom, false)?,
+                WebRenderer::create_render_target(gl, half_width, half_height, bloom, false)?,
+            ],
+            hdr,
+        })
+    }
+
+    /// The post targets, recreated first if the canvas has changed size or `hdr` has changed.
+    /// Without float colour buffers the targets stay 8 bit whatever `hdr` is.
+    pub fn post_targets(&self, gl: &GL, width: i32, height: i32, hdr: bool) -> Ref<PostTargets> {
+        let hdr = hdr && self.float_targets;
+        let resize = {
+            let targets = self.post_targets.borrow();
+            targets.scene.width != width || targets.scene.height != height || targets.hdr != hdr
+        };
+        if resize {
+            match WebRenderer::create_post_targets(gl, width, height, hdr) {
+                Ok(targets) => self.post_targets.replace(targets).delete(gl),
+                Err(e) => log::error!("Could not resize post targets: {:?}", e),
+            }
+        }
+
+        self.post_targets.borrow()
+    }
+
+    /// Draw the scene target to the canvas through every enabled post effect
+    pub(in crate::app::render) fn run_post(&self, gl: &GL, state: &State) {
+        let targets = self.post_targets.borrow();
+        let chain = state.post.chain();
+
+        targets.scene.framebuffer.resolve(gl);
+
+        gl.disable(GL::DEPTH_TEST);
+
+        if chain.is_empty() {
+            self.draw_post(
+                gl,
+                ShaderKind::TexturedQuad,
+                &targets.scene,
+                None,
+                state,
+                |_| (),
+            );
+        }
+
+        let mut source = &targets.scene;
+        for (i, effect) in chain.iter().enumerate() {
+            let output = if i + 1 == chain.len() {
+                None
+            } else {
+                Some(&targets.ping_pong[i % 2])
+            };
+
+            match effect {
+                PostEffect::Bloom => {
+                    let bloom = &state.post.bloom;
+                    let [a, b] = &targets.bloom;
+
+                    self.draw_post(gl, ShaderKind::BloomExtract, source, Some(a), state, |s| {
+                        gl.uniform1f(
+                            s.get_uniform_location(gl, "threshold").as_ref(),
+                            bloom.threshold,
+                        );
+                    });
+                    self.draw_post(gl, ShaderKind::Blur, a, Some(b), state, |s| {
+                        gl.uniform2f(
+                            s.get_uniform_location(gl, "direction").as_ref(),
+                            1.0 / a.width as f32,
+                            0.0,
+                        );
+                    });
+                    self.draw_post(gl, ShaderKind::Blur, b, Some(a), state, |s| {
+                        gl.uniform2f(
+                            s.get_uniform_location(gl, "direction").as_ref(),
+                            0.0,
+                            1.0 / b.height as f32,
+                        );
+                    });
+
+                    let unit = TexUnit::new(gl, POST_BLOOM_UNIT);
+                    a.framebuffer.bind_to_unit(gl, GL::COLOR_ATTACHMENT0, &unit);
+                    self.draw_post(gl, ShaderKind::BloomComposite, source, output, state, |s| {
+                        gl.uniform1i(
+                            s.get_uniform_location(gl, "bloomTexture").as_ref(),
+                            unit.uniti(),
+                        );
+                        gl.uniform1f(
+                            s.get_uniform_location(gl, "strength").as_ref(),
+                            bloom.strength,
+                        );
+                    });
+                }
+                PostEffect::ToneMapping => {
+                    let exposure = state.post.tone_mapping.exposure;
+                    self.draw_post(gl, ShaderKind::ToneMapping, source, output, state, |s| {
+                        gl.uniform1f(s.get_uniform_location(gl, "exposure").as_ref(), exposure);
+                    });
+                }
+                PostEffect::ColourGrading => {
+                    let grading = &state.post.grading;
+                    self.draw_post(gl, ShaderKind::ColourGrading, source, output, state, |s| {
+                        gl.uniform1f(
+                            s.get_uniform_location(gl, "saturation").as_ref(),
+                            grading.saturation,
+                        );
+                        gl.uniform1f(
+                            s.get_uniform_location(gl, "contrast").as_ref(),
+                            grading.contrast,
+                        );
+                        let [r, g, b] = grading.tint;
+                        gl.uniform3f(s.get_uniform_location(gl, "tint").as_ref(), r, g, b);
+                    });
+                }
+                PostEffect::Fxaa => {
+                    self.draw_post(gl, ShaderKind::Fxaa, source, output, state, |s| {
+                        gl.uniform2f(
+                            s.get_uniform_location(gl, "texelSize").as_ref(),
+                            1.0 / source.width as f32,
+                            1.0 / source.height as f32,
+                        );
+                    });
+                }
+                PostEffect::Vignette => {
+                    let vignette = &state.post.vignette;
+                    self.draw_post(gl, ShaderKind::Vignette, source, output, state, |s| {
+                        gl.uniform1f(
+                            s.get_uniform_location(gl, "strength").as_ref(),
+                            vignette.strength,
+                        );
+                        gl.uniform1f(
+                            s.get_uniform_location(gl, "radius").as_ref(),
+                            vignette.radius,
+                        );
+                    });
+                }
+            }
+
+            if let Some(output) = output {
+                source = output;
+            }
+        }
+
+        gl.enable(GL::DEPTH_TEST);
+    }
+
+    /// Draw `source` over all of `output`, or the canvas if `None`, with a full screen program.
+    /// `uniforms` sets anything the program needs beyond the source texture.
+    fn draw_post(
+        &self,
+        gl: &GL,
+        kind: ShaderKind,
+        source: &RenderTarget,
+        output: Option<&RenderTarget>,
+        state: &State,
+        uniforms: impl FnOnce(&Shader),
+    ) {
+        match output {
+            Some(target) => {
+                gl.bind_framebuffer(GL::FRAMEBUFFER, target.framebuffer.framebuffer.as_ref());
+                gl.viewport(0, 0, target.width, target.height);
+            }
+            None => {
+                gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+                gl.viewport(
+                    0,
+                    0,
+                    state.display.width as i32,
+                    state.display.height as i32,
+                );
+            }
+        }
+
+        let unit = TexUnit::new(gl, POST_SOURCE_UNIT);
+        source
+            .framebuffer
+            .bind_to_unit(gl, GL::COLOR_ATTACHMENT0, &unit);
+
+        let shader = self.shader_sys.get_shader(&kind).unwrap();
+        self.shader_sys.use_program(gl, kind);
+        uniforms(shader);
+
+        let quad = TexturedQuad::full_screen(&state.display, unit);
+        let b = self.prepare_for_render(gl, &quad, shader, "PostQuad", state);
+        quad.render(gl, &b, shader, self, &self.camera_buffer, state);
+    }
+}
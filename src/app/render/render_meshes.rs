@@ -1,14 +1,19 @@
 // use crate::app::render::mesh::MeshRenderOpts;
 // use crate::app::render::mesh::NonSkinnedMesh;
 // use crate::app::render::mesh::SkinnedMesh;
+use crate::app::frustum::Frustum;
 use crate::app::render::WebRenderer;
+use crate::app::store::entity::Entity;
+use crate::app::store::Mesh;
 use crate::Assets;
 use crate::State;
+use std::cell::Ref;
 use web_sys::WebGl2RenderingContext as GL;
 
+use super::draw_list::DrawList;
+use super::graph::RenderPass;
 use super::rgl::uniform_buffer::UniformBuffer;
 use super::CameraData;
-use super::RenderStage;
 
 // static BIRD_SPEED: f32 = 3.5;
 // static BIRD_START_Z: f32 = -30.0;
@@ -21,20 +26,46 @@ impl WebRenderer {
         state: &State,
         assets: &Assets,
         camera: &UniformBuffer<CameraData>,
-        clip_plane: [f32; 4],
-        stage: RenderStage,
+        frustum: &Frustum,
+        pass: &RenderPass,
     ) {
         if !state.show_scenery() {
             return;
         }
 
-        for entity in &state.entities {
-            let e = entity.borrow();
-            if e.should_render(&stage) {
-                e.render(gl, &self, camera, clip_plane.clone(), stage, state, assets);
+        let entities: Vec<Ref<dyn Entity>> = state
+            .entities
+            .iter()
+            .map(|e| e.borrow())
+            .filter(|e| e.layers().intersects(pass.filter))
+            .collect();
+
+        // Collected into one list so draws sharing a program, material or mesh run together
+        let mut list = DrawList::default();
+        let eye = state.camera().get_eye_pos();
+
+        // Meshes sharing a glTF and material are drawn together with instancing
+        let mut instanced: Vec<((usize, usize), Vec<&Mesh>)> = Vec::new();
+
+        for e in &entities {
+            match e.as_mesh() {
+                Some(m) if m.instanceable() => {
+                    let key = (m.mesh, m.mat);
+                    match instanced.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, meshes)) => meshes.push(m),
+                        None => instanced.push((key, vec![m])),
+                    }
+                }
+                Some(m) => Mesh::queue_draws(&[m], &mut list, self, frustum, pass, eye, assets),
+                None => e.render(gl, self, camera, frustum, pass, state, assets),
             }
         }
 
+        for (_, meshes) in &instanced {
+            Mesh::queue_draws(meshes, &mut list, self, frustum, pass, eye, assets);
+        }
+        self.draw_list(gl, list, camera, pass, state, assets);
+
         // let non_skinned_shader = self
         //     .shader_sys
         //     .get_shader(&ShaderKind::NonSkinnedMesh)
@@ -8,6 +8,7 @@ pub static REFLECTION_TEXTURE_HEIGHT: i32 = 128;
 pub static REFRACTION_TEXTURE_WIDTH: i32 = 512;
 pub static REFRACTION_TEXTURE_HEIGHT: i32 = 512;
 
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 
 use crate::app::render::WebRenderer;
@@ -16,6 +17,7 @@ use web_sys::WebGl2RenderingContext as GL;
 use web_sys::*;
 
 use super::renderbuffer::Renderbuffer;
+use super::texture::ColorFormat;
 use super::texture::Tex;
 use super::texture::TexFilter;
 use super::texture::TexUnit;
@@ -23,6 +25,33 @@ use super::texture::TexUnit;
 pub struct Framebuffer {
     pub framebuffer: Option<WebGlFramebuffer>,
     pub textures: HashMap<u32, Tex>,
+    /// Format of the colour texture, which multisampled colour must match to be resolved into it
+    pub color_format: ColorFormat,
+    /// Drawn into instead of `textures` when multisampling, until resolved
+    multisample: RefCell<Option<Multisample>>,
+    /// (samples, width, height) last passed to `set_samples`
+    requested: Cell<(i32, i32, i32)>,
+}
+
+/// Multisampled copies of a framebuffer's attachments
+struct Multisample {
+    framebuffer: Option<WebGlFramebuffer>,
+    renderbuffers: Vec<Renderbuffer>,
+    width: i32,
+    height: i32,
+}
+
+/// Depth only framebuffer the sun's shadow pass renders into
+pub struct ShadowMap {
+    pub framebuffer: Framebuffer,
+    pub resolution: i32,
+}
+
+/// Colour texture, and optionally a depth texture, to draw into and then sample
+pub struct RenderTarget {
+    pub framebuffer: Framebuffer,
+    pub width: i32,
+    pub height: i32,
 }
 
 pub struct FramebufferBind<'a> {
@@ -43,7 +72,125 @@ impl Framebuffer {
         Framebuffer {
             framebuffer,
             textures: HashMap::new(),
+            color_format: ColorFormat::Rgba8,
+            multisample: RefCell::new(None),
+            requested: Cell::new((1, 0, 0)),
+        }
+    }
+
+    /// Most samples per pixel a multisampled renderbuffer can have
+    pub fn max_samples(gl: &WebGl2RenderingContext) -> i32 {
+        gl.get_parameter(GL::MAX_SAMPLES)
+            .ok()
+            .and_then(|s| s.as_f64())
+            .unwrap_or(1.0) as i32
+    }
+
+    /// Draw with `samples` samples per pixel, clamped to `max_samples`, from the next
+    /// `bind_draw` on. 1 or less draws straight into the textures.
+    pub fn set_samples(&self, gl: &WebGl2RenderingContext, width: i32, height: i32, samples: i32) {
+        let samples = samples.max(1);
+        if self.requested.replace((samples, width, height)) == (samples, width, height) {
+            return;
+        }
+
+        let mut multisample = self.multisample.borrow_mut();
+        if let Some(old) = multisample.take() {
+            old.delete(gl);
+        }
+
+        if samples == 1 {
+            return;
+        }
+        let clamped = samples.min(Framebuffer::max_samples(gl));
+        if clamped <= 1 {
+            log::warn!("Multisampling is not supported, drawing with one sample");
+            return;
+        }
+
+        let framebuffer = gl.create_framebuffer();
+        gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
+
+        let mut renderbuffers = Vec::new();
+        if self.textures.contains_key(&GL::COLOR_ATTACHMENT0) {
+            let color = Renderbuffer::new_multisampled(
+                gl,
+                width,
+                height,
+                self.color_format.internal_format(),
+                clamped,
+            );
+            gl.framebuffer_renderbuffer(
+                GL::FRAMEBUFFER,
+                GL::COLOR_ATTACHMENT0,
+                GL::RENDERBUFFER,
+                color.rb.as_ref(),
+            );
+            renderbuffers.push(color);
+        }
+        // Always needed to draw, even when the depth is not kept
+        let depth =
+            Renderbuffer::new_multisampled(gl, width, height, GL::DEPTH_COMPONENT16, clamped);
+        gl.framebuffer_renderbuffer(
+            GL::FRAMEBUFFER,
+            GL::DEPTH_ATTACHMENT,
+            GL::RENDERBUFFER,
+            depth.rb.as_ref(),
+        );
+        renderbuffers.push(depth);
+
+        gl.bind_renderbuffer(GL::RENDERBUFFER, None);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+        *multisample = Some(Multisample {
+            framebuffer,
+            renderbuffers,
+            width,
+            height,
+        });
+    }
+
+    /// Bind the framebuffer draws should go to, which is the multisampled one if there is one
+    pub fn bind_draw(&self, gl: &WebGl2RenderingContext) {
+        match &*self.multisample.borrow() {
+            Some(m) => gl.bind_framebuffer(GL::FRAMEBUFFER, m.framebuffer.as_ref()),
+            None => gl.bind_framebuffer(GL::FRAMEBUFFER, self.framebuffer.as_ref()),
+        }
+    }
+
+    /// Average the multisampled draws into the textures, so they can be sampled.
+    /// Does nothing without multisampling.
+    pub fn resolve(&self, gl: &WebGl2RenderingContext) {
+        let multisample = self.multisample.borrow();
+        let m = match &*multisample {
+            Some(m) => m,
+            None => return,
+        };
+
+        let mut mask = 0;
+        if self.textures.contains_key(&GL::COLOR_ATTACHMENT0) {
+            mask |= GL::COLOR_BUFFER_BIT;
+        }
+        if self.textures.contains_key(&GL::DEPTH_ATTACHMENT) {
+            mask |= GL::DEPTH_BUFFER_BIT;
         }
+
+        gl.bind_framebuffer(GL::READ_FRAMEBUFFER, m.framebuffer.as_ref());
+        gl.bind_framebuffer(GL::DRAW_FRAMEBUFFER, self.framebuffer.as_ref());
+        gl.blit_framebuffer(
+            0,
+            0,
+            m.width,
+            m.height,
+            0,
+            0,
+            m.width,
+            m.height,
+            mask,
+            GL::NEAREST,
+        );
+        gl.bind_framebuffer(GL::READ_FRAMEBUFFER, None);
+        gl.bind_framebuffer(GL::DRAW_FRAMEBUFFER, None);
     }
 
     pub fn bind_to_unit(&self, gl: &WebGl2RenderingContext, tex: u32, unit: &TexUnit) {
@@ -54,6 +201,26 @@ impl Framebuffer {
         gl.bind_framebuffer(GL::FRAMEBUFFER, self.framebuffer.as_ref());
         FramebufferBind { fb: self, gl }
     }
+
+    /// Free the framebuffer with its textures and multisampled copies
+    pub fn delete(&self, gl: &WebGl2RenderingContext) {
+        if let Some(m) = self.multisample.take() {
+            m.delete(gl);
+        }
+        for tex in self.textures.values() {
+            tex.delete(gl);
+        }
+        gl.delete_framebuffer(self.framebuffer.as_ref());
+    }
+}
+
+impl Multisample {
+    fn delete(self, gl: &WebGl2RenderingContext) {
+        for rb in &self.renderbuffers {
+            rb.delete(gl);
+        }
+        gl.delete_framebuffer(self.framebuffer.as_ref());
+    }
 }
 
 impl<'a> FramebufferBind<'a> {
@@ -94,6 +261,7 @@ impl WebRenderer {
                 REFRACTION_TEXTURE_WIDTH,
                 REFRACTION_TEXTURE_HEIGHT,
                 TexFilter::Nearest,
+                ColorFormat::Rgba8,
             )?;
 
             let depth_texture =
@@ -120,6 +288,7 @@ impl WebRenderer {
                 REFLECTION_TEXTURE_WIDTH,
                 REFLECTION_TEXTURE_HEIGHT,
                 TexFilter::Linear,
+                ColorFormat::Rgba8,
             )?;
 
             let renderbuffer =
@@ -133,4 +302,49 @@ impl WebRenderer {
         }
         Ok(framebuffer)
     }
+
+    pub(in crate::app::render) fn create_shadow_map(
+        gl: &WebGl2RenderingContext,
+        resolution: i32,
+    ) -> Result<ShadowMap, JsValue> {
+        let mut framebuffer = Framebuffer::new(gl);
+        {
+            let mut fb = framebuffer.bind(gl);
+
+            let depth_texture = Tex::new_depth(gl, resolution, resolution)?;
+            fb.texture_2d(depth_texture, GL::DEPTH_ATTACHMENT);
+        }
+        Ok(ShadowMap {
+            framebuffer,
+            resolution,
+        })
+    }
+
+    pub(in crate::app::render) fn create_render_target(
+        gl: &WebGl2RenderingContext,
+        width: i32,
+        height: i32,
+        format: ColorFormat,
+        depth: bool,
+    ) -> Result<RenderTarget, JsValue> {
+        let mut framebuffer = Framebuffer::new(gl);
+        framebuffer.color_format = format;
+        {
+            let mut fb = framebuffer.bind(gl);
+
+            let color_texture = Tex::new_color(gl, width, height, TexFilter::Linear, format)?;
+            color_texture.clamp_to_edge(gl);
+            fb.texture_2d(color_texture, GL::COLOR_ATTACHMENT0);
+
+            if depth {
+                let depth_texture = Tex::new_depth(gl, width, height)?;
+                fb.texture_2d(depth_texture, GL::DEPTH_ATTACHMENT);
+            }
+        }
+        Ok(RenderTarget {
+            framebuffer,
+            width,
+            height,
+        })
+    }
 }
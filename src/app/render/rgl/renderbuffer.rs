@@ -17,6 +17,32 @@ impl Renderbuffer {
 
         rb
     }
+
+    /// Storage for `samples` samples per pixel, to draw into and then resolve into a texture
+    pub fn new_multisampled(
+        gl: &GL,
+        width: i32,
+        height: i32,
+        internal_format: u32,
+        samples: i32,
+    ) -> Renderbuffer {
+        let rb = Renderbuffer {
+            rb: gl.create_renderbuffer(),
+        };
+
+        gl.bind_renderbuffer(GL::RENDERBUFFER, rb.rb.as_ref());
+
+        gl.renderbuffer_storage_multisample(
+            GL::RENDERBUFFER,
+            samples,
+            internal_format,
+            width,
+            height,
+        );
+
+        rb
+    }
+
     pub fn delete(&self, gl: &GL) {
         gl.delete_renderbuffer(self.rb.as_ref())
     }
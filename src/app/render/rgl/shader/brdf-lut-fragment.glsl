@@ -0,0 +1,65 @@
+#version 300 es
+
+precision highp float;
+
+// x is n dot v, y is roughness
+in vec2 vUv;
+
+out vec4 fragColor;
+
+const float PI = 3.14159265;
+const uint SAMPLES = 256u;
+
+// Van der Corput sequence, for evenly spread samples
+float radicalInverse(uint bits) {
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+// Half vector around +Z, spread like the GGX distribution of microfacets
+vec3 importanceSampleGgx(vec2 xi, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    return vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+}
+
+// Smith's shadowing-masking with the remapping used for image based lighting
+float geometry(float nDotV, float nDotL, float roughness) {
+    float k = roughness * roughness / 2.0;
+    return nDotV / (nDotV * (1.0 - k) + k) * nDotL / (nDotL * (1.0 - k) + k);
+}
+
+// Scale and bias to f0 of the specular reflected from a uniformly lit environment
+void main(void) {
+    float nDotV = max(vUv.x, 0.001);
+    float roughness = vUv.y;
+    vec3 v = vec3(sqrt(1.0 - nDotV * nDotV), 0.0, nDotV);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint i = 0u; i < SAMPLES; i++) {
+        vec2 xi = vec2(float(i) / float(SAMPLES), radicalInverse(i));
+        vec3 h = importanceSampleGgx(xi, roughness);
+        vec3 l = normalize(2.0 * dot(v, h) * h - v);
+
+        float nDotL = max(l.z, 0.0);
+        if (nDotL > 0.0) {
+            float nDotH = max(h.z, 0.0);
+            float vDotH = max(dot(v, h), 0.0);
+
+            float visibility = geometry(nDotV, nDotL, roughness) * vDotH / (nDotH * nDotV);
+            float fc = pow(1.0 - vDotH, 5.0);
+            scale += (1.0 - fc) * visibility;
+            bias += fc * visibility;
+        }
+    }
+
+    fragColor = vec4(scale / float(SAMPLES), bias / float(SAMPLES), 0.0, 1.0);
+}
@@ -0,0 +1,38 @@
+#version 300 es
+
+precision highp float;
+
+in vec2 vUv;
+
+uniform sampler2D source;
+// Face being drawn, in the order +X, -X, +Y, -Y, +Z, -Z
+uniform int face;
+// Wrap the source around the sphere by longitude and latitude, instead of copying it to the face
+uniform bool equirect;
+
+out vec4 fragColor;
+
+const float PI = 3.14159265;
+
+// Direction through a point on the face, from -1 to 1 on each axis
+vec3 faceDirection(vec2 st) {
+    if (face == 0) return vec3(1.0, -st.y, -st.x);
+    if (face == 1) return vec3(-1.0, -st.y, st.x);
+    if (face == 2) return vec3(st.x, 1.0, st.y);
+    if (face == 3) return vec3(st.x, -1.0, -st.y);
+    if (face == 4) return vec3(st.x, -st.y, 1.0);
+    return vec3(-st.x, -st.y, -1.0);
+}
+
+void main(void) {
+    if (!equirect) {
+        fragColor = texture(source, vUv);
+        return;
+    }
+
+    vec3 d = normalize(faceDirection(vUv * 2.0 - 1.0));
+    vec2 uv = vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+
+    // Longitude wraps around at the seam, which would pick the smallest mip level
+    fragColor = textureLod(source, uv, 0.0);
+}
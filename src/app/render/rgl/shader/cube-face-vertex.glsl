@@ -0,0 +1,11 @@
+#version 300 es
+
+// Covers the viewport with one triangle, so needs no vertex buffers
+out vec2 vUv;
+
+void main (void) {
+  vec2 corner = vec2(gl_VertexID == 1 ? 3.0 : -1.0, gl_VertexID == 2 ? 3.0 : -1.0);
+
+  vUv = corner * 0.5 + 0.5;
+  gl_Position = vec4(corner, 0.0, 1.0);
+}
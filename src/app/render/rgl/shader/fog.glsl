@@ -0,0 +1,21 @@
+// Joined into fragment shaders in place of `#include <fog>`, which must come after their
+// Camera uniform block
+
+// Fraction of light from worldPos scattered away by fog before reaching the camera.
+// Density falls off exponentially with height, integrated along the view ray.
+float fogAmount(highp vec3 worldPos) {
+    highp float density = camera.fog.x;
+    if (density <= 0.0) {
+        return 0.0;
+    }
+
+    highp vec3 ray = worldPos - camera.pos.xyz;
+    highp float falloff = camera.fog.y;
+
+    highp float amount = length(ray) * density * exp(-falloff * (camera.pos.y - camera.fog.z));
+    if (falloff > 0.0 && abs(ray.y) > 0.001) {
+        amount *= (1.0 - exp(-falloff * ray.y)) / (falloff * ray.y);
+    }
+
+    return 1.0 - exp(-amount);
+}
@@ -0,0 +1,52 @@
+#version 300 es
+
+precision highp float;
+
+in vec2 vUv;
+
+uniform samplerCube source;
+// Face being drawn, in the order +X, -X, +Y, -Y, +Z, -Z
+uniform int face;
+// Mip level of the source to read, close to the size of the output
+uniform float sourceLod;
+
+out vec4 fragColor;
+
+const float PI = 3.14159265;
+
+// Direction through a point on the face, from -1 to 1 on each axis
+vec3 faceDirection(vec2 st) {
+    if (face == 0) return vec3(1.0, -st.y, -st.x);
+    if (face == 1) return vec3(-1.0, -st.y, st.x);
+    if (face == 2) return vec3(st.x, 1.0, st.y);
+    if (face == 3) return vec3(st.x, -1.0, -st.y);
+    if (face == 4) return vec3(st.x, -st.y, 1.0);
+    return vec3(-st.x, -st.y, -1.0);
+}
+
+vec3 toLinear(vec3 srgb) {
+    return pow(srgb, vec3(2.2));
+}
+
+// Cosine weighted sum of the light arriving over the hemisphere around each direction
+void main(void) {
+    vec3 n = normalize(faceDirection(vUv * 2.0 - 1.0));
+    vec3 up = abs(n.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, n));
+    up = cross(n, right);
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += 0.1) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += 0.1) {
+            vec3 t = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 d = t.x * right + t.y * up + t.z * n;
+
+            irradiance += toLinear(textureLod(source, d, sourceLod).rgb) * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+    irradiance = PI * irradiance / samples;
+
+    fragColor = vec4(pow(irradiance, vec3(1.0 / 2.2)), 1.0);
+}
@@ -5,6 +5,7 @@ precision mediump float;
 in vec3 vNormal;
 in vec4 vTangent;
 in vec4 vWorldPos;
+in highp vec4 vLightPos;
 
 in vec2 vUvs;
 
@@ -16,43 +17,171 @@ uniform vec4 clipPlane;
 
 in vec3 sunlightDirTangent;
 
-float shininess = 0.4;
+const float PI = 3.14159265;
 
-vec3 sunlightColor = vec3(1.0, 1.0, 1.0);
-vec3 sunlightDir = normalize(vec3(-1.0, -1.0, 0.5));
+// Bright enough that a white diffuse surface facing the sun is fully lit
+vec3 sunlightColor = vec3(PI);
+vec3 ambientColor = vec3(0.05);
+uniform vec3 sunDirection;
 
+// glTF metallic-roughness material. Colour textures are sRGB, everything else linear.
 uniform sampler2D meshTexture;
+uniform vec4 baseColorFactor;
+uniform sampler2D metallicRoughnessTexture;
+uniform float metallicFactor;
+uniform float roughnessFactor;
 uniform sampler2D meshNormal;
+uniform sampler2D occlusionTexture;
+uniform float occlusionStrength;
+uniform sampler2D emissiveTexture;
+uniform vec3 emissiveFactor;
+// 0 opaque, 1 masked below alphaCutoff, 2 blended
+uniform int alphaMode;
+uniform float alphaCutoff;
+
+// Image based lighting from the sky
+uniform samplerCube irradianceMap;
+uniform samplerCube prefilteredMap;
+uniform sampler2D brdfLut;
+// strength (0 without an environment), highest mip level of prefilteredMap
+uniform vec2 environmentParams;
+
+// 0 lit, 1 surface normals, 2 overdraw
+uniform int debugView;
+
+uniform highp sampler2D shadowMap;
+// bias, size of one shadow map texel, strength (0 with shadows off)
+uniform highp vec3 shadowParams;
+
+// highp to match the vertex shader's declaration of the block
+layout(std140) uniform Camera
+{
+  highp mat4 projection;
+  highp mat4 view;
+  highp vec4 pos;
+  highp vec4 clip; // near, far, orthographic, unused
+  highp vec4 fog; // density (0 without fog), height falloff, height, unused
+  highp vec4 fogColor;
+} camera;
 
 out vec4 fragColor;
 
- 
+// Fraction of the 3x3 texels around this fragment that it is in shadow of
+float shadow() {
+    highp vec3 lightPos = vLightPos.xyz / vLightPos.w * 0.5 + 0.5;
+
+    // Outside the shadow map nothing is known, so treat it as lit
+    if (lightPos.z > 1.0 || any(lessThan(lightPos.xy, vec2(0.0))) || any(greaterThan(lightPos.xy, vec2(1.0)))) {
+        return 0.0;
+    }
+
+    float shadowed = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            highp float depth = texture(shadowMap, lightPos.xy + vec2(x, y) * shadowParams.y).r;
+            shadowed += lightPos.z - shadowParams.x > depth ? 1.0 : 0.0;
+        }
+    }
+
+    return shadowed / 9.0 * shadowParams.z;
+}
+
+// fogAmount, from fog.glsl
+#include <fog>
+
+vec3 toLinear(vec3 srgb) {
+    return pow(srgb, vec3(2.2));
+}
+
+// Trowbridge-Reitz GGX normal distribution
+highp float distribution(highp float nDotH, highp float roughness) {
+    highp float a2 = roughness * roughness * roughness * roughness;
+    highp float d = nDotH * nDotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith's shadowing-masking with Schlick-GGX, for both the light and view directions
+float geometry(float nDotV, float nDotL, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return nDotV / (nDotV * (1.0 - k) + k) * nDotL / (nDotL * (1.0 - k) + k);
+}
+
+vec3 fresnel(float cosTheta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// Fresnel averaged over rough microfacets, for light from every direction at once
+vec3 fresnelRoughness(float cosTheta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - cosTheta, 5.0);
+}
 
 
 void main(void) {
 
 
-    vec4 textureColor = texture(meshTexture, vUvs);
+    // The factor is already linear, so only the texture is converted
+    vec4 tex = texture(meshTexture, vUvs);
+    vec3 albedo = toLinear(tex.rgb) * baseColorFactor.rgb;
+    float alpha = tex.a * baseColorFactor.a;
 
 
-    if (dot(vWorldPos, clipPlane) < 0.0 || textureColor.a < 0.9) {
+    if (dot(vWorldPos, clipPlane) < 0.0 || (alphaMode == 1 && alpha < alphaCutoff)) {
         discard;
     }
 
+    // Drawn additively, so each surface covering the pixel brightens it
+    if (debugView == 2) {
+        fragColor = vec4(0.1, 0.04, 0.01, 1.0);
+        return;
+    }
+
+    vec3 metallicRoughness = texture(metallicRoughnessTexture, vUvs).rgb;
+    float roughness = clamp(metallicRoughness.g * roughnessFactor, 0.04, 1.0);
+    float metallic = clamp(metallicRoughness.b * metallicFactor, 0.0, 1.0);
+
+    float occlusion = 1.0 + occlusionStrength * (texture(occlusionTexture, vUvs).r - 1.0);
+    vec3 emissive = toLinear(texture(emissiveTexture, vUvs).rgb) * emissiveFactor;
+
     vec3 textureNormal = normalize(texture(meshNormal, vUvs).xyz * 2.0 - 1.0);
-    vec3 normal = TBN * textureNormal.xyz;
+    vec3 n = normalize(TBN * textureNormal.xyz);
+    vec3 v = normalize(fromFragmentToCamera);
+
+    if (debugView == 1) {
+        fragColor = vec4(n * 0.5 + 0.5, 1.0);
+        return;
+    }
+    vec3 l = -sunDirection;
+    vec3 h = normalize(v + l);
+
+    float nDotL = max(dot(n, l), 0.0);
+    float nDotV = max(dot(n, v), 0.001);
 
-    vec3 ambient = vec3(0.24725, 0.1995, 0.0745);
+    // Cook-Torrance specular, with dielectrics reflecting 4% head on
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    vec3 f = fresnel(max(dot(h, v), 0.0), f0);
+    vec3 specular = distribution(max(dot(n, h), 0.0), roughness) * geometry(nDotV, nDotL, roughness) * f
+        / (4.0 * nDotV * nDotL + 0.001);
 
-    float diff = max(dot(normal, -sunlightDir), 0.0);
-    vec3 diffuse = diff * sunlightColor;
+    // Metals have no diffuse, and light reflected specularly is not also diffused
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo / PI;
 
-    vec3 reflectDir = reflect(-sunlightDir, normal);
-    float spec = pow(max(dot(normalize(fromFragmentToCamera), reflectDir), 0.0), 32.0);
-    vec3 specular = shininess * spec * vec3(0.628281, 0.555802, 0.366065);
+    float lit = 1.0 - shadow();
+    vec3 direct = lit * (diffuse + specular) * sunlightColor * nDotL;
+    vec3 ambient = ambientColor * albedo * occlusion;
+    if (environmentParams.x > 0.0) {
+        vec3 kS = fresnelRoughness(nDotV, f0, roughness);
+        vec3 kD = (1.0 - kS) * (1.0 - metallic);
+
+        vec3 irradiance = toLinear(texture(irradianceMap, n).rgb);
+        vec3 prefiltered = toLinear(textureLod(prefilteredMap, reflect(-v, n), roughness * environmentParams.y).rgb);
+        vec2 brdf = texture(brdfLut, vec2(nDotV, roughness)).rg;
+
+        ambient = (kD * irradiance * albedo + prefiltered * (kS * brdf.x + brdf.y)) * occlusion * environmentParams.x;
+    }
 
-    vec4 lighting = vec4(ambient + diffuse + specular, 1.0);
+    vec3 color = direct + ambient + emissive;
+    color = mix(color, toLinear(camera.fogColor.rgb), fogAmount(vWorldPos.xyz));
 
-    fragColor = textureColor * lighting;
+    fragColor = vec4(pow(color, vec3(1.0 / 2.2)), alphaMode == 2 ? alpha : 1.0);
 
 }
@@ -1,13 +1,32 @@
 #version 300 es
 
-in vec3 position;
-in vec3 normal;
-in vec4 tangent;
+// Fixed locations so the shadow shader can draw from the same vertex arrays
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec4 tangent;
 
-in vec2 uvs;
+layout(location = 3) in vec2 uvs;
+
+// Position and normal offsets of up to 4 morph targets, blended by their weights
+layout(location = 6) in vec3 morphPosition0;
+layout(location = 7) in vec3 morphPosition1;
+layout(location = 8) in vec3 morphPosition2;
+layout(location = 9) in vec3 morphPosition3;
+layout(location = 10) in vec3 morphNormal0;
+layout(location = 11) in vec3 morphNormal1;
+layout(location = 12) in vec3 morphNormal2;
+layout(location = 13) in vec3 morphNormal3;
+uniform float morphWeights[4];
 out vec2 vUvs;
 
 uniform mat4 model; 
+// Placement of each copy when instancing, applied after model. Identity for single draws.
+layout(std140) uniform Instances
+{
+  mat4 models[128];
+} instances;
+// Sun's projection * view, for looking up the shadow map
+uniform mat4 lightSpace;
 
 
 layout(std140) uniform Camera
@@ -15,30 +34,47 @@ layout(std140) uniform Camera
   mat4 projection;
   mat4 view;
   vec4 pos;
+  vec4 clip; // near, far, orthographic, unused
+  vec4 fog; // density (0 without fog), height falloff, height, unused
+  vec4 fogColor;
 } camera;
 
 out vec3 vNormal;
 out vec4 vTangent;
 out vec4 vWorldPos; 
+out vec4 vLightPos;
  
 out vec3 fromFragmentToCamera;
  
 out mat3 TBN;
 
 void main (void) {
-  vec4 worldPosition = model * vec4(position, 1.0);
+  vec3 morphedPosition = position +
+    morphWeights[0] * morphPosition0 +
+    morphWeights[1] * morphPosition1 +
+    morphWeights[2] * morphPosition2 +
+    morphWeights[3] * morphPosition3;
+  vec3 morphedNormal = normal +
+    morphWeights[0] * morphNormal0 +
+    morphWeights[1] * morphNormal1 +
+    morphWeights[2] * morphNormal2 +
+    morphWeights[3] * morphNormal3;
+
+  mat4 instanceModel = instances.models[gl_InstanceID] * model;
+  vec4 worldPosition = instanceModel * vec4(morphedPosition, 1.0);
 
   gl_Position = camera.projection * camera.view * worldPosition;
 
-  vNormal = normal;
+  vNormal = morphedNormal;
   vTangent = tangent;
   vWorldPos = worldPosition;
+  vLightPos = lightSpace * worldPosition;
   fromFragmentToCamera = camera.pos.xyz - worldPosition.xyz;
 
 
-   vec3 T = normalize(vec3(model * vec4(tangent.xyz,   0.0)));
-   vec3 B = normalize(vec3(model * vec4(cross(normal, tangent.xyz) * tangent.w, 0.0)));
-   vec3 N = normalize(vec3(model * vec4(normal,    0.0)));
+   vec3 T = normalize(vec3(instanceModel * vec4(tangent.xyz,   0.0)));
+   vec3 B = normalize(vec3(instanceModel * vec4(cross(morphedNormal, tangent.xyz) * tangent.w, 0.0)));
+   vec3 N = normalize(vec3(instanceModel * vec4(morphedNormal,    0.0)));
    TBN = mat3(T, B, N);
 
 
@@ -3,20 +3,45 @@ use std::collections::HashMap;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::*;
-static TEXTURED_QUAD_VS: &'static str = include_str!("./textured-quad-vertex.glsl");
-static TEXTURED_QUAD_FS: &'static str = include_str!("./textured-quad-fragment.glsl");
+static TEXTURED_QUAD_VS: &str = include_str!("./textured-quad-vertex.glsl");
+static TEXTURED_QUAD_FS: &str = include_str!("./textured-quad-fragment.glsl");
 
-//static MESH_SKINNED_VS: &'static str = include_str!("./mesh-skinned-vertex.glsl");
-//static MESH_SKINNED_FS: &'static str = include_str!("./mesh-skinned-fragment.glsl");
+static MESH_SKINNED_VS: &str = include_str!("./mesh-skinned-vertex.glsl");
 
-static MESH_NON_SKINNED_VS: &'static str = include_str!("./mesh-non-skinned-vertex.glsl");
-static MESH_NON_SKINNED_FS: &'static str = include_str!("./mesh-non-skinned-fragment.glsl");
+static MESH_NON_SKINNED_VS: &str = include_str!("./mesh-non-skinned-vertex.glsl");
+static MESH_NON_SKINNED_FS: &str = include_str!("./mesh-non-skinned-fragment.glsl");
 
-static WATER_VS: &'static str = include_str!("./water-vertex.glsl");
-static WATER_FS: &'static str = include_str!("./water-fragment.glsl");
+static WATER_VS: &str = include_str!("./water-vertex.glsl");
+static WATER_FS: &str = include_str!("./water-fragment.glsl");
 
-static WIREFRAME_VS: &'static str = include_str!("./wireframe-vertex.glsl");
-static WIREFRAME_FS: &'static str = include_str!("./wireframe-fragment.glsl");
+/// `fogAmount`, shared by the fragment shaders that fog what they draw
+static FOG_SNIPPET: &str = include_str!("./fog.glsl");
+
+static SHADOW_VS: &str = include_str!("./shadow-vertex.glsl");
+static SHADOW_FS: &str = include_str!("./shadow-fragment.glsl");
+
+static SKYBOX_VS: &str = include_str!("./skybox-vertex.glsl");
+static SKYBOX_FS: &str = include_str!("./skybox-fragment.glsl");
+
+static CUBE_FACE_VS: &str = include_str!("./cube-face-vertex.glsl");
+static CUBE_FACE_FS: &str = include_str!("./cube-face-fragment.glsl");
+static IRRADIANCE_FS: &str = include_str!("./irradiance-fragment.glsl");
+static PREFILTER_FS: &str = include_str!("./prefilter-fragment.glsl");
+static BRDF_LUT_FS: &str = include_str!("./brdf-lut-fragment.glsl");
+
+static POST_BRIGHT_FS: &str = include_str!("./post-bright-fragment.glsl");
+static POST_BLUR_FS: &str = include_str!("./post-blur-fragment.glsl");
+static POST_BLOOM_FS: &str = include_str!("./post-bloom-fragment.glsl");
+static POST_TONEMAP_FS: &str = include_str!("./post-tonemap-fragment.glsl");
+static POST_GRADING_FS: &str = include_str!("./post-grading-fragment.glsl");
+static POST_FXAA_FS: &str = include_str!("./post-fxaa-fragment.glsl");
+static POST_VIGNETTE_FS: &str = include_str!("./post-vignette-fragment.glsl");
+
+static WIREFRAME_VS: &str = include_str!("./wireframe-vertex.glsl");
+static WIREFRAME_FS: &str = include_str!("./wireframe-fragment.glsl");
+
+static TEXT_VS: &str = include_str!("./text-vertex.glsl");
+static TEXT_FS: &str = include_str!("./text-fragment.glsl");
 
 /// Powers retrieving and using our shaders
 pub struct ShaderSystem {
@@ -29,21 +54,57 @@ impl ShaderSystem {
     pub fn new(gl: &WebGl2RenderingContext) -> ShaderSystem {
         let mut programs = HashMap::new();
 
-        let water_shader = Shader::new(&gl, WATER_VS, WATER_FS).unwrap();
-        let non_skinned_shader =
-            Shader::new(&gl, MESH_NON_SKINNED_VS, MESH_NON_SKINNED_FS).unwrap();
-        //let skinned_mesh_shader = Shader::new(&gl, MESH_SKINNED_VS, MESH_SKINNED_FS).unwrap();
-        let textured_quad_shader = Shader::new(&gl, TEXTURED_QUAD_VS, TEXTURED_QUAD_FS).unwrap();
-        let wireframe_shader = Shader::new(&gl, WIREFRAME_VS, WIREFRAME_FS).unwrap();
+        let water_fs = join_snippets(WATER_FS);
+        let mesh_fs = join_snippets(MESH_NON_SKINNED_FS);
+
+        let water_shader = Shader::new(gl, WATER_VS, &water_fs).unwrap();
+        let non_skinned_shader = Shader::new(gl, MESH_NON_SKINNED_VS, &mesh_fs).unwrap();
+        // Skinning only changes where vertices end up, so shading is shared with static meshes
+        let skinned_mesh_shader = Shader::new(gl, MESH_SKINNED_VS, &mesh_fs).unwrap();
+        let textured_quad_shader = Shader::new(gl, TEXTURED_QUAD_VS, TEXTURED_QUAD_FS).unwrap();
+        let wireframe_shader = Shader::new(gl, WIREFRAME_VS, WIREFRAME_FS).unwrap();
+        let text_shader = Shader::new(gl, TEXT_VS, TEXT_FS).unwrap();
+        let shadow_shader = Shader::new(gl, SHADOW_VS, SHADOW_FS).unwrap();
+        let skinned_shadow_shader = Shader::new(gl, MESH_SKINNED_VS, SHADOW_FS).unwrap();
+        let skybox_shader = Shader::new(gl, SKYBOX_VS, SKYBOX_FS).unwrap();
+        let cube_face_shader = Shader::new(gl, CUBE_FACE_VS, CUBE_FACE_FS).unwrap();
+        // The cube face vertex shader covers the target, which is all these need
+        let irradiance_shader = Shader::new(gl, CUBE_FACE_VS, IRRADIANCE_FS).unwrap();
+        let prefilter_shader = Shader::new(gl, CUBE_FACE_VS, PREFILTER_FS).unwrap();
+        let brdf_lut_shader = Shader::new(gl, CUBE_FACE_VS, BRDF_LUT_FS).unwrap();
+        // Post effects are drawn on the textured quad, covering the screen
+        let post_shaders = [
+            (ShaderKind::BloomExtract, POST_BRIGHT_FS),
+            (ShaderKind::Blur, POST_BLUR_FS),
+            (ShaderKind::BloomComposite, POST_BLOOM_FS),
+            (ShaderKind::ToneMapping, POST_TONEMAP_FS),
+            (ShaderKind::ColourGrading, POST_GRADING_FS),
+            (ShaderKind::Fxaa, POST_FXAA_FS),
+            (ShaderKind::Vignette, POST_VIGNETTE_FS),
+        ];
 
         let active_program = RefCell::new(ShaderKind::TexturedQuad);
         gl.use_program(Some(&textured_quad_shader.program));
 
         programs.insert(ShaderKind::Water, Rc::new(water_shader));
         programs.insert(ShaderKind::NonSkinnedMesh, Rc::new(non_skinned_shader));
-        //programs.insert(ShaderKind::SkinnedMesh, skinned_mesh_shader);
+        programs.insert(ShaderKind::SkinnedMesh, Rc::new(skinned_mesh_shader));
         programs.insert(ShaderKind::TexturedQuad, Rc::new(textured_quad_shader));
         programs.insert(ShaderKind::WireFrame, Rc::new(wireframe_shader));
+        programs.insert(ShaderKind::Text, Rc::new(text_shader));
+        programs.insert(ShaderKind::Shadow, Rc::new(shadow_shader));
+        programs.insert(ShaderKind::SkinnedShadow, Rc::new(skinned_shadow_shader));
+        programs.insert(ShaderKind::Skybox, Rc::new(skybox_shader));
+        programs.insert(ShaderKind::CubeFace, Rc::new(cube_face_shader));
+        programs.insert(ShaderKind::Irradiance, Rc::new(irradiance_shader));
+        programs.insert(ShaderKind::Prefilter, Rc::new(prefilter_shader));
+        programs.insert(ShaderKind::BrdfLut, Rc::new(brdf_lut_shader));
+        for (kind, fs) in post_shaders {
+            programs.insert(
+                kind,
+                Rc::new(Shader::new(gl, TEXTURED_QUAD_VS, fs).unwrap()),
+            );
+        }
 
         ShaderSystem {
             programs,
@@ -68,14 +129,43 @@ impl ShaderSystem {
     }
 }
 
+/// Replace `#include <fog>` lines with the shared GLSL they name
+fn join_snippets(source: &str) -> String {
+    source.replace("#include <fog>", FOG_SNIPPET)
+}
+
 /// Identifiers for our different shaders
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
 pub enum ShaderKind {
     Water,
     NonSkinnedMesh,
     WireFrame,
     SkinnedMesh,
     TexturedQuad,
+    /// Depth only, for the sun's shadow map
+    Shadow,
+    SkinnedShadow,
+    Skybox,
+    /// Draws a 2D texture into one face of a cube map
+    CubeFace,
+    /// Diffuse light from the environment, for one face of a cube map
+    Irradiance,
+    /// Specular light from the environment at one roughness, for one face of a cube map
+    Prefilter,
+    /// Split sum lookup table for image based specular
+    BrdfLut,
+    /// Keeps only the bright parts of the scene, to make bloom from
+    BloomExtract,
+    /// One direction of a separable gaussian blur
+    Blur,
+    /// Adds blurred bright parts back onto the scene
+    BloomComposite,
+    ToneMapping,
+    ColourGrading,
+    Fxaa,
+    Vignette,
+    /// Glyphs from a signed distance field font atlas, on screen or facing the camera
+    Text,
 }
 
 /// One per ShaderKind
@@ -92,10 +182,9 @@ impl Shader {
         vert_shader: &str,
         frag_shader: &str,
     ) -> Result<Shader, JsValue> {
-        let vert_shader = compile_shader(&gl, WebGl2RenderingContext::VERTEX_SHADER, vert_shader)?;
-        let frag_shader =
-            compile_shader(&gl, WebGl2RenderingContext::FRAGMENT_SHADER, frag_shader)?;
-        let program = link_program(&gl, &vert_shader, &frag_shader)?;
+        let vert_shader = compile_shader(gl, WebGl2RenderingContext::VERTEX_SHADER, vert_shader)?;
+        let frag_shader = compile_shader(gl, WebGl2RenderingContext::FRAGMENT_SHADER, frag_shader)?;
+        let program = link_program(gl, &vert_shader, &frag_shader)?;
 
         let uniforms = RefCell::new(HashMap::new());
         let uniform_block_indexes = RefCell::new(HashMap::new());
@@ -122,7 +211,7 @@ impl Shader {
         } else {
             let uni = gl
                 .get_uniform_location(&self.program, uniform_name)
-                .expect(&format!(r#"Uniform '{}' not found"#, uniform_name));
+                .unwrap_or_else(|| panic!(r#"Uniform '{}' not found"#, uniform_name));
             uniforms.insert(uniform_name.to_owned(), uni.clone());
             Some(uni)
         }
@@ -182,8 +271,8 @@ fn link_program(
         .create_program()
         .ok_or_else(|| "Unable to create shader program".to_string())?;
 
-    gl.attach_shader(&program, &vert_shader);
-    gl.attach_shader(&program, &frag_shader);
+    gl.attach_shader(&program, vert_shader);
+    gl.attach_shader(&program, frag_shader);
 
     gl.link_program(&program);
 
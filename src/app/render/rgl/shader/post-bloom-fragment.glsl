@@ -0,0 +1,18 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+uniform sampler2D bloomTexture;
+uniform float strength;
+
+out vec4 fragColor;
+
+void main() {
+    vec3 color = texture(u_texture, texCoords).rgb;
+    vec3 bloom = texture(bloomTexture, texCoords).rgb;
+
+    fragColor = vec4(color + bloom * strength, 1.0);
+}
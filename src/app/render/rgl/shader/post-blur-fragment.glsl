@@ -0,0 +1,26 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+// One texel along the direction to blur in
+uniform vec2 direction;
+
+out vec4 fragColor;
+
+// Gaussian weights for the centre and the four texels either side
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec3 color = texture(u_texture, texCoords).rgb * weights[0];
+
+    for (int i = 1; i < 5; i++) {
+        vec2 offset = direction * float(i);
+        color += texture(u_texture, texCoords + offset).rgb * weights[i];
+        color += texture(u_texture, texCoords - offset).rgb * weights[i];
+    }
+
+    fragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+uniform float threshold;
+
+out vec4 fragColor;
+
+void main() {
+    vec3 color = texture(u_texture, texCoords).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+
+    // Scale rather than cut off, so glow fades in as pixels brighten
+    float contribution = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+
+    fragColor = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,51 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+// Size of one texel in texture coordinates
+uniform vec2 texelSize;
+
+out vec4 fragColor;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+vec3 sampleAt(vec2 offset) {
+    return texture(u_texture, texCoords + offset).rgb;
+}
+
+void main() {
+    float lumaNW = luma(sampleAt(vec2(-1.0, -1.0) * texelSize));
+    float lumaNE = luma(sampleAt(vec2(1.0, -1.0) * texelSize));
+    float lumaSW = luma(sampleAt(vec2(-1.0, 1.0) * texelSize));
+    float lumaSE = luma(sampleAt(vec2(1.0, 1.0) * texelSize));
+    float lumaM = luma(sampleAt(vec2(0.0)));
+
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    // Blur along the edge, which runs perpendicular to the luma gradient
+    vec2 dir = vec2(
+        -((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+        (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texelSize;
+
+    vec3 rgbA = 0.5 * (sampleAt(dir * (1.0 / 3.0 - 0.5)) + sampleAt(dir * (2.0 / 3.0 - 0.5)));
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (sampleAt(dir * -0.5) + sampleAt(dir * 0.5));
+
+    // The wider blur crossed another edge, so fall back to the narrow one
+    float lumaB = luma(rgbB);
+    fragColor = vec4(lumaB < lumaMin || lumaB > lumaMax ? rgbA : rgbB, 1.0);
+}
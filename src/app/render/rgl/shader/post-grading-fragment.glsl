@@ -0,0 +1,22 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+uniform float saturation;
+uniform float contrast;
+uniform vec3 tint;
+
+out vec4 fragColor;
+
+void main() {
+    vec3 color = texture(u_texture, texCoords).rgb;
+
+    float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    color = mix(vec3(luma), color, saturation);
+    color = (color - 0.5) * contrast + 0.5;
+
+    fragColor = vec4(clamp(color * tint, 0.0, 1.0), 1.0);
+}
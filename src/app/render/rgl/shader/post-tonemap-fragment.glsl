@@ -0,0 +1,22 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+uniform float exposure;
+
+out vec4 fragColor;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    // The scene is drawn gamma encoded, so decode before mapping the light itself
+    vec3 color = pow(texture(u_texture, texCoords).rgb, vec3(2.2)) * exposure;
+
+    fragColor = vec4(pow(aces(color), vec3(1.0 / 2.2)), 1.0);
+}
@@ -0,0 +1,21 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+uniform sampler2D u_texture;
+uniform float strength;
+uniform float radius;
+
+out vec4 fragColor;
+
+void main() {
+    vec3 color = texture(u_texture, texCoords).rgb;
+
+    // Distance in half screens, so the edges are at 1
+    float d = length(texCoords - 0.5) * 2.0;
+    float darken = strength * smoothstep(radius, radius + 0.7, d);
+
+    fragColor = vec4(color * (1.0 - darken), 1.0);
+}
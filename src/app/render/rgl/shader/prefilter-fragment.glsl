@@ -0,0 +1,91 @@
+#version 300 es
+
+precision highp float;
+
+in vec2 vUv;
+
+uniform samplerCube source;
+// Face being drawn, in the order +X, -X, +Y, -Y, +Z, -Z
+uniform int face;
+// Roughness this mip level is blurred for
+uniform float roughness;
+// Width of a face of the source in texels
+uniform float sourceSize;
+
+out vec4 fragColor;
+
+const float PI = 3.14159265;
+const uint SAMPLES = 64u;
+
+// Direction through a point on the face, from -1 to 1 on each axis
+vec3 faceDirection(vec2 st) {
+    if (face == 0) return vec3(1.0, -st.y, -st.x);
+    if (face == 1) return vec3(-1.0, -st.y, st.x);
+    if (face == 2) return vec3(st.x, 1.0, st.y);
+    if (face == 3) return vec3(st.x, -1.0, -st.y);
+    if (face == 4) return vec3(st.x, -st.y, 1.0);
+    return vec3(-st.x, -st.y, -1.0);
+}
+
+vec3 toLinear(vec3 srgb) {
+    return pow(srgb, vec3(2.2));
+}
+
+// Van der Corput sequence, for evenly spread samples
+float radicalInverse(uint bits) {
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+// Half vector around n, spread like the GGX distribution of microfacets
+vec3 importanceSampleGgx(vec2 xi, vec3 n, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, n));
+    vec3 bitangent = cross(n, tangent);
+
+    return normalize(tangent * cos(phi) * sinTheta + bitangent * sin(phi) * sinTheta + n * cosTheta);
+}
+
+float distribution(float nDotH, float roughness) {
+    float a2 = roughness * roughness * roughness * roughness;
+    float d = nDotH * nDotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Light reflected towards each direction by a surface of this roughness facing it
+void main(void) {
+    vec3 n = normalize(faceDirection(vUv * 2.0 - 1.0));
+    vec3 v = n;
+
+    vec3 color = vec3(0.0);
+    float weight = 0.0;
+    for (uint i = 0u; i < SAMPLES; i++) {
+        vec2 xi = vec2(float(i) / float(SAMPLES), radicalInverse(i));
+        vec3 h = importanceSampleGgx(xi, n, roughness);
+        vec3 l = normalize(2.0 * dot(v, h) * h - v);
+
+        float nDotL = dot(n, l);
+        if (nDotL > 0.0) {
+            // Read blurrier mips for unlikely samples, which each stand for a larger solid angle
+            float nDotH = max(dot(n, h), 0.0);
+            float pdf = distribution(nDotH, roughness) / 4.0 + 0.0001;
+            float texelAngle = 4.0 * PI / (6.0 * sourceSize * sourceSize);
+            float sampleAngle = 1.0 / (float(SAMPLES) * pdf + 0.0001);
+            float lod = roughness == 0.0 ? 0.0 : 0.5 * log2(sampleAngle / texelAngle);
+
+            color += toLinear(textureLod(source, l, lod).rgb) * nDotL;
+            weight += nDotL;
+        }
+    }
+
+    fragColor = vec4(pow(color / weight, vec3(1.0 / 2.2)), 1.0);
+}
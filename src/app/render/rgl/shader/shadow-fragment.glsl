@@ -0,0 +1,22 @@
+#version 300 es
+
+precision mediump float;
+
+in vec4 vWorldPos;
+in vec2 vUvs;
+
+uniform vec4 clipPlane;
+
+// Set for masked materials, 0 otherwise so nothing is cut out
+uniform sampler2D meshTexture;
+uniform vec4 baseColorFactor;
+uniform float alphaCutoff;
+
+// Only depth is written, so there is no colour output
+
+void main(void) {
+    float alpha = texture(meshTexture, vUvs).a * baseColorFactor.a;
+    if (dot(vWorldPos, clipPlane) < 0.0 || alpha < alphaCutoff) {
+        discard;
+    }
+}
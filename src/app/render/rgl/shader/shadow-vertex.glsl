@@ -0,0 +1,49 @@
+#version 300 es
+
+layout(location = 0) in vec3 position;
+// Read for alpha tested materials, which cast shadows only where they are drawn
+layout(location = 3) in vec2 uvs;
+
+// Position offsets of up to 4 morph targets, blended by their weights
+layout(location = 6) in vec3 morphPosition0;
+layout(location = 7) in vec3 morphPosition1;
+layout(location = 8) in vec3 morphPosition2;
+layout(location = 9) in vec3 morphPosition3;
+uniform float morphWeights[4];
+
+uniform mat4 model;
+// Placement of each copy when instancing, applied after model. Identity for single draws.
+layout(std140) uniform Instances
+{
+  mat4 models[128];
+} instances;
+
+
+layout(std140) uniform Camera
+{
+  mat4 projection;
+  mat4 view;
+  vec4 pos;
+  vec4 clip; // near, far, orthographic, unused
+  vec4 fog; // density (0 without fog), height falloff, height, unused
+  vec4 fogColor;
+} camera;
+
+out vec4 vWorldPos;
+out vec2 vUvs;
+
+void main (void) {
+  vec3 morphedPosition = position +
+    morphWeights[0] * morphPosition0 +
+    morphWeights[1] * morphPosition1 +
+    morphWeights[2] * morphPosition2 +
+    morphWeights[3] * morphPosition3;
+
+  mat4 instanceModel = instances.models[gl_InstanceID] * model;
+  vec4 worldPosition = instanceModel * vec4(morphedPosition, 1.0);
+
+  gl_Position = camera.projection * camera.view * worldPosition;
+
+  vWorldPos = worldPosition;
+  vUvs = uvs;
+}
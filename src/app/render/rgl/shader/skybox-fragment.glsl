@@ -0,0 +1,13 @@
+#version 300 es
+
+precision mediump float;
+
+in vec3 vDirection;
+
+uniform samplerCube skybox;
+
+out vec4 fragColor;
+
+void main(void) {
+    fragColor = texture(skybox, vDirection);
+}
@@ -0,0 +1,25 @@
+#version 300 es
+
+layout(location = 0) in vec3 position;
+
+layout(std140) uniform Camera
+{
+  mat4 projection;
+  mat4 view;
+  vec4 pos;
+  vec4 clip; // near, far, orthographic, unused
+  vec4 fog; // density (0 without fog), height falloff, height, unused
+  vec4 fogColor;
+} camera;
+
+out vec3 vDirection;
+
+void main (void) {
+  vDirection = position;
+
+  // Only the camera's rotation, so the sky is always infinitely far away
+  vec4 clipPosition = camera.projection * mat4(mat3(camera.view)) * vec4(position, 1.0);
+
+  // Depth of exactly 1, behind everything else
+  gl_Position = clipPosition.xyww;
+}
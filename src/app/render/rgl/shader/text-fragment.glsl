@@ -0,0 +1,24 @@
+#version 300 es
+
+precision mediump float;
+
+in vec2 texCoords;
+
+// Distance to the nearest glyph edge in alpha, 0.5 on the edge and rising inside
+uniform sampler2D atlas;
+uniform vec4 color;
+
+out vec4 fragColor;
+
+void main() {
+    float distance = texture(atlas, texCoords).a;
+
+    // Smoothed over about a pixel, whatever size the text is drawn at
+    float width = fwidth(distance);
+    float alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    fragColor = vec4(color.rgb, color.a * alpha);
+}
@@ -0,0 +1,39 @@
+#version 300 es
+
+// Laid out like the textured quad's, positions in line heights from the text's anchor, y down
+layout(location = 0) in vec4 vertexData; // <vec2 position, vec2 texCoords>
+
+layout(std140) uniform Camera
+{
+  mat4 projection;
+  mat4 view;
+  vec4 pos;
+  vec4 clip; // near, far, orthographic, unused
+  vec4 fog; // density (0 without fog), height falloff, height, unused
+  vec4 fogColor;
+} camera;
+
+// Where the anchor is, in the world or in pixels from the top left of the canvas
+uniform vec3 origin;
+// Height of a line, in world units or pixels
+uniform float size;
+uniform bool worldSpace;
+uniform vec2 screenSize;
+
+out vec2 texCoords;
+
+void main() {
+    vec2 offset = vertexData.xy * size;
+
+    if (worldSpace) {
+        // Offset in view space, so the text always faces the camera
+        vec4 viewPos = camera.view * vec4(origin, 1.0);
+        viewPos.xy += vec2(offset.x, -offset.y);
+        gl_Position = camera.projection * viewPos;
+    } else {
+        vec2 pixel = origin.xy + offset;
+        gl_Position = vec4(pixel.x / screenSize.x * 2.0 - 1.0, 1.0 - pixel.y / screenSize.y * 2.0, 0.0, 1.0);
+    }
+
+    texCoords = vertexData.zw;
+}
@@ -1,6 +1,7 @@
 #version 300 es
 
-in vec4 vertexData; // <vec2 position, vec2 texCoords>
+// Fixed so every program drawing the quad can share its vertex array
+layout(location = 0) in vec4 vertexData; // <vec2 position, vec2 texCoords>
 
 uniform float x;
 uniform float y;
@@ -13,6 +13,17 @@ vec3 sunlightDir = normalize(vec3(-1.0, -1.0, 0.5));
 
 in vec3 fromFragmentToCamera;
 
+// highp to match the vertex shader's declaration of the block
+layout(std140) uniform Camera
+{
+  highp mat4 projection;
+  highp mat4 view;
+  highp vec4 pos;
+  highp vec4 clip; // near, far, orthographic, unused
+  highp vec4 fog; // density (0 without fog), height falloff, height, unused
+  highp vec4 fogColor;
+} camera;
+
 // Changes over time, making the water look like it's moving
 uniform float dudvOffset;
 
@@ -30,6 +41,8 @@ vec4 shallowWaterColor =  vec4(0.0, 0.1, 0.3, 1.0);
 vec4 deepWaterColor = vec4(0.0, 0.1, 0.2, 1.0);
 
 vec3 getNormal(vec2 textureCoords);
+float linearDepth(float depth);
+float fogAmount(highp vec3 worldPos);
 
 out vec4 fragColor;
 
@@ -41,20 +54,15 @@ void main() {
     // Reflections are upside down
     vec2 reflectTexCoords = vec2(ndc.x, -ndc.y);
 
-    float near = 0.1;
-    float far = 50.0;
-
     // Get the distance from our camera to the first thing under this water fragment that a
     // ray would collide with. This might be the ground, the under water walls, a fish, or any
     // other thing under the water. This distance will depend on our camera angle.
     float cameraToFirstThingBehindWater = texture(waterDepthTexture, refractTexCoords).r;
-    // Convert from our perspective transformed distance to our world distance
-    float cameraToFirstThingUnderWater = 2.0 * near * far /
-     (far + near - (2.0 * cameraToFirstThingBehindWater - 1.0)
-      * (far - near));
+    // Convert from our projected distance to our world distance
+    float cameraToFirstThingUnderWater = linearDepth(cameraToFirstThingBehindWater);
 
     float cameraToWaterDepth = gl_FragCoord.z;
-    float cameraToWaterDistance = 2.0 * near * far / (far + near - (2.0 * cameraToWaterDepth - 1.0) * (far - near));
+    float cameraToWaterDistance = linearDepth(cameraToWaterDepth);
 
     float angledWaterDepth = cameraToFirstThingUnderWater - cameraToWaterDistance;
 
@@ -101,6 +109,9 @@ void main() {
     fragColor = mix(reflectColor, refractColor, refractiveFactor);
     // Mix in a bit of blue so that it looks like water
     fragColor = mix(fragColor, shallowWaterColor, 0.2) + vec4(specularHighlights, 0.0);
+
+    float fog = fogAmount(camera.pos.xyz - fromFragmentToCamera);
+    fragColor.rgb = mix(fragColor.rgb, camera.fogColor.rgb, fog);
 }
 
 vec3 getNormal(vec2 textureCoords) {
@@ -115,3 +126,18 @@ vec3 getNormal(vec2 textureCoords) {
 
     return normal;
 }
+
+float linearDepth(float depth) {
+    float near = camera.clip.x;
+    float far = camera.clip.y;
+
+    // Orthographic depth is already linear
+    if (camera.clip.z > 0.5) {
+        return near + depth * (far - near);
+    }
+
+    return 2.0 * near * far / (far + near - (2.0 * depth - 1.0) * (far - near));
+}
+
+// fogAmount, from fog.glsl
+#include <fog>
@@ -9,6 +9,9 @@ layout(std140) uniform Camera
   mat4 projection;
   mat4 view;
   vec4 pos;
+  vec4 clip; // near, far, orthographic, unused
+  vec4 fog; // density (0 without fog), height falloff, height, unused
+  vec4 fogColor;
 } camera;
 
  
@@ -5,6 +5,8 @@ precision mediump float;
 in vec4 vWorldPos;
  
 in vec3 fromFragmentToCamera;
+
+in vec3 vColor;
   
 
 uniform vec4 clipPlane;
@@ -20,6 +22,6 @@ void main(void) {
     }
   
 
-    fragColor = vec4(1,0,0,1);
+    fragColor = vec4(vColor, 1.0);
 
 }
@@ -1,9 +1,8 @@
 #version 300 es
 
-in vec3 position; 
- 
-
-uniform mat4 model; 
+// Lines are uploaded in world space, one colour per end
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 color;
 
 
 layout(std140) uniform Camera
@@ -11,20 +10,26 @@ layout(std140) uniform Camera
   mat4 projection;
   mat4 view;
   vec4 pos;
+  vec4 clip; // near, far, orthographic, unused
+  vec4 fog; // density (0 without fog), height falloff, height, unused
+  vec4 fogColor;
 } camera;
  
 out vec4 vWorldPos; 
  
 out vec3 fromFragmentToCamera;
+
+out vec3 vColor;
   
 
 void main (void) {
-  vec4 worldPosition = model * vec4(position, 1.0);
+  vec4 worldPosition = vec4(position, 1.0);
 
   gl_Position = camera.projection * camera.view * worldPosition;
  
   vWorldPos = worldPosition;
   fromFragmentToCamera = camera.pos.xyz - worldPosition.xyz;
+  vColor = color;
 
   
 }
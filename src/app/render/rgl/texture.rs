@@ -46,6 +46,42 @@ pub enum TexFilter {
     Linear = GL::LINEAR as isize,
 }
 
+/// How the colour of a texture that is drawn into is stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorFormat {
+    /// 8 bits per channel, clamped to 0-1
+    Rgba8,
+    /// Half floats, so values can go past 1. Float formats can only be drawn into with
+    /// `EXT_color_buffer_float`.
+    Rgba16F,
+    /// Packed floats with no alpha, for HDR colour in half the space of `Rgba16F`
+    R11fG11fB10f,
+}
+
+impl ColorFormat {
+    pub fn internal_format(self) -> u32 {
+        match self {
+            ColorFormat::Rgba8 => GL::RGBA8,
+            ColorFormat::Rgba16F => GL::RGBA16F,
+            ColorFormat::R11fG11fB10f => GL::R11F_G11F_B10F,
+        }
+    }
+
+    fn format(self) -> u32 {
+        match self {
+            ColorFormat::Rgba8 | ColorFormat::Rgba16F => GL::RGBA,
+            ColorFormat::R11fG11fB10f => GL::RGB,
+        }
+    }
+
+    fn data_type(self) -> u32 {
+        match self {
+            ColorFormat::Rgba8 => GL::UNSIGNED_BYTE,
+            ColorFormat::Rgba16F | ColorFormat::R11fG11fB10f => GL::HALF_FLOAT,
+        }
+    }
+}
+
 impl Tex {
     pub fn bind_at(&self, gl: &GL, unit: &TexUnit) {
         gl.active_texture(GL::TEXTURE0 + unit.unit());
@@ -116,7 +152,38 @@ impl Tex {
         Tex { texture }
     }
 
-    pub fn new_color(gl: &GL, width: i32, height: i32, filter: TexFilter) -> Result<Tex, JsValue> {
+    /// A single pixel texture, for material slots with no texture of their own
+    pub fn new_solid(gl: &GL, rgba: [u8; 4]) -> Tex {
+        let texture = Self::create_texture(gl);
+
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::NEAREST as i32);
+
+        let data_array = super::to_array_buffer_view(&rgba);
+
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+            GL::TEXTURE_2D,
+            0,
+            GL::RGBA as i32,
+            1,
+            1,
+            0,
+            GL::RGBA as u32,
+            GL::UNSIGNED_BYTE,
+            Some(&data_array),
+        )
+        .expect("Failed making solid texture");
+
+        Tex { texture }
+    }
+
+    pub fn new_color(
+        gl: &GL,
+        width: i32,
+        height: i32,
+        filter: TexFilter,
+        format: ColorFormat,
+    ) -> Result<Tex, JsValue> {
         let texture = Self::create_texture(gl);
 
         gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, filter as i32);
@@ -124,12 +191,12 @@ impl Tex {
         gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
             GL::TEXTURE_2D,
             0,
-            GL::RGBA as i32,
+            format.internal_format() as i32,
             width,
             height,
             0,
-            GL::RGBA as u32,
-            GL::UNSIGNED_BYTE,
+            format.format(),
+            format.data_type(),
             None,
         )?;
 
@@ -157,7 +224,84 @@ impl Tex {
         })
     }
 
+    /// Stop sampling past the edges from wrapping around to the other side
+    pub fn clamp_to_edge(&self, gl: &GL) {
+        gl.active_texture(GL::TEXTURE31);
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    }
+
     pub fn texture(&self) -> Option<&WebGlTexture> {
         self.texture.as_ref()
     }
+
+    pub fn delete(&self, gl: &GL) {
+        gl.delete_texture(self.texture.as_ref())
+    }
+}
+
+/// Six square faces sampled by direction, in the order +X, -X, +Y, -Y, +Z, -Z
+pub struct CubeTex {
+    texture: Option<WebGlTexture>,
+    /// Width and height of each face in texels
+    pub size: i32,
+}
+
+impl CubeTex {
+    /// Empty faces to draw into, with room for `levels` mip levels
+    pub fn new_color(gl: &GL, size: i32, levels: i32) -> CubeTex {
+        gl.active_texture(GL::TEXTURE31);
+
+        let texture = gl.create_texture();
+        gl.bind_texture(GL::TEXTURE_CUBE_MAP, texture.as_ref());
+
+        gl.tex_parameteri(
+            GL::TEXTURE_CUBE_MAP,
+            GL::TEXTURE_MIN_FILTER,
+            GL::LINEAR_MIPMAP_LINEAR as i32,
+        );
+        gl.tex_parameteri(
+            GL::TEXTURE_CUBE_MAP,
+            GL::TEXTURE_MAG_FILTER,
+            GL::LINEAR as i32,
+        );
+        for wrap in [GL::TEXTURE_WRAP_S, GL::TEXTURE_WRAP_T, GL::TEXTURE_WRAP_R] {
+            gl.tex_parameteri(GL::TEXTURE_CUBE_MAP, wrap, GL::CLAMP_TO_EDGE as i32);
+        }
+
+        gl.tex_storage_2d(GL::TEXTURE_CUBE_MAP, levels, GL::RGBA8, size, size);
+
+        CubeTex { texture, size }
+    }
+
+    /// Mip levels down to a single texel
+    pub fn full_levels(size: i32) -> i32 {
+        32 - (size.max(1) as u32).leading_zeros() as i32
+    }
+
+    /// Target to attach one face to a framebuffer with
+    pub fn face_target(face: u32) -> u32 {
+        GL::TEXTURE_CUBE_MAP_POSITIVE_X + face
+    }
+
+    pub fn bind_at(&self, gl: &GL, unit: &TexUnit) {
+        gl.active_texture(GL::TEXTURE0 + unit.unit());
+        gl.bind_texture(GL::TEXTURE_CUBE_MAP, self.texture.as_ref());
+    }
+
+    /// Fill in the smaller mip levels from the faces
+    pub fn generate_mipmap(&self, gl: &GL) {
+        gl.active_texture(GL::TEXTURE31);
+        gl.bind_texture(GL::TEXTURE_CUBE_MAP, self.texture.as_ref());
+        gl.generate_mipmap(GL::TEXTURE_CUBE_MAP);
+    }
+
+    pub fn texture(&self) -> Option<&WebGlTexture> {
+        self.texture.as_ref()
+    }
+
+    pub fn delete(&self, gl: &GL) {
+        gl.delete_texture(self.texture.as_ref())
+    }
 }
@@ -0,0 +1,20 @@
+use std::cell::Ref;
+
+use web_sys::WebGl2RenderingContext as GL;
+
+use super::rgl::framebuffer::Framebuffer;
+use super::WebRenderer;
+
+impl WebRenderer {
+    /// The shadow map, recreated first if the requested resolution has changed
+    pub fn shadow_map(&self, gl: &GL, resolution: i32) -> Ref<Framebuffer> {
+        if self.shadow_map.borrow().resolution != resolution {
+            match WebRenderer::create_shadow_map(gl, resolution) {
+                Ok(shadow_map) => self.shadow_map.replace(shadow_map).framebuffer.delete(gl),
+                Err(e) => log::error!("Could not resize shadow map: {:?}", e),
+            }
+        }
+
+        Ref::map(self.shadow_map.borrow(), |s| &s.framebuffer)
+    }
+}
@@ -0,0 +1,144 @@
+use web_sys::WebGl2RenderingContext as GL;
+use web_sys::WebGlBuffer;
+
+use super::rgl::shader::ShaderKind;
+use super::rgl::texture::TexUnit;
+use super::rgl::uniform_buffer::UniformBuffer;
+use super::rgl::vao::Vao;
+use super::{stream_data, CameraData, WebRenderer};
+use crate::app::store::text::{Text, TextSpace};
+use crate::app::Assets;
+use crate::app::State;
+
+/// Texture unit text shaders read their font's atlas from
+static TEXT_ATLAS_UNIT: u32 = 21;
+
+/// The vertex array glyph quads are streamed through, laid out like `TexturedQuad`'s
+/// so each vertex is a `vec4` of position and texture coordinates.
+///
+/// `TexturedQuad` itself is not reused. It buffers one full texture quad into a cached vertex
+/// array and only moves it with its `x` and `y` uniforms, so every glyph's atlas rectangle would
+/// need its own draw, and its vertex shader has no camera to place world space text with.
+pub struct TextQuads {
+    vao: Vao,
+    buffer: WebGlBuffer,
+}
+
+impl TextQuads {
+    pub fn new(gl: &GL) -> TextQuads {
+        let vao = Vao::new(gl);
+        vao.bind(gl);
+
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer));
+
+        // Matches `vertexData` in `text-vertex.glsl`
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_with_i32(0, 4, GL::FLOAT, false, 0, 0);
+
+        gl.bind_vertex_array(None);
+
+        TextQuads { vao, buffer }
+    }
+}
+
+impl WebRenderer {
+    /// Draw every glyph of a text entity in one call, once its font and atlas have loaded
+    pub fn draw_text(
+        &self,
+        gl: &GL,
+        text: &Text,
+        camera: &UniformBuffer<CameraData>,
+        state: &State,
+        assets: &Assets,
+    ) {
+        let font = match assets.get_font(text.font) {
+            Some(f) if assets.tex_loaded(f.atlas) => f,
+            _ => return,
+        };
+        let (origin, world) = match &text.space {
+            TextSpace::Screen([x, y]) => ([*x, *y, 0.0], false),
+            TextSpace::World { .. } => match text.world_position(state) {
+                Some(p) => (p.into(), true),
+                None => return,
+            },
+        };
+
+        let layout = font.layout(&text.text);
+        if layout.glyphs.is_empty() {
+            return;
+        }
+
+        // Positions are moved so the anchor lands on the origin
+        let [anchor_x, anchor_y] = text.anchor;
+        let (dx, dy) = (layout.width * anchor_x, layout.height * anchor_y);
+
+        let mut vertices: Vec<[f32; 4]> = Vec::with_capacity(layout.glyphs.len() * 6);
+        for glyph in &layout.glyphs {
+            let [l, t, r, b] = glyph.rect;
+            let (l, t, r, b) = (l - dx, t - dy, r - dx, b - dy);
+            let [u0, v0, u1, v1] = glyph.uv;
+
+            vertices.extend_from_slice(&[
+                [l, t, u0, v0], // Top Left
+                [l, b, u0, v1], // Bottom Left
+                [r, b, u1, v1], // Bottom Right
+                [l, t, u0, v0], // Top Left
+                [r, b, u1, v1], // Bottom Right
+                [r, t, u1, v0], // Top Right
+            ]);
+        }
+
+        self.text_quads.vao.bind(gl);
+        stream_data(gl, &self.text_quads.buffer, &vertices);
+
+        let unit = TexUnit::new(gl, TEXT_ATLAS_UNIT);
+        assets.get_tex(font.atlas).bind_at(gl, &unit);
+
+        let shader = self.shader_sys.get_shader(&ShaderKind::Text).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::Text);
+
+        let block_index = shader.get_uniform_block_index(gl, "Camera");
+        camera.bind_base(gl, shader, block_index, 2);
+
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "atlas").as_ref(),
+            unit.uniti(),
+        );
+        let [r, g, b, a] = text.color;
+        gl.uniform4f(
+            shader.get_uniform_location(gl, "color").as_ref(),
+            r,
+            g,
+            b,
+            a,
+        );
+        let [x, y, z] = origin;
+        gl.uniform3f(shader.get_uniform_location(gl, "origin").as_ref(), x, y, z);
+        gl.uniform1f(shader.get_uniform_location(gl, "size").as_ref(), text.size);
+        gl.uniform1i(
+            shader.get_uniform_location(gl, "worldSpace").as_ref(),
+            world as i32,
+        );
+        gl.uniform2f(
+            shader.get_uniform_location(gl, "screenSize").as_ref(),
+            state.display.width as f32,
+            state.display.height as f32,
+        );
+
+        // Glyph edges fade out, so are blended. Screen text goes over everything.
+        gl.enable(GL::BLEND);
+        gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA);
+        gl.depth_mask(false);
+        if !world {
+            gl.disable(GL::DEPTH_TEST);
+        }
+
+        gl.draw_arrays(GL::TRIANGLES, 0, vertices.len() as i32);
+        self.count_draw_call();
+
+        gl.disable(GL::BLEND);
+        gl.depth_mask(true);
+        gl.enable(GL::DEPTH_TEST);
+    }
+}
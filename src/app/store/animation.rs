@@ -0,0 +1,420 @@
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::{Interpolation, Property};
+use gltf::buffer::Data;
+use nalgebra::{Matrix4, Quaternion, Translation3, UnitQuaternion, Vector3, Vector4};
+
+use crate::app::GltfMesh;
+
+/// Seconds a clip takes to fade in or out when no fade time is given
+pub const DEFAULT_FADE_SECONDS: f32 = 0.2;
+
+/// Local transform of a node, split so poses can be blended
+#[derive(Debug, Clone, Copy)]
+pub struct Trs {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Trs {
+    pub fn from_gltf(node: &gltf::Node) -> Trs {
+        let (t, [x, y, z, w], s) = node.transform().decomposed();
+        Trs {
+            translation: t.into(),
+            rotation: UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)),
+            scale: s.into(),
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Translation3::from(self.translation).to_homogeneous()
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+/// Keyframes driving one property of one node
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub property: Property,
+    interpolation: Interpolation,
+    times: Vec<f32>,
+    /// `stride` floats per key, or three times that for cubic splines (in tangent, value, out tangent)
+    values: Vec<f32>,
+    stride: usize,
+}
+
+impl Channel {
+    fn from_gltf(channel: &gltf::animation::Channel, buffers: &[Data]) -> Option<Channel> {
+        let reader = channel.reader(|b| Some(&buffers[b.index()][..]));
+        let times: Vec<f32> = reader.read_inputs()?.collect();
+
+        let (values, stride): (Vec<f32>, usize) = match reader.read_outputs()? {
+            ReadOutputs::Translations(iter) => (iter.flatten().collect(), 3),
+            ReadOutputs::Rotations(iter) => (iter.into_f32().flatten().collect(), 4),
+            ReadOutputs::Scales(iter) => (iter.flatten().collect(), 3),
+            ReadOutputs::MorphTargetWeights(iter) => {
+                let weights: Vec<f32> = iter.into_f32().collect();
+                (weights, 0)
+            }
+        };
+
+        if times.is_empty() {
+            return None;
+        }
+
+        // Weight channels have one value per morph target for every key
+        let stride = match (stride, channel.sampler().interpolation()) {
+            (0, Interpolation::CubicSpline) => values.len() / times.len() / 3,
+            (0, _) => values.len() / times.len(),
+            (s, _) => s,
+        };
+
+        Some(Channel {
+            node: channel.target().node().index(),
+            property: channel.target().property(),
+            interpolation: channel.sampler().interpolation(),
+            times,
+            values,
+            stride,
+        })
+    }
+
+    /// The channel's value at `time` seconds, clamped to its first and last keys
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let s = self.stride;
+        let cubic = self.interpolation == Interpolation::CubicSpline;
+        let value = |k: usize| match cubic {
+            true => &self.values[(k * 3 + 1) * s..(k * 3 + 2) * s],
+            false => &self.values[k * s..(k + 1) * s],
+        };
+
+        let last = self.times.len() - 1;
+        let i = self.times.partition_point(|&t| t <= time).saturating_sub(1);
+        if i >= last || time <= self.times[0] || self.interpolation == Interpolation::Step {
+            return value(i.min(last)).to_vec();
+        }
+
+        let span = self.times[i + 1] - self.times[i];
+        let u = (time - self.times[i]) / span;
+        let (v0, v1) = (value(i), value(i + 1));
+
+        let mut out: Vec<f32> = match self.interpolation {
+            Interpolation::CubicSpline => {
+                let out_tangent = &self.values[(i * 3 + 2) * s..(i * 3 + 3) * s];
+                let in_tangent = &self.values[(i + 1) * 3 * s..((i + 1) * 3 + 1) * s];
+
+                let (u2, u3) = (u * u, u * u * u);
+                (0..s)
+                    .map(|c| {
+                        (2.0 * u3 - 3.0 * u2 + 1.0) * v0[c]
+                            + (u3 - 2.0 * u2 + u) * span * out_tangent[c]
+                            + (-2.0 * u3 + 3.0 * u2) * v1[c]
+                            + (u3 - u2) * span * in_tangent[c]
+                    })
+                    .collect()
+            }
+            _ if self.property == Property::Rotation => {
+                let q = |v: &[f32]| {
+                    UnitQuaternion::from_quaternion(Quaternion::new(v[3], v[0], v[1], v[2]))
+                };
+                let r = q(v0).slerp(&q(v1), u);
+                vec![r.i, r.j, r.k, r.w]
+            }
+            _ => (0..s).map(|c| v0[c] + (v1[c] - v0[c]) * u).collect(),
+        };
+
+        if self.property == Property::Rotation {
+            let len = out.iter().map(|c| c * c).sum::<f32>().sqrt();
+            out.iter_mut().for_each(|c| *c /= len);
+        }
+        out
+    }
+}
+
+/// A named glTF animation
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    /// Seconds until the last key of any channel
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    pub fn from_gltf(anim: &gltf::Animation, buffers: &[Data]) -> AnimationClip {
+        let channels: Vec<Channel> = anim
+            .channels()
+            .filter_map(|c| Channel::from_gltf(&c, buffers))
+            .collect();
+
+        AnimationClip {
+            name: anim.name().map(str::to_owned),
+            duration: channels
+                .iter()
+                .filter_map(|c| c.times.last().copied())
+                .fold(0.0, f32::max),
+            channels,
+        }
+    }
+
+    /// Overwrite the animated parts of `pose` and each node's morph target `weights`
+    /// with this clip at `time` seconds
+    pub fn apply(&self, time: f32, pose: &mut [Trs], weights: &mut [Vec<f32>]) {
+        for channel in &self.channels {
+            let v = channel.sample(time);
+
+            if channel.property == Property::MorphTargetWeights {
+                if let Some(w) = weights.get_mut(channel.node) {
+                    w.iter_mut().zip(&v).for_each(|(w, v)| *w = *v);
+                }
+                continue;
+            }
+
+            let trs = match pose.get_mut(channel.node) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            match channel.property {
+                Property::Translation => trs.translation = Vector3::new(v[0], v[1], v[2]),
+                Property::Scale => trs.scale = Vector3::new(v[0], v[1], v[2]),
+                Property::Rotation => {
+                    trs.rotation =
+                        UnitQuaternion::from_quaternion(Quaternion::new(v[3], v[0], v[1], v[2]))
+                }
+                Property::MorphTargetWeights => (),
+            }
+        }
+    }
+}
+
+/// Joints a skinned mesh is bound to
+#[derive(Debug, Clone)]
+pub struct Skin {
+    /// Node index of each joint
+    pub joints: Vec<usize>,
+    /// Takes a vertex from mesh space into the space of each joint at bind time
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+}
+
+impl Skin {
+    pub fn from_gltf(skin: &gltf::Skin, buffers: &[Data]) -> Skin {
+        let joints: Vec<usize> = skin.joints().map(|j| j.index()).collect();
+
+        let inverse_bind_matrices = match skin
+            .reader(|b| Some(&buffers[b.index()][..]))
+            .read_inverse_bind_matrices()
+        {
+            Some(iter) => iter.map(Matrix4::from).collect(),
+            None => vec![Matrix4::identity(); joints.len()],
+        };
+
+        Skin {
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+
+    /// World joint transforms multiplied by their inverse bind matrices, for the vertex shader
+    pub fn joint_matrices(&self, world_transforms: &[Matrix4<f32>]) -> Vec<Matrix4<f32>> {
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&j, ibm)| world_transforms[j] * ibm)
+            .collect()
+    }
+}
+
+/// A clip being played on an entity
+#[derive(Debug, Clone)]
+pub struct PlayingClip {
+    pub clip: String,
+    /// Seconds into the clip
+    pub time: f32,
+    pub speed: f32,
+    pub looping: bool,
+    /// Share of the blended pose, fading towards `target_weight`
+    pub weight: f32,
+    target_weight: f32,
+    /// Weight change per second
+    fade_rate: f32,
+}
+
+/// Plays and blends glTF animation clips for one entity
+#[derive(Debug, Clone, Default)]
+pub struct Animator {
+    pub playing: Vec<PlayingClip>,
+    /// Node transforms from the last update, or `None` to use the glTF's rest pose
+    pub world_transforms: Option<Vec<Matrix4<f32>>>,
+    /// Morph target weights of each node from the last update, or `None` for the glTF's defaults
+    pub morph_weights: Option<Vec<Vec<f32>>>,
+    /// Weights set by a script, used for every morphed mesh in place of the animated ones
+    pub morph_override: Option<Vec<f32>>,
+}
+
+impl Animator {
+    /// Start a clip, or change the settings of one already playing, fading it in over `fade` seconds.
+    /// Clips the glTF lacks are refused, or dropped by `advance` if it has not loaded yet.
+    pub fn play(
+        &mut self,
+        gltf: Option<&GltfMesh>,
+        clip: &str,
+        looping: bool,
+        speed: f32,
+        weight: f32,
+        fade: f32,
+    ) {
+        if gltf.map_or(false, |g| g.animation(clip).is_none()) {
+            log::warn!("No animation clip named {}", clip);
+            return;
+        }
+        let fade_rate = fade_rate(fade);
+
+        match self.playing.iter_mut().find(|p| p.clip == clip) {
+            Some(p) => {
+                p.looping = looping;
+                p.speed = speed;
+                p.target_weight = weight;
+                p.fade_rate = fade_rate;
+            }
+            None => self.playing.push(PlayingClip {
+                clip: clip.to_owned(),
+                time: 0.0,
+                speed,
+                looping,
+                weight: if fade > 0.0 { 0.0 } else { weight },
+                target_weight: weight,
+                fade_rate,
+            }),
+        }
+    }
+
+    /// Fade a clip out over `fade` seconds, after which it is removed
+    pub fn stop(&mut self, clip: &str, fade: f32) {
+        if let Some(p) = self.playing.iter_mut().find(|p| p.clip == clip) {
+            p.target_weight = 0.0;
+            p.fade_rate = fade_rate(fade);
+        }
+    }
+
+    /// Fade a playing clip to a new share of the blended pose
+    pub fn set_weight(&mut self, clip: &str, weight: f32, fade: f32) {
+        if let Some(p) = self.playing.iter_mut().find(|p| p.clip == clip) {
+            p.target_weight = weight;
+            p.fade_rate = fade_rate(fade);
+        }
+    }
+
+    /// Advance playing clips by `dt` seconds and pose the glTF's nodes
+    pub fn advance(&mut self, dt: f32, gltf: &GltfMesh) {
+        for p in &mut self.playing {
+            let duration = gltf.animation(&p.clip).map(|c| c.duration).unwrap_or(0.0);
+
+            p.time += dt * p.speed;
+            if p.looping && duration > 0.0 {
+                p.time = p.time.rem_euclid(duration);
+            } else {
+                p.time = p.time.clamp(0.0, duration);
+            }
+
+            let step = p.fade_rate * dt;
+            p.weight = if p.fade_rate.is_infinite() {
+                p.target_weight
+            } else if p.weight < p.target_weight {
+                (p.weight + step).min(p.target_weight)
+            } else {
+                (p.weight - step).max(p.target_weight)
+            };
+        }
+        self.playing.retain(|p| {
+            let known = gltf.animation(&p.clip).is_some();
+            if !known {
+                log::warn!("No animation clip named {}", p.clip);
+            }
+            known && (p.weight > 0.0 || p.target_weight > 0.0)
+        });
+
+        if self.playing.is_empty() {
+            self.world_transforms = None;
+            self.morph_weights = None;
+            return;
+        }
+
+        let (pose, weights) = self.blended_pose(gltf);
+        self.world_transforms = Some(gltf.pose_world_transforms(&pose));
+        self.morph_weights = Some(weights);
+    }
+
+    /// Morph target weights to draw a node with
+    pub fn node_morph_weights<'a>(&'a self, node: usize, gltf: &'a GltfMesh) -> &'a [f32] {
+        match (&self.morph_override, &self.morph_weights) {
+            (Some(w), _) => w,
+            (None, Some(w)) => &w[node],
+            (None, None) => &gltf.rest_morph_weights[node],
+        }
+    }
+
+    /// Weighted average of every playing clip, topped up with the rest pose if the weights sum below 1
+    fn blended_pose(&self, gltf: &GltfMesh) -> (Vec<Trs>, Vec<Vec<f32>>) {
+        let rest = &gltf.rest_pose;
+        let rest_weights = &gltf.rest_morph_weights;
+        let mut translation = vec![Vector3::zeros(); rest.len()];
+        let mut rotation = vec![Vector4::zeros(); rest.len()];
+        let mut scale = vec![Vector3::zeros(); rest.len()];
+        let mut morph: Vec<Vec<f32>> = rest_weights.iter().map(|w| vec![0.0; w.len()]).collect();
+
+        let mut accumulate = |pose: &[Trs], weights: &[Vec<f32>], w: f32| {
+            for (m, weights) in morph.iter_mut().zip(weights) {
+                m.iter_mut().zip(weights).for_each(|(m, v)| *m += v * w);
+            }
+
+            for (i, trs) in pose.iter().enumerate() {
+                translation[i] += trs.translation * w;
+                scale[i] += trs.scale * w;
+
+                // Keep quaternions in the same hemisphere as the rest pose so they don't cancel out
+                let mut q = trs.rotation.as_ref().coords;
+                if q.dot(&rest[i].rotation.as_ref().coords) < 0.0 {
+                    q = -q;
+                }
+                rotation[i] += q * w;
+            }
+        };
+
+        let mut total = 0.0;
+        for p in &self.playing {
+            if let Some(clip) = gltf.animation(&p.clip) {
+                let mut pose = rest.clone();
+                let mut weights = rest_weights.clone();
+                clip.apply(p.time, &mut pose, &mut weights);
+                accumulate(&pose, &weights, p.weight);
+                total += p.weight;
+            }
+        }
+        if total < 1.0 {
+            accumulate(rest, rest_weights, 1.0 - total);
+            total = 1.0;
+        }
+
+        let pose = (0..rest.len())
+            .map(|i| Trs {
+                translation: translation[i] / total,
+                rotation: UnitQuaternion::from_quaternion(Quaternion::from_vector(rotation[i])),
+                scale: scale[i] / total,
+            })
+            .collect();
+        morph.iter_mut().flatten().for_each(|w| *w /= total);
+
+        (pose, morph)
+    }
+}
+
+fn fade_rate(fade: f32) -> f32 {
+    if fade > 0.0 {
+        1.0 / fade
+    } else {
+        f32::INFINITY
+    }
+}
@@ -1,60 +1,198 @@
-use nalgebra::{Isometry3, Matrix4, Perspective3, Point3, Vector3};
+use nalgebra::{Isometry3, Matrix4, Orthographic3, Perspective3, Point3, Vector3, Vector4};
 use std::f32::consts::PI;
 
 use crate::ray::Ray;
 
-use super::{
-    keyboard::{KeyCode, Keyboard},
-    mouse::Mouse,
-};
+use super::{display::Display, input::Input, mouse::Mouse};
 
 pub struct Camera {
-    projection: Perspective3<f32>,
+    projection: Projection,
+    projection_mat: Matrix4<f32>,
+    aspect: f32,
     left_right_radians: f32,
     up_down_radians: f32,
     orbit_radius: f32,
     pos_x: f32,
     pos_z: f32,
+    view_override: Option<CameraOverride>,
+    mode: CameraMode,
+    pub mouse_look: MouseLook,
 }
 
-enum Mode {
+/// How the camera maps view space onto the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectionKind {
+    /// Vertical field of view in radians
+    Perspective { fovy: f32 },
+    /// Height of the view volume in world units
+    Orthographic { height: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    pub kind: ProjectionKind,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection {
+            kind: ProjectionKind::Perspective { fovy: 1.0 },
+            near: 0.1,
+            far: 400.0,
+        }
+    }
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match self.kind {
+            ProjectionKind::Perspective { fovy } => {
+                Perspective3::new(aspect, fovy, self.near, self.far).to_homogeneous()
+            }
+            ProjectionKind::Orthographic { height } => {
+                let half_h = height * 0.5;
+                let half_w = half_h * aspect;
+                Orthographic3::new(-half_w, half_w, -half_h, half_h, self.near, self.far)
+                    .to_homogeneous()
+            }
+        }
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.kind, ProjectionKind::Orthographic { .. })
+    }
+}
+
+/// An externally driven eye and target, blended over the interactive camera by `weight`
+#[derive(Debug, Clone, Copy)]
+pub struct CameraOverride {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Circles a target on the ground
     Orbit,
-    FPS,
+    /// Looks out from a point that moves with the move actions
+    Fps,
+}
+
+/// Settings for looking around with relative mouse movement while the pointer is locked
+#[derive(Debug, Clone, Copy)]
+pub struct MouseLook {
+    /// Radians per pixel of mouse movement
+    pub sensitivity: f32,
+    pub invert_y: bool,
+    /// Lock the pointer to the canvas when clicked in FPS mode
+    pub pointer_lock: bool,
+}
+
+impl Default for MouseLook {
+    fn default() -> Self {
+        MouseLook {
+            sensitivity: 0.002,
+            invert_y: false,
+            pointer_lock: true,
+        }
+    }
 }
-const MODE: Mode = Mode::Orbit;
+
+/// Radians per millisecond with a look input fully held
+const LOOK_SPEED: f32 = 0.002;
+/// Pixels of pan per millisecond with a move input fully held, as if dragged
+const PAN_SPEED: f32 = 0.3;
+/// Zoom per millisecond with a zoom input fully held
+const ZOOM_SPEED: f32 = 0.02;
 
 impl Camera {
     pub fn new() -> Camera {
-        let fovy = 16.0 / 9.0;
-
-        match MODE {
-            Mode::FPS => Camera {
-                projection: Perspective3::new(fovy, 1.0, 0.1, 400.0),
-                left_right_radians: 45.0f32.to_radians(),
-                up_down_radians: 80.0f32.to_radians(),
-                orbit_radius: 0.02,
-                pos_x: 0.,
-                pos_z: 0.,
-            },
-            Mode::Orbit => Camera {
-                projection: Perspective3::new(fovy, 1.0, 0.1, 400.0),
-                left_right_radians: 45.0f32.to_radians(),
-                up_down_radians: 80.0f32.to_radians(),
-                orbit_radius: 15.,
-                pos_x: 0.,
-                pos_z: 0.,
-            },
+        let aspect = 16.0 / 9.0;
+        let projection = Projection::default();
+        let projection_mat = projection.matrix(aspect);
+
+        Camera {
+            projection,
+            projection_mat,
+            aspect,
+            left_right_radians: 45.0f32.to_radians(),
+            up_down_radians: 80.0f32.to_radians(),
+            orbit_radius: 15.,
+            pos_x: 0.,
+            pos_z: 0.,
+            view_override: None,
+            mode: CameraMode::Orbit,
+            mouse_look: MouseLook::default(),
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if self.mode == mode {
+            return;
         }
+        self.mode = mode;
+        self.orbit_radius = match mode {
+            CameraMode::Fps => 0.02,
+            CameraMode::Orbit => 15.,
+        };
+        // re-apply the pitch limits of the new mode
+        self.orbit_up_down(0.0);
+    }
+
+    /// Whether clicking the canvas should capture the pointer for mouse look
+    pub fn wants_pointer_lock(&self) -> bool {
+        self.mode == CameraMode::Fps && self.mouse_look.pointer_lock
+    }
+
+    /// Turn by a relative mouse movement in pixels, as reported while the pointer is locked
+    pub fn mouse_look(&mut self, dx: f32, dy: f32) {
+        let MouseLook {
+            sensitivity,
+            invert_y,
+            ..
+        } = self.mouse_look;
+        let dy = if invert_y { -dy } else { dy };
+
+        // Increasing yaw turns the view left and increasing pitch tilts it down
+        self.orbit_left_right(-dx * sensitivity);
+        self.orbit_up_down(dy * sensitivity);
     }
 
     pub fn update_aspect(&mut self, aspect: f32) {
-        self.projection = Perspective3::new(aspect, 1.0, 0.1, 400.0);
+        self.aspect = aspect;
+        self.projection_mat = self.projection.matrix(aspect);
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.projection_mat = projection.matrix(self.aspect);
+    }
+
+    /// Mirrors the `clip` member of the camera uniform block - (near, far, orthographic, 0)
+    pub fn clip_data(&self) -> Vector4<f32> {
+        Vector4::new(
+            self.projection.near,
+            self.projection.far,
+            if self.projection.is_orthographic() {
+                1.0
+            } else {
+                0.0
+            },
+            0.0,
+        )
+    }
+
+    pub fn set_override(&mut self, view_override: Option<CameraOverride>) {
+        self.view_override = view_override;
     }
 
     pub fn view_mat(&self) -> Matrix4<f32> {
         let eye = self.get_eye_pos();
 
-        let target = Point3::new(self.pos_x, 0.0, self.pos_z);
+        let target = self.get_target();
 
         let view = Isometry3::look_at_rh(&eye, &target, &Vector3::y());
 
@@ -62,20 +200,39 @@ impl Camera {
     }
 
     pub fn projection_mat(&self) -> &Matrix4<f32> {
-        self.projection.as_matrix()
+        &self.projection_mat
     }
     pub fn view_flipped_y_mat(&self) -> Matrix4<f32> {
         let mut eye = self.get_eye_pos();
-        eye.y = -1.0 * eye.y;
+        eye.y *= -1.0;
 
-        let target = Point3::new(self.pos_x, 0.0, self.pos_z);
+        let mut target = self.get_target();
+        target.y *= -1.0;
 
         let view = Isometry3::look_at_rh(&eye, &target, &Vector3::y());
 
         view.to_homogeneous()
     }
 
+    pub fn get_target(&self) -> Point3<f32> {
+        let target = Point3::new(self.pos_x, 0.0, self.pos_z);
+
+        match self.view_override {
+            Some(o) => target + (o.target - target) * o.weight,
+            None => target,
+        }
+    }
+
     pub fn get_eye_pos(&self) -> Point3<f32> {
+        let eye = self.get_orbit_eye_pos();
+
+        match self.view_override {
+            Some(o) => eye + (o.eye - eye) * o.weight,
+            None => eye,
+        }
+    }
+
+    fn get_orbit_eye_pos(&self) -> Point3<f32> {
         let yaw = self.left_right_radians;
         let pitch = self.up_down_radians;
 
@@ -86,8 +243,26 @@ impl Camera {
         Point3::new(eye_x + self.pos_x, eye_y, eye_z + self.pos_z)
     }
 
-    pub fn get_mouse_ray(&self, mouse: &Mouse) -> Ray {
-        Ray::forward().transform(&self.view_mat())
+    /// Ray through a point in normalized device coordinates, for either projection
+    #[allow(dead_code)]
+    pub fn get_ndc_ray(&self, x: f32, y: f32) -> Ray {
+        let inverse = (self.projection_mat * self.view_mat())
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+
+        let near = inverse.transform_point(&Point3::new(x, y, -1.0));
+        let far = inverse.transform_point(&Point3::new(x, y, 1.0));
+
+        Ray::new(near, (far - near).normalize())
+    }
+
+    #[allow(dead_code)]
+    pub fn get_mouse_ray(&self, mouse: &Mouse, display: &Display) -> Ray {
+        let (x, y) = mouse.get_pos();
+        let x = x as f32 * display.pixel_ratio / display.width as f32;
+        let y = y as f32 * display.pixel_ratio / display.height as f32;
+
+        self.get_ndc_ray(x * 2.0 - 1.0, 1.0 - y * 2.0)
     }
 
     pub fn orbit_left_right(&mut self, delta: f32) {
@@ -101,8 +276,8 @@ impl Camera {
         // 0.1 <= radians <= PI / 2.1
         // in order to restrict the camera's up/down orbit motion
 
-        match MODE {
-            Mode::FPS => {
+        match self.mode {
+            CameraMode::Fps => {
                 if self.up_down_radians > PI / 2.1 {
                     self.up_down_radians = PI / 2.1;
                 }
@@ -111,7 +286,7 @@ impl Camera {
                     self.up_down_radians = -PI / 2.1;
                 }
             }
-            Mode::Orbit => {
+            CameraMode::Orbit => {
                 if self.up_down_radians > (PI / 2.1) {
                     self.up_down_radians = PI / 2.1;
                 }
@@ -123,22 +298,32 @@ impl Camera {
         }
     }
 
-    pub fn update(&mut self, dt: f32, keyboard: &Keyboard) {
-        if let Mode::FPS = MODE {
-            let x = if keyboard.get_pressed(KeyCode::W) {
-                dt * 0.005
-            } else if keyboard.get_pressed(KeyCode::S) {
-                -dt * 0.005
-            } else {
-                0.
-            };
-            let z = if keyboard.get_pressed(KeyCode::A) {
-                dt * 0.005
-            } else if keyboard.get_pressed(KeyCode::D) {
-                -dt * 0.005
-            } else {
-                0.
-            };
+    pub fn update(&mut self, dt: f32, input: &Input) {
+        // Analog look, such as a right stick. Increasing yaw turns the view left
+        // and increasing pitch tilts it down
+        let look_x = input.axis("look_left", "look_right");
+        let look_y = input.axis("look_down", "look_up");
+        if look_x != 0.0 || look_y != 0.0 {
+            self.orbit_left_right(-look_x * dt * LOOK_SPEED);
+            self.orbit_up_down(-look_y * dt * LOOK_SPEED);
+        }
+
+        if let CameraMode::Orbit = self.mode {
+            let zoom = input.axis("zoom_in", "zoom_out");
+            if zoom != 0.0 {
+                self.zoom(zoom * dt * ZOOM_SPEED);
+            }
+
+            let x = input.axis("pan_left", "pan_right");
+            let y = input.axis("pan_back", "pan_forward");
+            if x != 0.0 || y != 0.0 {
+                self.pan(-x * dt * PAN_SPEED, y * dt * PAN_SPEED);
+            }
+        }
+
+        if let CameraMode::Fps = self.mode {
+            let x = input.axis("move_back", "move_forward") * dt * 0.005;
+            let z = input.axis("move_right", "move_left") * dt * 0.005;
 
             let view = self.view_mat();
 
@@ -149,8 +334,27 @@ impl Camera {
         }
     }
 
+    /// Slide the orbit target across the ground, following a drag of `dx`, `dy` pixels
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let yaw = self.left_right_radians;
+        let scale = self.orbit_radius / 500.0;
+
+        // Camera right and forward, flattened onto the ground
+        let (right_x, right_z) = (yaw.cos(), -yaw.sin());
+        let (forward_x, forward_z) = (-yaw.sin(), -yaw.cos());
+
+        self.pos_x += (-right_x * dx + forward_x * dy) * scale;
+        self.pos_z += (-right_z * dx + forward_z * dy) * scale;
+    }
+
     pub fn zoom(&mut self, zoom: f32) {
-        if let Mode::Orbit = MODE {
+        if let ProjectionKind::Orthographic { height } = self.projection.kind {
+            let height = (height + zoom).clamp(1.0, 300.0);
+            self.set_projection(Projection {
+                kind: ProjectionKind::Orthographic { height },
+                ..self.projection
+            });
+        } else if let CameraMode::Orbit = self.mode {
             self.orbit_radius += zoom;
 
             if self.orbit_radius > 300. {
@@ -161,3 +365,81 @@ impl Camera {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{gamepad::FakeGamepad, keyboard::KeyCode};
+    use super::*;
+
+    fn input_with(pad: FakeGamepad) -> Input {
+        let mut input = Input::default();
+        input.set_gamepad_source(Box::new(pad));
+        input.poll_gamepad();
+        input
+    }
+
+    #[test]
+    fn left_stick_pans_orbit_camera() {
+        let mut camera = Camera::new();
+        let input = input_with(FakeGamepad::with((0.0, -1.0), (0.0, 0.0), (0.0, 0.0)));
+
+        camera.update(16.0, &input);
+        assert!(camera.pos_x != 0.0 || camera.pos_z != 0.0);
+    }
+
+    #[test]
+    fn keys_do_not_pan_orbit_camera() {
+        let mut camera = Camera::new();
+        let mut input = Input::default();
+        input.keyboard.set_pressed(KeyCode::W, true);
+
+        camera.update(16.0, &input);
+        assert_eq!((camera.pos_x, camera.pos_z), (0.0, 0.0));
+    }
+
+    #[test]
+    fn keys_walk_fps_camera() {
+        let mut camera = Camera::new();
+        camera.set_mode(CameraMode::Fps);
+        let mut input = Input::default();
+        input.keyboard.set_pressed(KeyCode::W, true);
+
+        camera.update(16.0, &input);
+        assert!(camera.pos_x != 0.0 || camera.pos_z != 0.0);
+    }
+
+    #[test]
+    fn right_stick_turns_camera() {
+        let mut camera = Camera::new();
+        let yaw = camera.left_right_radians;
+        let input = input_with(FakeGamepad::with((0.0, 0.0), (1.0, 0.0), (0.0, 0.0)));
+
+        camera.update(16.0, &input);
+        // Right on the stick turns right, which is decreasing yaw
+        assert!(camera.left_right_radians < yaw);
+    }
+
+    #[test]
+    fn triggers_zoom_orbit_camera() {
+        let mut camera = Camera::new();
+        let radius = camera.orbit_radius;
+
+        let zoom_in = input_with(FakeGamepad::with((0.0, 0.0), (0.0, 0.0), (0.0, 1.0)));
+        camera.update(100.0, &zoom_in);
+        assert!(camera.orbit_radius < radius);
+
+        let zoom_out = input_with(FakeGamepad::with((0.0, 0.0), (0.0, 0.0), (1.0, 0.0)));
+        camera.update(200.0, &zoom_out);
+        assert!(camera.orbit_radius > radius);
+    }
+
+    #[test]
+    fn stick_inside_dead_zone_leaves_camera_still() {
+        let mut camera = Camera::new();
+        let input = input_with(FakeGamepad::with((0.05, 0.1), (0.1, -0.05), (0.0, 0.0)));
+        let before = camera.view_mat();
+
+        camera.update(16.0, &input);
+        assert_eq!(camera.view_mat(), before);
+    }
+}
@@ -0,0 +1,354 @@
+use gltf::animation::util::ReadOutputs;
+use nalgebra::{Point3, Quaternion, UnitQuaternion, Vector3};
+
+use crate::app::GltfMesh;
+
+/// Seconds taken to blend between the interactive camera and a path
+pub const CAMERA_PATH_BLEND_SECONDS: f32 = 1.0;
+
+/// A single point on a camera path
+#[derive(Debug, Clone, Copy)]
+pub struct CameraKey {
+    /// Seconds since the start of the path
+    pub time: f32,
+    pub position: Point3<f32>,
+    pub target: Point3<f32>,
+}
+
+/// Catmull-Rom spline through a set of camera keys
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    keys: Vec<CameraKey>,
+    pub looping: bool,
+}
+
+impl CameraPath {
+    pub fn new(mut keys: Vec<CameraKey>, looping: bool) -> CameraPath {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        CameraPath { keys, looping }
+    }
+
+    /// Length of the path in seconds, including the closing segment of a looping path
+    pub fn duration(&self) -> f32 {
+        match self.lap() {
+            Some((ring, period)) => ring[0].time + period,
+            None => self.keys.last().map(|k| k.time).unwrap_or(0.0),
+        }
+    }
+
+    /// The keys a looping path cycles through and the seconds one lap takes, or `None` if it
+    /// does not loop. A last key repeating the first already closes the loop, otherwise a
+    /// segment as long as the average one leads from the last key back to the first.
+    fn lap(&self) -> Option<(&[CameraKey], f32)> {
+        let (first, last) = match self.keys[..] {
+            [first, .., last] if self.looping => (first, last),
+            _ => return None,
+        };
+
+        let span = last.time - first.time;
+        let closed = (last.position - first.position).norm() < 1e-4
+            && (last.target - first.target).norm() < 1e-4;
+
+        let (ring, period) = if closed {
+            (&self.keys[..self.keys.len() - 1], span)
+        } else {
+            let segments = (self.keys.len() - 1) as f32;
+            (&self.keys[..], span * (segments + 1.0) / segments)
+        };
+
+        if ring.len() < 2 || period <= 0.0 {
+            return None;
+        }
+        Some((ring, period))
+    }
+
+    /// Sample the (eye, target) pair at `time` seconds. Looping paths wrap around, others
+    /// clamp to their ends.
+    pub fn sample(&self, time: f32) -> Option<(Point3<f32>, Point3<f32>)> {
+        if let Some((ring, period)) = self.lap() {
+            return Some(sample_lap(ring, period, time));
+        }
+
+        let last = self.keys.len().checked_sub(1)?;
+
+        // Index of the key at the start of the segment containing time
+        let i = match self.keys.iter().rposition(|k| k.time <= time) {
+            Some(i) if i < last => i,
+            Some(_) => return Some((self.keys[last].position, self.keys[last].target)),
+            None => return Some((self.keys[0].position, self.keys[0].target)),
+        };
+
+        let keys = [
+            &self.keys[i.saturating_sub(1)],
+            &self.keys[i],
+            &self.keys[i + 1],
+            &self.keys[(i + 2).min(last)],
+        ];
+        Some(spline(keys, time, self.keys[i + 1].time))
+    }
+
+    /// Build a path from the translation and rotation channels of a glTF animation.
+    /// Uses the first animated node with a camera, falling back to the first translated node.
+    /// Parent transforms are not applied, so the animated node should sit at the scene root.
+    pub fn from_gltf(
+        gltf: &GltfMesh,
+        animation: Option<&str>,
+        looping: bool,
+    ) -> Option<CameraPath> {
+        let anim = gltf
+            .doc
+            .animations()
+            .find(|a| animation.is_none() || a.name() == animation)?;
+
+        let node = anim
+            .channels()
+            .map(|c| c.target().node())
+            .find(|n| n.camera().is_some())
+            .or_else(|| anim.channels().map(|c| c.target().node()).next())?;
+
+        let mut translations: Vec<(f32, Vector3<f32>)> = Vec::new();
+        let mut rotations: Vec<(f32, UnitQuaternion<f32>)> = Vec::new();
+
+        for channel in anim
+            .channels()
+            .filter(|c| c.target().node().index() == node.index())
+        {
+            let reader = channel.reader(|b| Some(&gltf.buffers[b.index()][..]));
+            let inputs: Vec<f32> = match reader.read_inputs() {
+                Some(i) => i.collect(),
+                None => continue,
+            };
+
+            match reader.read_outputs() {
+                Some(ReadOutputs::Translations(iter)) => {
+                    translations.extend(inputs.iter().copied().zip(iter.map(Vector3::from)))
+                }
+                Some(ReadOutputs::Rotations(iter)) => {
+                    rotations.extend(inputs.iter().copied().zip(iter.into_f32().map(
+                        |[x, y, z, w]| UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)),
+                    )))
+                }
+                _ => (),
+            }
+        }
+
+        if translations.is_empty() {
+            log::warn!("Animation has no translation channel to build a camera path from");
+            return None;
+        }
+
+        let keys = translations
+            .into_iter()
+            .map(|(time, t)| {
+                let rot = sample_rotation(&rotations, time);
+                // glTF cameras look down their local -Z axis
+                let forward = rot * -Vector3::z();
+
+                CameraKey {
+                    time,
+                    position: Point3::from(t),
+                    target: Point3::from(t + forward),
+                }
+            })
+            .collect();
+
+        Some(CameraPath::new(keys, looping))
+    }
+}
+
+/// Sample a loop of at least two keys, taking neighbours across the seam so it stays smooth
+fn sample_lap(ring: &[CameraKey], period: f32, time: f32) -> (Point3<f32>, Point3<f32>) {
+    let n = ring.len();
+    let start = ring[0].time;
+    let time = start + (time - start).rem_euclid(period);
+
+    let i = ring.iter().rposition(|k| k.time <= time).unwrap_or(0);
+    let end = match ring.get(i + 1) {
+        Some(k) => k.time,
+        None => start + period,
+    };
+
+    let keys = [
+        &ring[(i + n - 1) % n],
+        &ring[i],
+        &ring[(i + 1) % n],
+        &ring[(i + 2) % n],
+    ];
+    spline(keys, time, end)
+}
+
+/// Catmull-Rom through the middle two of four keys, at `time` between the second key and `end`
+fn spline(keys: [&CameraKey; 4], time: f32, end: f32) -> (Point3<f32>, Point3<f32>) {
+    let [k0, k1, k2, k3] = keys;
+
+    let span = end - k1.time;
+    let u = if span > 0.0 {
+        (time - k1.time) / span
+    } else {
+        0.0
+    };
+
+    (
+        catmull_rom(k0.position, k1.position, k2.position, k3.position, u),
+        catmull_rom(k0.target, k1.target, k2.target, k3.target, u),
+    )
+}
+
+fn sample_rotation(rotations: &[(f32, UnitQuaternion<f32>)], time: f32) -> UnitQuaternion<f32> {
+    match rotations.iter().position(|(t, _)| *t > time) {
+        None => rotations
+            .last()
+            .map(|(_, r)| *r)
+            .unwrap_or_else(UnitQuaternion::identity),
+        Some(0) => rotations[0].1,
+        Some(i) => {
+            let (t0, r0) = rotations[i - 1];
+            let (t1, r1) = rotations[i];
+            r0.slerp(&r1, (time - t0) / (t1 - t0))
+        }
+    }
+}
+
+fn catmull_rom(
+    p0: Point3<f32>,
+    p1: Point3<f32>,
+    p2: Point3<f32>,
+    p3: Point3<f32>,
+    u: f32,
+) -> Point3<f32> {
+    let u2 = u * u;
+    let u3 = u2 * u;
+
+    let v = (p1.coords * 2.0
+        + (p2.coords - p0.coords) * u
+        + (p0.coords * 2.0 - p1.coords * 5.0 + p2.coords * 4.0 - p3.coords) * u2
+        + (p1.coords * 3.0 - p0.coords - p2.coords * 3.0 + p3.coords) * u3)
+        * 0.5;
+
+    Point3::from(v)
+}
+
+/// Playback state of the active camera path
+#[derive(Debug, Clone)]
+pub struct CameraPathPlayer {
+    pub name: String,
+    /// Seconds into the path
+    pub time: f32,
+    pub playing: bool,
+    /// Fading back to the interactive camera
+    pub stopping: bool,
+    /// 0 is the interactive camera, 1 is the path
+    blend: f32,
+}
+
+impl CameraPathPlayer {
+    pub fn new(name: String) -> CameraPathPlayer {
+        CameraPathPlayer {
+            name,
+            time: 0.0,
+            playing: true,
+            stopping: false,
+            blend: 0.0,
+        }
+    }
+
+    /// Advance by `dt` seconds. Returns false once the player has fully handed control back.
+    pub fn advance(&mut self, path: &CameraPath, dt: f32) -> bool {
+        if self.playing {
+            self.time += dt;
+
+            let duration = path.duration();
+            if self.time > duration {
+                if path.looping && duration > 0.0 {
+                    self.time %= duration;
+                } else {
+                    self.time = duration;
+                    self.stopping = true;
+                }
+            }
+        }
+
+        let step = dt / CAMERA_PATH_BLEND_SECONDS;
+        if self.stopping {
+            self.blend = (self.blend - step).max(0.0);
+            self.blend > 0.0
+        } else {
+            self.blend = (self.blend + step).min(1.0);
+            true
+        }
+    }
+
+    /// Smoothstepped blend weight, so the hand over eases in and out
+    pub fn weight(&self) -> f32 {
+        self.blend * self.blend * (3.0 - 2.0 * self.blend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, x: f32, z: f32) -> CameraKey {
+        CameraKey {
+            time,
+            position: Point3::new(x, 1.0, z),
+            target: Point3::origin(),
+        }
+    }
+
+    fn square(looping: bool) -> CameraPath {
+        CameraPath::new(
+            vec![
+                key(0.0, 1.0, 0.0),
+                key(1.0, 0.0, 1.0),
+                key(2.0, -1.0, 0.0),
+                key(3.0, 0.0, -1.0),
+            ],
+            looping,
+        )
+    }
+
+    fn eye(path: &CameraPath, time: f32) -> Vector3<f32> {
+        path.sample(time).unwrap().0.coords
+    }
+
+    /// Difference between the velocity just before a looping path wraps and just after
+    fn velocity_change_at_seam(path: &CameraPath) -> f32 {
+        let (end, dt) = (path.duration(), 1e-3);
+        let before = (eye(path, end) - eye(path, end - dt)) / dt;
+        let after = (eye(path, dt) - eye(path, 0.0)) / dt;
+        (before - after).norm()
+    }
+
+    #[test]
+    fn open_path_clamps_to_its_ends() {
+        let path = square(false);
+        assert_eq!(path.duration(), 3.0);
+        assert_eq!(eye(&path, -1.0), Vector3::new(1.0, 1.0, 0.0));
+        assert_eq!(eye(&path, 5.0), Vector3::new(0.0, 1.0, -1.0));
+    }
+
+    #[test]
+    fn looping_path_closes_back_to_the_first_key() {
+        let path = square(true);
+        assert_eq!(path.duration(), 4.0);
+        assert!((eye(&path, 4.0) - eye(&path, 0.0)).norm() < 1e-5);
+        assert!((eye(&path, 3.5) - Vector3::new(0.5, 1.0, -0.5)).norm() < 0.2);
+    }
+
+    #[test]
+    fn looping_path_is_smooth_across_the_seam() {
+        let path = square(true);
+        assert!(velocity_change_at_seam(&path) < 0.05);
+    }
+
+    #[test]
+    fn repeated_first_key_already_closes_the_loop() {
+        let mut keys = square(true).keys;
+        keys.push(key(4.0, 1.0, 0.0));
+        let path = CameraPath::new(keys, true);
+
+        assert_eq!(path.duration(), 4.0);
+        assert!(velocity_change_at_seam(&path) < 0.05);
+    }
+}
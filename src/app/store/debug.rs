@@ -0,0 +1,172 @@
+use nalgebra::Vector3;
+
+/// Views for debugging the renderer, all off by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DebugSettings {
+    /// The first loaded textures drawn as a row of thumbnails
+    pub textures: bool,
+    /// The water's refraction and reflection framebuffers drawn as thumbnails
+    pub framebuffers: bool,
+    /// Outlines of each primitive's bounding box
+    pub bounds: bool,
+    /// Meshes shaded by their surface normals instead of lit
+    pub normals: bool,
+    /// Meshes drawn as the edges of their triangles
+    pub wireframe: bool,
+    /// Meshes drawn without depth testing, brighter where more surfaces cover a pixel
+    pub overdraw: bool,
+}
+
+/// One toggleable view of `DebugSettings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    Textures,
+    Framebuffers,
+    Bounds,
+    Normals,
+    Wireframe,
+    Overdraw,
+}
+
+impl DebugView {
+    pub fn from_name(name: &str) -> Option<DebugView> {
+        match name {
+            "textures" => Some(DebugView::Textures),
+            "framebuffers" => Some(DebugView::Framebuffers),
+            "bounds" => Some(DebugView::Bounds),
+            "normals" => Some(DebugView::Normals),
+            "wireframe" => Some(DebugView::Wireframe),
+            "overdraw" => Some(DebugView::Overdraw),
+            _ => None,
+        }
+    }
+}
+
+impl DebugSettings {
+    pub fn set_enabled(&mut self, view: DebugView, enabled: bool) {
+        match view {
+            DebugView::Textures => self.textures = enabled,
+            DebugView::Framebuffers => self.framebuffers = enabled,
+            DebugView::Bounds => self.bounds = enabled,
+            DebugView::Normals => self.normals = enabled,
+            DebugView::Wireframe => self.wireframe = enabled,
+            DebugView::Overdraw => self.overdraw = enabled,
+        }
+    }
+
+    /// Mirrors `debugView` in `mesh-non-skinned-fragment.glsl` - 0 lit, 1 normals, 2 overdraw
+    pub fn mesh_view(&self) -> i32 {
+        if self.overdraw {
+            2
+        } else if self.normals {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// Colour of debug shapes drawn without one, and of bounding box outlines
+pub const DEFAULT_DEBUG_COLOR: [f32; 3] = [1.0, 0.0, 0.0];
+
+/// One end of a debug line, laid out as the wireframe shader's attributes
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+/// A label drawn in line strokes, facing the camera
+#[derive(Debug, Clone)]
+pub struct DebugText {
+    pub position: Vector3<f32>,
+    pub text: String,
+    /// Height of a character in world units
+    pub size: f32,
+    pub color: [f32; 3],
+}
+
+/// Shapes drawn for one frame, cleared at the start of every update. Drawn as lines in a
+/// single call after the opaque pass.
+#[derive(Debug, Default)]
+pub struct DebugDraw {
+    pub vertices: Vec<DebugVertex>,
+    pub texts: Vec<DebugText>,
+}
+
+/// Segments in each circle of a debug sphere
+static SPHERE_SEGMENTS: usize = 24;
+
+impl DebugDraw {
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.texts.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty() && self.texts.is_empty()
+    }
+
+    pub fn line(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: [f32; 3]) {
+        self.vertices.push(DebugVertex {
+            position: from.into(),
+            color,
+        });
+        self.vertices.push(DebugVertex {
+            position: to.into(),
+            color,
+        });
+    }
+
+    /// Outline of an axis aligned box
+    pub fn aabb(&mut self, centre: Vector3<f32>, extents: Vector3<f32>, color: [f32; 3]) {
+        let corner =
+            |x: f32, y: f32, z: f32| centre + extents.component_mul(&Vector3::new(x, y, z));
+
+        // (x, z) of the corners around the lower and upper faces
+        let around = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        for i in 0..4 {
+            let (x0, z0) = around[i];
+            let (x1, z1) = around[(i + 1) % 4];
+            self.line(corner(x0, -1.0, z0), corner(x1, -1.0, z1), color);
+            self.line(corner(x0, 1.0, z0), corner(x1, 1.0, z1), color);
+            self.line(corner(x0, -1.0, z0), corner(x0, 1.0, z0), color);
+        }
+    }
+
+    /// A circle around each axis
+    pub fn sphere(&mut self, centre: Vector3<f32>, radius: f32, color: [f32; 3]) {
+        let point = |axis: usize, i: usize| {
+            let angle = i as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let (s, c) = angle.sin_cos();
+            let mut p = Vector3::zeros();
+            p[(axis + 1) % 3] = c * radius;
+            p[(axis + 2) % 3] = s * radius;
+            centre + p
+        };
+
+        for axis in 0..3 {
+            for i in 0..SPHERE_SEGMENTS {
+                self.line(point(axis, i), point(axis, i + 1), color);
+            }
+        }
+    }
+
+    /// The x, y and z axes in red, green and blue
+    pub fn axes(&mut self, origin: Vector3<f32>, size: f32) {
+        self.line(origin, origin + Vector3::x() * size, [1.0, 0.0, 0.0]);
+        self.line(origin, origin + Vector3::y() * size, [0.0, 1.0, 0.0]);
+        self.line(origin, origin + Vector3::z() * size, [0.0, 0.0, 1.0]);
+    }
+
+    /// A label centred on `position`
+    pub fn text(&mut self, position: Vector3<f32>, text: &str, size: f32, color: [f32; 3]) {
+        self.texts.push(DebugText {
+            position,
+            text: text.to_string(),
+            size,
+            color,
+        });
+    }
+}
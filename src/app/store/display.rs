@@ -1,5 +1,7 @@
 pub struct Display {
     pub width: u32,
     pub height: u32,
+    /// Device pixels per CSS pixel
+    pub pixel_ratio: f32,
     pub changed_this_frame: bool,
 }
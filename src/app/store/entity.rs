@@ -1,22 +1,73 @@
-use super::State;
+use super::animation::Animator;
+use super::text::Text;
+use super::{Mesh, State};
 use crate::app::{
-    render::{rgl::uniform_buffer::UniformBuffer, CameraData, RenderStage, WebRenderer},
-    Assets, Control,
+    frustum::Frustum,
+    render::{
+        graph::{RenderLayers, RenderPass},
+        rgl::uniform_buffer::UniformBuffer,
+        CameraData, WebRenderer,
+    },
+    Assets, Control, GltfMesh,
 };
+use nalgebra::Vector3;
 use web_sys::WebGl2RenderingContext as GL;
 
 pub trait Entity {
-    fn should_render(&self, stage: &RenderStage) -> bool;
+    /// Render passes draw entities on the layers they filter for
+    fn layers(&self) -> RenderLayers;
+
+    /// Outputs of earlier render passes this entity samples, so those passes are run
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// Name scripts use to find the entity
+    fn name(&self) -> Option<&str> {
+        None
+    }
 
     fn update(&mut self, control: &Control);
 
+    /// Where the entity is in the world, for entities placed at a point
+    fn position(&self) -> Option<Vector3<f32>> {
+        None
+    }
+
+    /// Advance animations by `dt` seconds
+    fn animate(&mut self, _dt: f32, _assets: &Assets) {}
+
+    /// The loaded glTF the entity's animation clips come from
+    fn gltf<'a>(&self, _assets: &'a Assets) -> Option<&'a GltfMesh> {
+        None
+    }
+
+    /// Clips playing on the entity, for entities that can be animated
+    fn animator_mut(&mut self) -> Option<&mut Animator> {
+        None
+    }
+
+    /// The entity as text, for scripts to change what it says
+    fn text_mut(&mut self) -> Option<&mut Text> {
+        None
+    }
+
+    /// Called once a frame before any pass, for entities that draw into textures of their own
+    fn prepare(&self, _gl: &GL, _renderer: &WebRenderer, _assets: &Assets) {}
+
+    /// The entity as a glTF mesh, so renderers can draw matching meshes together
+    fn as_mesh(&self) -> Option<&Mesh> {
+        None
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn render(
         &self,
         gl: &GL,
         renderer: &WebRenderer,
         camera: &UniformBuffer<CameraData>,
-        clip_plane: [f32; 4],
-        stage: RenderStage,
+        frustum: &Frustum,
+        pass: &RenderPass,
         state: &State,
         assets: &Assets,
     );
@@ -0,0 +1,25 @@
+/// Exponential fog, thickening with distance from the camera and towards the ground
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub enabled: bool,
+    /// Fraction of light lost per unit travelled through fog at `height`
+    pub density: f32,
+    /// How quickly fog thins going up, 0 for the same density at every height
+    pub height_falloff: f32,
+    /// World height the fog has its `density` at, thicker below and thinner above
+    pub height: f32,
+    /// sRGB colour that distant geometry fades into, usually the sky's
+    pub color: [f32; 3],
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog {
+            enabled: false,
+            density: 0.01,
+            height_falloff: 0.0,
+            height: 0.0,
+            color: [0.53, 0.8, 0.98],
+        }
+    }
+}
@@ -0,0 +1,161 @@
+/// Standard mapping axes - left stick x, y then right stick x, y. Down and right are positive.
+pub const LEFT_STICK: (usize, usize) = (0, 1);
+pub const RIGHT_STICK: (usize, usize) = (2, 3);
+/// Standard mapping buttons that report an analog value
+pub const LEFT_TRIGGER: usize = 6;
+pub const RIGHT_TRIGGER: usize = 7;
+
+/// Raw values read from a controller, before dead zones are applied
+#[derive(Debug, Clone, Default)]
+pub struct GamepadState {
+    /// 0 to 1, analog for triggers
+    pub buttons: Vec<f32>,
+    /// -1 to 1
+    pub axes: Vec<f32>,
+}
+
+/// Somewhere to read a controller from, so the browser can be swapped for a fake one
+pub trait GamepadSource {
+    /// The current state of the first connected controller
+    fn poll(&mut self) -> Option<GamepadState>;
+}
+
+/// Used until a real source is attached
+pub struct NoGamepad;
+
+impl GamepadSource for NoGamepad {
+    fn poll(&mut self) -> Option<GamepadState> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeadZones {
+    /// Radius below which a stick reads as centred
+    pub stick: f32,
+    /// Value below which a button or trigger reads as released
+    pub trigger: f32,
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        DeadZones {
+            stick: 0.15,
+            trigger: 0.05,
+        }
+    }
+}
+
+impl DeadZones {
+    /// Zero small values and rescale the rest, so output still ramps smoothly from 0 to 1
+    pub fn apply(&self, raw: &GamepadState) -> GamepadState {
+        let mut axes: Vec<f32> = raw
+            .axes
+            .iter()
+            .map(|&a| a.signum() * dead_zone(a.abs(), self.stick))
+            .collect();
+
+        // Sticks use a radial dead zone so diagonals are not snapped to the axes
+        for (x, y) in [LEFT_STICK, RIGHT_STICK] {
+            if let (Some(&rx), Some(&ry)) = (raw.axes.get(x), raw.axes.get(y)) {
+                let len = (rx * rx + ry * ry).sqrt();
+                let scale = match dead_zone(len, self.stick) {
+                    v if v > 0.0 => v / len,
+                    _ => 0.0,
+                };
+                axes[x] = rx * scale;
+                axes[y] = ry * scale;
+            }
+        }
+
+        GamepadState {
+            buttons: raw
+                .buttons
+                .iter()
+                .map(|&b| dead_zone(b, self.trigger))
+                .collect(),
+            axes,
+        }
+    }
+}
+
+/// Zero below the dead zone, then ramp from 0 to 1 over the rest of the range
+fn dead_zone(v: f32, dead_zone: f32) -> f32 {
+    if v > dead_zone {
+        ((v - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Reports the same controller state on every poll, so input can be tested without a browser
+#[cfg(test)]
+pub struct FakeGamepad(pub Option<GamepadState>);
+
+#[cfg(test)]
+impl FakeGamepad {
+    /// A standard mapping controller with the given sticks and triggers, everything else released
+    pub fn with(left: (f32, f32), right: (f32, f32), triggers: (f32, f32)) -> FakeGamepad {
+        let mut buttons = vec![0.0; 17];
+        buttons[LEFT_TRIGGER] = triggers.0;
+        buttons[RIGHT_TRIGGER] = triggers.1;
+
+        FakeGamepad(Some(GamepadState {
+            buttons,
+            axes: vec![left.0, left.1, right.0, right.1],
+        }))
+    }
+}
+
+#[cfg(test)]
+impl GamepadSource for FakeGamepad {
+    fn poll(&mut self) -> Option<GamepadState> {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(pad: FakeGamepad) -> GamepadState {
+        DeadZones::default().apply(&pad.0.unwrap())
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn stick_inside_radial_dead_zone_is_centred() {
+        // Each axis alone is under the dead zone, and so is their length
+        let state = applied(FakeGamepad::with((0.1, -0.1), (0.0, 0.14), (0.0, 0.0)));
+        assert!(state.axes.iter().all(|&a| a == 0.0));
+    }
+
+    #[test]
+    fn stick_is_rescaled_along_its_direction() {
+        let state = applied(FakeGamepad::with((0.3, -0.4), (0.0, 0.0), (0.0, 0.0)));
+
+        // Length 0.5 maps to (0.5 - 0.15) / 0.85 without snapping to an axis
+        let (x, y) = (state.axes[LEFT_STICK.0], state.axes[LEFT_STICK.1]);
+        let length = (0.5 - 0.15) / 0.85;
+        assert!(close((x * x + y * y).sqrt(), length));
+        assert!(close(x / y, 0.3 / -0.4));
+    }
+
+    #[test]
+    fn stick_at_full_tilt_stays_at_full_tilt() {
+        let state = applied(FakeGamepad::with((0.6, 0.8), (-1.0, 0.0), (0.0, 0.0)));
+        assert!(close(state.axes[LEFT_STICK.0], 0.6));
+        assert!(close(state.axes[LEFT_STICK.1], 0.8));
+        assert!(close(state.axes[RIGHT_STICK.0], -1.0));
+    }
+
+    #[test]
+    fn triggers_ramp_from_their_dead_zone() {
+        let state = applied(FakeGamepad::with((0.0, 0.0), (0.0, 0.0), (0.04, 0.525)));
+        assert_eq!(state.buttons[LEFT_TRIGGER], 0.0);
+        assert!(close(state.buttons[RIGHT_TRIGGER], 0.5));
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use super::gamepad::{
+    DeadZones, GamepadSource, GamepadState, NoGamepad, LEFT_STICK, LEFT_TRIGGER, RIGHT_STICK,
+    RIGHT_TRIGGER,
+};
+use super::keyboard::{KeyCode, Keyboard};
+use super::mouse::Mouse;
+
+/// A physical input that can drive an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    /// Indexed by `MouseEvent.button` - 0 left, 1 middle, 2 right
+    MouseButton(u8),
+    /// Indexed by the standard gamepad mapping, analog for triggers
+    GamepadButton(u8),
+    /// One direction of a gamepad axis, reading 0 to 1
+    GamepadAxis {
+        axis: u8,
+        positive: bool,
+    },
+}
+
+impl Binding {
+    /// Parse a key name as accepted by `KeyCode::from_name`, `Mouse0` to `Mouse2`,
+    /// a gamepad button `Pad0` to `Pad16` or a gamepad axis direction such as `Axis1-`
+    pub fn from_name(name: &str) -> Option<Binding> {
+        let lower = name.to_ascii_lowercase();
+
+        if let Some(button) = lower.strip_prefix("mouse") {
+            return button.parse().ok().map(Binding::MouseButton);
+        }
+        if let Some(button) = lower.strip_prefix("pad") {
+            return button.parse().ok().map(Binding::GamepadButton);
+        }
+        if let Some(axis) = lower.strip_prefix("axis") {
+            let (axis, positive) = match axis.split_at(axis.len().saturating_sub(1)) {
+                (axis, "+") => (axis, true),
+                (axis, "-") => (axis, false),
+                _ => return None,
+            };
+            return axis
+                .parse()
+                .ok()
+                .map(|axis| Binding::GamepadAxis { axis, positive });
+        }
+        KeyCode::from_name(name).map(Binding::Key)
+    }
+
+    fn axis(axis: usize, positive: bool) -> Binding {
+        Binding::GamepadAxis {
+            axis: axis as u8,
+            positive,
+        }
+    }
+}
+
+/// Named actions such as `move_forward`, each bound to any number of inputs
+pub struct InputMap {
+    actions: HashMap<String, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut map = InputMap {
+            actions: HashMap::new(),
+        };
+
+        map.bind("move_forward", Binding::Key(KeyCode::W));
+        map.bind("move_forward", Binding::Key(KeyCode::ArrowUp));
+        map.bind("move_back", Binding::Key(KeyCode::S));
+        map.bind("move_back", Binding::Key(KeyCode::ArrowDown));
+        map.bind("move_left", Binding::Key(KeyCode::A));
+        map.bind("move_left", Binding::Key(KeyCode::ArrowLeft));
+        map.bind("move_right", Binding::Key(KeyCode::D));
+        map.bind("move_right", Binding::Key(KeyCode::ArrowRight));
+
+        map.bind("move_forward", Binding::axis(LEFT_STICK.1, false));
+        map.bind("move_back", Binding::axis(LEFT_STICK.1, true));
+        map.bind("move_left", Binding::axis(LEFT_STICK.0, false));
+        map.bind("move_right", Binding::axis(LEFT_STICK.0, true));
+
+        // Orbit panning is left to the stick, keys only walk in first person
+        map.bind("pan_forward", Binding::axis(LEFT_STICK.1, false));
+        map.bind("pan_back", Binding::axis(LEFT_STICK.1, true));
+        map.bind("pan_left", Binding::axis(LEFT_STICK.0, false));
+        map.bind("pan_right", Binding::axis(LEFT_STICK.0, true));
+
+        map.bind("look_up", Binding::axis(RIGHT_STICK.1, false));
+        map.bind("look_down", Binding::axis(RIGHT_STICK.1, true));
+        map.bind("look_left", Binding::axis(RIGHT_STICK.0, false));
+        map.bind("look_right", Binding::axis(RIGHT_STICK.0, true));
+
+        map.bind("zoom_in", Binding::GamepadButton(RIGHT_TRIGGER as u8));
+        map.bind("zoom_out", Binding::GamepadButton(LEFT_TRIGGER as u8));
+
+        map
+    }
+}
+
+impl InputMap {
+    /// Add another binding to an action, creating the action if needed
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_owned()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replace every binding of an action
+    pub fn set_bindings(&mut self, action: &str, bindings: Vec<Binding>) {
+        self.actions.insert(action.to_owned(), bindings);
+    }
+
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(|b| &b[..]).unwrap_or(&[])
+    }
+}
+
+/// Raw device state together with the action bindings used to read it
+pub struct Input {
+    pub keyboard: Keyboard,
+    pub mouse: Mouse,
+    /// Controller state from the last poll, with dead zones applied
+    pub gamepad: GamepadState,
+    pub dead_zones: DeadZones,
+    gamepad_source: Box<dyn GamepadSource>,
+    pub map: InputMap,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            keyboard: Keyboard::default(),
+            mouse: Mouse::default(),
+            gamepad: GamepadState::default(),
+            dead_zones: DeadZones::default(),
+            gamepad_source: Box::new(NoGamepad),
+            map: InputMap::default(),
+        }
+    }
+}
+
+impl Input {
+    pub fn set_gamepad_source(&mut self, source: Box<dyn GamepadSource>) {
+        self.gamepad_source = source;
+    }
+
+    /// Read the controller, called once per frame
+    pub fn poll_gamepad(&mut self) {
+        self.gamepad = match self.gamepad_source.poll() {
+            Some(raw) => self.dead_zones.apply(&raw),
+            None => GamepadState::default(),
+        };
+    }
+
+    /// Whether any input bound to the action is held. Unknown actions are never pressed.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.value(action) > 0.0
+    }
+
+    /// Strength of the action from 0 to 1, taking the strongest of its bindings.
+    /// Keys and mouse buttons are either 0 or 1, sticks and triggers anywhere between.
+    pub fn value(&self, action: &str) -> f32 {
+        self.map
+            .bindings(action)
+            .iter()
+            .map(|b| self.binding_value(b))
+            .fold(0.0, f32::max)
+    }
+
+    /// `positive` minus `negative`, for movement axes
+    pub fn axis(&self, negative: &str, positive: &str) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+
+    fn binding_value(&self, binding: &Binding) -> f32 {
+        let held = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+
+        match *binding {
+            Binding::Key(k) => held(self.keyboard.get_pressed(k)),
+            Binding::MouseButton(b) => held(self.mouse.get_button(b)),
+            Binding::GamepadButton(b) => {
+                self.gamepad.buttons.get(b as usize).copied().unwrap_or(0.0)
+            }
+            Binding::GamepadAxis { axis, positive } => {
+                let v = self.gamepad.axes.get(axis as usize).copied().unwrap_or(0.0);
+                if positive {
+                    v.max(0.0)
+                } else {
+                    (-v).max(0.0)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::gamepad::FakeGamepad;
+    use super::*;
+
+    fn input_with(pad: FakeGamepad) -> Input {
+        let mut input = Input::default();
+        input.set_gamepad_source(Box::new(pad));
+        input.poll_gamepad();
+        input
+    }
+
+    #[test]
+    fn no_gamepad_reads_as_released() {
+        let mut input = Input::default();
+        input.poll_gamepad();
+        assert_eq!(input.value("zoom_in"), 0.0);
+        assert_eq!(input.axis("pan_left", "pan_right"), 0.0);
+    }
+
+    #[test]
+    fn triggers_are_analog() {
+        let input = input_with(FakeGamepad::with((0.0, 0.0), (0.0, 0.0), (0.0, 0.525)));
+        assert!((input.value("zoom_in") - 0.5).abs() < 1e-5);
+        assert_eq!(input.value("zoom_out"), 0.0);
+        assert!(input.pressed("zoom_in"));
+    }
+
+    #[test]
+    fn stick_directions_read_as_separate_actions() {
+        // Up on the stick is negative
+        let input = input_with(FakeGamepad::with((0.0, -1.0), (1.0, 0.0), (0.0, 0.0)));
+        assert_eq!(input.value("move_forward"), 1.0);
+        assert_eq!(input.value("move_back"), 0.0);
+        assert_eq!(input.axis("pan_back", "pan_forward"), 1.0);
+        assert_eq!(input.axis("look_left", "look_right"), 1.0);
+    }
+
+    #[test]
+    fn keys_and_sticks_share_actions() {
+        let mut input = input_with(FakeGamepad::with((0.0, -0.5), (0.0, 0.0), (0.0, 0.0)));
+        let stick = input.value("move_forward");
+        assert!(stick > 0.0 && stick < 1.0);
+
+        // The strongest binding wins
+        input.keyboard.set_pressed(KeyCode::W, true);
+        assert_eq!(input.value("move_forward"), 1.0);
+
+        // Keys do not pan the orbit camera
+        assert_eq!(input.value("pan_forward"), stick);
+    }
+
+    #[test]
+    fn buttons_map_to_bound_actions() {
+        let mut pad = FakeGamepad::with((0.0, 0.0), (0.0, 0.0), (0.0, 0.0));
+        if let Some(state) = &mut pad.0 {
+            state.buttons[0] = 1.0;
+        }
+        let mut input = input_with(pad);
+
+        assert!(!input.pressed("jump"));
+        input.map.bind("jump", Binding::from_name("Pad0").unwrap());
+        assert!(input.pressed("jump"));
+
+        input.map.unbind("jump");
+        assert!(!input.pressed("jump"));
+    }
+
+    #[test]
+    fn binding_names_parse() {
+        assert_eq!(Binding::from_name("Pad7"), Some(Binding::GamepadButton(7)));
+        assert_eq!(
+            Binding::from_name("Axis1-"),
+            Some(Binding::GamepadAxis {
+                axis: 1,
+                positive: false
+            })
+        );
+        assert_eq!(Binding::from_name("Mouse2"), Some(Binding::MouseButton(2)));
+        assert_eq!(Binding::from_name("Axis1"), None);
+    }
+}
@@ -1,13 +1,24 @@
+use std::collections::HashSet;
+
 #[derive(Default)]
 pub struct Keyboard {
-    w: bool,
-    s: bool,
-    a: bool,
-    d: bool,
+    pressed: HashSet<KeyCode>,
 }
 
-#[derive(FromPrimitive, Debug, Copy, Clone)]
+#[derive(FromPrimitive, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum KeyCode {
+    Backspace = 8,
+    Tab = 9,
+    Enter = 13,
+    Shift = 16,
+    Control = 17,
+    Alt = 18,
+    Escape = 27,
+    Space = 32,
+    ArrowLeft = 37,
+    ArrowUp = 38,
+    ArrowRight = 39,
+    ArrowDown = 40,
     Zero = 48,
     One = 49,
     Two = 50,
@@ -46,24 +57,46 @@ pub enum KeyCode {
     Z = 90,
 }
 
+impl KeyCode {
+    /// Parse a key name such as `"W"`, `"7"`, `"Space"` or `"ArrowUp"`, ignoring case
+    pub fn from_name(name: &str) -> Option<KeyCode> {
+        let name = name.to_ascii_lowercase();
+
+        let key = match name.as_str() {
+            "backspace" => KeyCode::Backspace,
+            "tab" => KeyCode::Tab,
+            "enter" => KeyCode::Enter,
+            "shift" => KeyCode::Shift,
+            "control" | "ctrl" => KeyCode::Control,
+            "alt" => KeyCode::Alt,
+            "escape" | "esc" => KeyCode::Escape,
+            "space" | " " => KeyCode::Space,
+            "arrowleft" | "left" => KeyCode::ArrowLeft,
+            "arrowup" | "up" => KeyCode::ArrowUp,
+            "arrowright" | "right" => KeyCode::ArrowRight,
+            "arrowdown" | "down" => KeyCode::ArrowDown,
+            // Letters and digits share their key code with their upper case ASCII value
+            _ => match name.as_bytes() {
+                [c] if c.is_ascii_alphanumeric() => {
+                    return num::FromPrimitive::from_u8(c.to_ascii_uppercase())
+                }
+                _ => return None,
+            },
+        };
+        Some(key)
+    }
+}
+
 impl Keyboard {
     pub fn get_pressed(&self, key_code: KeyCode) -> bool {
-        match key_code {
-            KeyCode::W => self.w,
-            KeyCode::A => self.a,
-            KeyCode::S => self.s,
-            KeyCode::D => self.d,
-            _ => false,
-        }
+        self.pressed.contains(&key_code)
     }
 
     pub fn set_pressed(&mut self, key_code: KeyCode, pressed: bool) {
-        match key_code {
-            KeyCode::W => self.w = pressed,
-            KeyCode::A => self.a = pressed,
-            KeyCode::S => self.s = pressed,
-            KeyCode::D => self.d = pressed,
-            _ => (),
+        if pressed {
+            self.pressed.insert(key_code);
+        } else {
+            self.pressed.remove(&key_code);
         }
     }
 }
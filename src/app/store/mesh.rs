@@ -1,22 +1,28 @@
-use crate::app::render::mesh::cube::Cube;
+use crate::app::frustum::{Aabb, Frustum};
+use crate::app::render::draw_list::{DrawItem, DrawList};
+use crate::app::render::graph::{RenderLayers, RenderPass, SHADOW};
+use crate::app::render::material::AlphaMode;
 use crate::app::render::mesh::MeshRenderOpts;
-use crate::app::render::mesh::NonSkinnedGltfMesh;
-use crate::app::render::render_trait::Render;
+use crate::app::render::mesh::MAX_MORPH_TARGETS;
 use crate::app::render::rgl::shader::ShaderKind;
 use crate::app::render::rgl::uniform_buffer::UniformBuffer;
-use crate::app::render::RenderStage;
 use crate::app::render::{CameraData, WebRenderer};
-use crate::app::Assets;
 use crate::app::Control;
 use crate::app::State;
+use crate::app::{Assets, GltfMesh};
 use nalgebra;
-use nalgebra::ArrayStorage;
+use nalgebra::Isometry3;
+use nalgebra::Matrix4;
+use nalgebra::Point3;
+use nalgebra::Scale3;
 use nalgebra::Vector3;
 use web_sys::WebGl2RenderingContext as GL;
 
+use super::animation::Animator;
 use super::entity::Entity;
 #[derive(Debug, Clone)]
 pub struct Mesh {
+    pub name: Option<String>,
     pub mesh: usize,
     pub mat: usize,
 
@@ -24,148 +30,260 @@ pub struct Mesh {
     pub scale: Vector3<f32>,
     pub rotation: Vector3<f32>,
     pub update: Option<String>,
+    pub animator: Animator,
 }
 
-impl Entity for Mesh {
-    fn should_render(&self, stage: &RenderStage) -> bool {
-        *stage != RenderStage::Water
+impl Mesh {
+    /// Whether the mesh is drawn in its rest pose, so it looks the same as any other entity
+    /// sharing its glTF and material and can be drawn in the same instanced call
+    pub fn instanceable(&self) -> bool {
+        self.animator.playing.is_empty() && self.animator.morph_override.is_none()
     }
 
-    fn render(
-        &self,
-        gl: &GL,
+    /// The entity's own position, rotation and scale
+    pub fn model_mat(&self) -> Matrix4<f32> {
+        Isometry3::new(self.position, self.rotation).to_homogeneous()
+            * Scale3::from(self.scale).to_homogeneous()
+    }
+
+    /// Queue draws of meshes sharing a glTF and material, culling each against the frustum.
+    /// More than one are drawn instanced, so they must all be `instanceable`.
+    /// Blended draws are sorted by their distance from `eye`.
+    pub fn queue_draws<'a>(
+        meshes: &[&Mesh],
+        list: &mut DrawList<'a>,
         renderer: &WebRenderer,
-        camera: &UniformBuffer<CameraData>,
-        clip_plane: [f32; 4],
-        stage: RenderStage,
-        state: &State,
-        assets: &Assets,
+        frustum: &Frustum,
+        pass: &RenderPass,
+        eye: Point3<f32>,
+        assets: &'a Assets,
     ) {
-        //log::info!("Rendering mesh");
+        let first = match meshes.first() {
+            Some(m) => *m,
+            None => return,
+        };
+
+        let doc = match assets.get_gltf(first.mesh) {
+            Some(doc) => doc,
+            None => return,
+        };
 
-        // Render Meshes
-        let non_skinned_shader = renderer
-            .shader_sys
-            .get_shader(&ShaderKind::NonSkinnedMesh)
-            .unwrap();
-        renderer
-            .shader_sys
-            .use_program(gl, ShaderKind::NonSkinnedMesh);
+        // Joint matrices are per entity, so skins are never instanced
+        if meshes.len() > 1 && !doc.skins.is_empty() {
+            for m in meshes {
+                Mesh::queue_draws(&[m], list, renderer, frustum, pass, eye, assets);
+            }
+            return;
+        }
 
-        let mut gizmos: Vec<(Vector3<f32>, Vector3<f32>)> = Vec::new();
+        let single = meshes.len() == 1;
+        let instance_mats: Vec<Matrix4<f32>> = meshes.iter().map(|m| m.model_mat()).collect();
 
+        // A single mesh is placed by `model`, instances by their own transforms after it
         let mut mesh_opts = MeshRenderOpts {
-            pos: self.position,
-            scale: self.scale,
-            rot: self.rotation,
-            clip_plane,
+            pos: if single {
+                first.position
+            } else {
+                Vector3::zeros()
+            },
+            scale: if single {
+                first.scale
+            } else {
+                Vector3::from_element(1.0)
+            },
+            rot: if single {
+                first.rotation
+            } else {
+                Vector3::zeros()
+            },
+            node: Matrix4::identity(),
+            morph_weights: [0.0; MAX_MORPH_TARGETS],
+            wireframe: false,
+            clip_plane: pass.clip_plane,
             flip_camera_y: false,
+            instances: 1,
+        };
+
+        let world_transforms = match &first.animator.world_transforms {
+            Some(w) => w,
+            None => &doc.world_transforms,
         };
 
-        if let Some(doc) = assets.get_gltf(self.mesh) {
-            for node in doc.doc.nodes() {
-                match node.transform() {
-                    gltf::scene::Transform::Matrix { matrix: _ } => todo!(),
-                    gltf::scene::Transform::Decomposed {
-                        translation,
-                        rotation: _,
-                        scale,
-                    } => {
-                        let s = Vector3::from_array_storage(ArrayStorage([scale]));
-
-                        mesh_opts.pos = self.position
-                            + Vector3::from_array_storage(ArrayStorage([translation]));
-
-                        mesh_opts.scale = mesh_opts.scale.component_mul(&s);
+        for &node_index in &doc.mesh_nodes {
+            let node = doc.doc.nodes().nth(node_index).unwrap();
+            let skin = node.skin().map(|s| &doc.skins[s.index()]);
+
+            // Skinned vertices are placed by their joints, which already include the node's parents
+            let node_mat = match skin {
+                Some(_) => Matrix4::identity(),
+                None => world_transforms[node_index],
+            };
+            mesh_opts.node = node_mat;
+
+            mesh_opts.morph_weights = [0.0; MAX_MORPH_TARGETS];
+            for (w, v) in mesh_opts
+                .morph_weights
+                .iter_mut()
+                .zip(first.animator.node_morph_weights(node_index, doc))
+            {
+                *w = *v;
+            }
+
+            // Every variant shares the mesh's vertex arrays, as they all fix their locations
+            let shader = match (pass.depth_only, skin.is_some()) {
+                (false, false) => ShaderKind::NonSkinnedMesh,
+                (false, true) => ShaderKind::SkinnedMesh,
+                (true, false) => ShaderKind::Shadow,
+                (true, true) => ShaderKind::SkinnedShadow,
+            };
+            let joints = skin.map(|skin| skin.joint_matrices(world_transforms));
+
+            if let Some(m) = node.mesh() {
+                //get primitives
+                for p in m.primitives() {
+                    let material = p.material().index().unwrap_or(first.mat);
+                    let alpha_mode = assets
+                        .get_material(material)
+                        .unwrap_or_else(|| assets.default_material())
+                        .alpha_mode;
+
+                    // Blended primitives are left to the transparent pass, so cast no shadows
+                    if alpha_mode.is_blended() != pass.transparent {
+                        continue;
                     }
-                }
 
-                if let Some(m) = node.mesh() {
-                    //get primitives
-                    for p in m.primitives() {
-                        let meshdata = NonSkinnedGltfMesh {
-                            mesh: &p,
-                            buffers: &doc.buffers,
-                            opts: &mesh_opts,
-                        };
-
-                        let bounds = p.bounding_box();
-                        let min = Vector3::from_data(ArrayStorage([bounds.min]));
-                        let max = Vector3::from_data(ArrayStorage([bounds.max]));
-
-                        let pos = (min + max) * 0.5f32;
-                        let extents = (max - pos).component_mul(&mesh_opts.scale);
-
-                        gizmos.push((
-                            pos + mesh_opts.pos + Vector3::new(0.0, extents.y, 0.0),
-                            extents,
-                        ));
+                    let bounds = p.bounding_box();
+                    let bounds = Aabb::new(Point3::from(bounds.min), Point3::from(bounds.max));
+
+                    // Each skinned vertex is a weighted mix of its joints' transforms, so lies
+                    // within the bind pose box moved by every joint
+                    let bounds = match &joints {
+                        Some(joints) => joints
+                            .iter()
+                            .map(|j| bounds.transform(j))
+                            .reduce(|a, b| a.union(&b))
+                            .unwrap_or(bounds),
+                        None => bounds,
+                    };
+
+                    // Cull each instance on its own, drawing only those in view
+                    let mut visible_instances = Vec::with_capacity(instance_mats.len());
+                    for instance in &instance_mats {
+                        let world_bounds = bounds.transform(&(instance * node_mat));
+                        let centre = (world_bounds.min.coords + world_bounds.max.coords) * 0.5;
+
+                        list.push_gizmo(centre, (world_bounds.max - world_bounds.min) * 0.5);
+
+                        let visible = frustum.intersects_aabb(&world_bounds);
+                        renderer.count_primitive(visible);
+                        if visible {
+                            visible_instances
+                                .push((*instance, (centre - eye.coords).norm_squared()));
+                        }
+                    }
+                    if visible_instances.is_empty() {
+                        continue;
+                    }
+
+                    // if let Uri { uri, .. } = p
+                    //     .material()
+                    //     .pbr_metallic_roughness()
+                    //     .base_color_texture()
+                    //     .unwrap()
+                    //     .texture()
+                    //     .source()
+                    //     .source()
+                    // {}
 
-                        // if let Uri { uri, .. } = p
-                        //     .material()
-                        //     .pbr_metallic_roughness()
-                        //     .base_color_texture()
-                        //     .unwrap()
-                        //     .texture()
-                        //     .source()
-                        //     .source()
-                        // {}
-
-                        if let Some(mat) = assets.get_material(match p.material().index() {
-                            Some(i) => i,
-                            None => self.mat,
-                        }) {
-                            mat.uniform(gl, &non_skinned_shader, assets);
-                        };
-
-                        //    log::info!("{}", mat.normal);
-
-                        // let mesh_mat = MatAlbedo {
-                        //     shader: non_skinned_shader.clone(),
-                        //     tex: assets.get_tex(mat.tex),
-                        //     normal: assets.get_tex(mat.normal),
-                        // };
-
-                        let block_index = non_skinned_shader.get_uniform_block_index(gl, "Camera");
-                        camera.bind_base(gl, &non_skinned_shader, block_index, 2);
-
-                        //mesh_mat.bind_uniforms(gl, camera, state);
-
-                        //    web_sys::console::log_1(&p.index().into());
-                        //web_sys::console::log_1(&"Rendering mesh".into());
-
-                        let b = renderer.prepare_for_render(
-                            gl,
-                            &meshdata,
-                            non_skinned_shader,
-                            &format!("{}{}{}", &self.mesh, m.index(), p.index()),
-                            state,
-                        );
-
-                        meshdata.render(gl, &b, non_skinned_shader, renderer, camera, state);
+                    let item = |instances: Vec<Matrix4<f32>>, depth: f32| DrawItem {
+                        shader,
+                        // Depth only draws need a material just to cut out masked shadows
+                        material: match (pass.depth_only, alpha_mode) {
+                            (false, _) | (true, AlphaMode::Mask(_)) => Some(material),
+                            (true, _) => None,
+                        },
+                        alpha_mode,
+                        depth,
+                        vao: format!("{}{}{}", &first.mesh, m.index(), p.index()),
+                        primitive: p.clone(),
+                        buffers: &doc.buffers,
+                        opts: mesh_opts,
+                        joints: joints.clone(),
+                        instances,
+                    };
+
+                    if single {
+                        list.push(item(Vec::new(), visible_instances[0].1));
+                    } else if pass.transparent {
+                        // Each copy is sorted on its own, so overlapping copies blend in order
+                        for (instance, depth) in visible_instances {
+                            list.push(item(vec![instance], depth));
+                        }
+                    } else {
+                        list.push(item(
+                            visible_instances.iter().map(|(i, _)| *i).collect(),
+                            0.0,
+                        ));
                     }
                 }
             }
         }
-        if stage == RenderStage::Opaques {
-            let wireframe_shader = renderer
-                .shader_sys
-                .get_shader(&ShaderKind::WireFrame)
-                .unwrap();
-
-            renderer.shader_sys.use_program(gl, ShaderKind::WireFrame);
-            for (pos, extents) in gizmos {
-                let b = Cube::new(pos, extents);
-                //    log::info!("Gizmo at p: {} e: {}", pos, extents);
-                let buff = renderer.prepare_for_render(gl, &b, wireframe_shader, "gizmo", state);
-
-                b.render(gl, &buff, wireframe_shader, renderer, camera, state)
-            }
+    }
+}
+
+impl Entity for Mesh {
+    fn layers(&self) -> RenderLayers {
+        RenderLayers::OPAQUE
+    }
+
+    fn inputs(&self) -> Vec<&'static str> {
+        vec![SHADOW]
+    }
+
+    fn render(
+        &self,
+        gl: &GL,
+        renderer: &WebRenderer,
+        camera: &UniformBuffer<CameraData>,
+        frustum: &Frustum,
+        pass: &RenderPass,
+        state: &State,
+        assets: &Assets,
+    ) {
+        let mut list = DrawList::default();
+        let eye = state.camera().get_eye_pos();
+        Mesh::queue_draws(&[self], &mut list, renderer, frustum, pass, eye, assets);
+        renderer.draw_list(gl, list, camera, pass, state, assets);
+    }
+
+    fn as_mesh(&self) -> Option<&Mesh> {
+        Some(self)
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn position(&self) -> Option<Vector3<f32>> {
+        Some(self.position)
+    }
+
+    fn animate(&mut self, dt: f32, assets: &Assets) {
+        if let Some(gltf) = assets.get_gltf(self.mesh) {
+            self.animator.advance(dt, gltf);
         }
     }
 
-    fn update(&mut self, control: &Control) {
-        if let Some(f) = &self.update {
+    fn gltf<'a>(&self, assets: &'a Assets) -> Option<&'a GltfMesh> {
+        assets.get_gltf(self.mesh)
+    }
+
+    fn animator_mut(&mut self) -> Option<&mut Animator> {
+        Some(&mut self.animator)
+    }
+
+    fn update(&mut self, _control: &Control) {
+        if let Some(_f) = &self.update {
             //    control.run_func(&f, m.clone());
         }
     }
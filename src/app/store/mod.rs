@@ -1,19 +1,38 @@
+pub mod animation;
+pub mod camera_path;
+pub mod debug;
 pub mod display;
 pub mod entity;
+pub mod fog;
+pub mod gamepad;
+pub mod input;
 pub mod keyboard;
 pub mod mesh;
 mod mouse;
+pub mod post;
+pub mod skybox;
+pub mod sun;
+pub mod text;
+pub mod touch;
 
+use self::camera_path::{CameraPath, CameraPathPlayer};
+use self::debug::{DebugDraw, DebugSettings};
 use self::display::Display;
 use self::entity::Entity;
+use self::fog::Fog;
+use self::input::Input;
 use self::keyboard::KeyCode;
-use self::keyboard::Keyboard;
-use self::mouse::*;
+use self::post::PostSettings;
+use self::sun::Sun;
+use self::touch::{Gesture, GestureRecognizer, TouchPoint};
 
-mod camera;
+pub mod camera;
 use self::camera::*;
 pub use self::mesh::Mesh;
 
+use crate::app::Assets;
+use std::collections::HashMap;
+
 pub mod water;
 
 pub struct Store {
@@ -39,10 +58,39 @@ pub struct State {
     dt_rolling: f32,
     pub display: super::display::Display,
     camera: Camera,
-    keyboard: Keyboard,
-    mouse: Mouse,
+    pub sun: Sun,
+    pub fog: Fog,
+    pub post: PostSettings,
+    pub debug: DebugSettings,
+    /// Lines and labels from scripts and the engine, drawn by the next render
+    pub debug_draw: DebugDraw,
+    pub input: Input,
+    /// The canvas has captured the pointer for mouse look
+    pointer_locked: bool,
+    gestures: GestureRecognizer,
+    /// Gestures recognised since the last update, waiting to be passed to scripts
+    gesture_events: Vec<Gesture>,
     show_scenery: bool,
     pub entities: Vec<std::rc::Rc<std::cell::RefCell<dyn Entity>>>,
+    camera_paths: HashMap<String, CameraPath>,
+    camera_path_player: Option<CameraPathPlayer>,
+    pub camera_path_requests: Vec<CameraPathRequest>,
+}
+
+/// A camera path defined by a script, waiting for its assets to load
+pub struct CameraPathRequest {
+    pub name: String,
+    pub source: CameraPathSource,
+    pub autoplay: bool,
+}
+
+pub enum CameraPathSource {
+    Keys(CameraPath),
+    Gltf {
+        gltf: usize,
+        animation: Option<String>,
+        looping: bool,
+    },
 }
 
 impl State {
@@ -53,15 +101,26 @@ impl State {
             next_log: 0.,
             dt_rolling: 0.,
             camera: Camera::new(),
-            keyboard: Keyboard::default(),
-            mouse: Mouse::default(),
+            sun: Sun::default(),
+            fog: Fog::default(),
+            post: PostSettings::default(),
+            debug: DebugSettings::default(),
+            debug_draw: DebugDraw::default(),
+            input: Input::default(),
+            pointer_locked: false,
+            gestures: GestureRecognizer::default(),
+            gesture_events: vec![],
             display: Display {
                 width: 16,
                 height: 9,
+                pixel_ratio: 1.0,
                 changed_this_frame: true,
             },
             show_scenery: true,
             entities: vec![],
+            camera_paths: HashMap::new(),
+            camera_path_player: None,
+            camera_path_requests: vec![],
         }
     }
 
@@ -76,16 +135,127 @@ impl State {
         self.clock
     }
 
+    pub fn pointer_locked(&self) -> bool {
+        self.pointer_locked
+    }
+
     pub fn show_scenery(&self) -> bool {
         self.show_scenery
     }
 
+    /// Turn script camera path requests into playable paths, once their glTFs have loaded
+    pub fn resolve_camera_paths(&mut self, assets: &Assets) {
+        let mut autoplay = None;
+
+        for request in self.camera_path_requests.drain(..) {
+            let path = match request.source {
+                CameraPathSource::Keys(path) => Some(path),
+                CameraPathSource::Gltf {
+                    gltf,
+                    animation,
+                    looping,
+                } => assets
+                    .get_gltf(gltf)
+                    .and_then(|g| CameraPath::from_gltf(g, animation.as_deref(), looping)),
+            };
+
+            match path {
+                Some(path) => {
+                    if request.autoplay {
+                        autoplay = Some(request.name.clone());
+                    }
+                    self.camera_paths.insert(request.name, path);
+                }
+                None => log::warn!("Could not build camera path {}", request.name),
+            }
+        }
+
+        if let Some(name) = autoplay {
+            self.msg(&Msg::PlayCameraPath(name));
+        }
+    }
+
+    pub fn clear_camera_paths(&mut self) {
+        self.camera_paths.clear();
+        self.camera_path_requests.clear();
+        self.camera_path_player = None;
+        self.camera.set_override(None);
+    }
+
+    fn update_camera_path(&mut self, dt: f32) {
+        let player = match &mut self.camera_path_player {
+            Some(p) => p,
+            None => return,
+        };
+
+        let path = match self.camera_paths.get(&player.name) {
+            Some(p) => p,
+            None => {
+                self.camera_path_player = None;
+                self.camera.set_override(None);
+                return;
+            }
+        };
+
+        if player.advance(path, dt / 1000.0) {
+            if let Some((eye, target)) = path.sample(player.time) {
+                self.camera.set_override(Some(CameraOverride {
+                    eye,
+                    target,
+                    weight: player.weight(),
+                }));
+            }
+        } else {
+            self.camera_path_player = None;
+            self.camera.set_override(None);
+        }
+    }
+
+    /// Advance every entity's animations by `dt` milliseconds
+    pub fn animate(&mut self, dt: f32, assets: &Assets) {
+        for e in &self.entities {
+            e.borrow_mut().animate(dt / 1000.0, assets);
+        }
+    }
+
+    /// The first entity given this name by its script
+    pub fn find_entity(&self, name: &str) -> Option<std::rc::Rc<std::cell::RefCell<dyn Entity>>> {
+        self.entities
+            .iter()
+            .find(|e| e.borrow().name() == Some(name))
+            .cloned()
+    }
+
+    /// Take the gestures recognised since this was last called
+    pub fn take_gesture_events(&mut self) -> Vec<Gesture> {
+        std::mem::take(&mut self.gesture_events)
+    }
+
+    fn gesture(&mut self, gesture: Gesture) {
+        match gesture {
+            Gesture::Pinch(zoom) => {
+                self.gesture_events.push(gesture);
+                self.msg(&Msg::Zoom(zoom));
+            }
+            Gesture::Pan(dx, dy) => self.msg(&Msg::Pan(dx, dy)),
+            Gesture::DoubleTap(x, y) => self.msg(&Msg::DoubleTap(x, y)),
+            Gesture::LongPress(x, y) => self.msg(&Msg::LongPress(x, y)),
+        }
+    }
+
     pub fn msg(&mut self, msg: &Msg) {
         match msg {
             Msg::AdvanceClock(dt) => {
                 self.clock += dt;
+                self.debug_draw.clear();
+
+                self.input.poll_gamepad();
+                self.camera.update(*dt, &self.input);
+                self.update_camera_path(*dt);
 
-                self.camera.update(*dt, &self.keyboard);
+                if let Some(g) = self.gestures.update(self.clock) {
+                    self.gesture(g);
+                }
 
                 //exponential falloff rolling average
                 self.dt_rolling = self.dt_rolling * 0.8 + dt * 0.2;
@@ -95,19 +265,20 @@ impl State {
                     self.next_log += 1000.0;
                 }
             }
-            Msg::MouseDown(x, y) => {
-                self.mouse.set_pressed(true);
-                self.mouse.set_pos(*x, *y);
+            Msg::MouseDown(x, y, button) => {
+                self.input.mouse.set_button(*button, true);
+                self.input.mouse.set_pos(*x, *y);
             }
-            Msg::MouseUp => {
-                self.mouse.set_pressed(false);
+            Msg::MouseUp(button) => {
+                self.input.mouse.set_button(*button, false);
             }
             Msg::MouseMove(x, y) => {
-                if !self.mouse.get_pressed() {
+                // Absolute positions are meaningless while locked, mouse look uses MouseLook instead
+                if !self.input.mouse.get_pressed() || self.pointer_locked {
                     return;
                 }
 
-                let (old_x, old_y) = self.mouse.get_pos();
+                let (old_x, old_y) = self.input.mouse.get_pos();
 
                 let x_delta = old_x as i32 - x;
                 let y_delta = y - old_y as i32;
@@ -115,13 +286,85 @@ impl State {
                 self.camera.orbit_left_right(x_delta as f32 / 50.0);
                 self.camera.orbit_up_down(y_delta as f32 / 50.0);
 
-                self.mouse.set_pos(*x, *y);
+                self.input.mouse.set_pos(*x, *y);
+            }
+            Msg::MouseLook(dx, dy) => {
+                if self.pointer_locked {
+                    self.camera.mouse_look(*dx as f32, *dy as f32);
+                }
+            }
+            Msg::PointerLockChanged(locked) => {
+                self.pointer_locked = *locked;
+                // Buttons released while the lock was being dropped never send a mouseup
+                if !locked {
+                    self.input.mouse.release_all();
+                }
             }
             Msg::Zoom(zoom) => {
                 self.camera.zoom(*zoom);
             }
-            Msg::KeyDown(key_code) => self.keyboard.set_pressed(*key_code, true),
-            Msg::KeyUp(key_code) => self.keyboard.set_pressed(*key_code, false),
+            Msg::TouchStart(touches) => {
+                self.gestures.touch_start(touches, self.clock);
+
+                // One finger orbits like a mouse drag, more fingers pinch and pan instead
+                match (self.gestures.touch_count(), &touches[..]) {
+                    (1, [t]) => self.msg(&Msg::MouseDown(t.x, t.y, 0)),
+                    _ => self.msg(&Msg::MouseUp(0)),
+                }
+            }
+            Msg::TouchMove(touches) => {
+                let gestures = self.gestures.touch_move(touches);
+
+                if let (1, [t]) = (self.gestures.touch_count(), &touches[..]) {
+                    self.msg(&Msg::MouseMove(t.x, t.y));
+                }
+                gestures.into_iter().for_each(|g| self.gesture(g));
+            }
+            Msg::TouchEnd(remaining) => {
+                let gestures = self.gestures.touch_end(remaining, self.clock);
+
+                match &remaining[..] {
+                    // Re-anchor the drag on the remaining finger so the camera does not jump
+                    [t] => self.msg(&Msg::MouseDown(t.x, t.y, 0)),
+                    _ => self.msg(&Msg::MouseUp(0)),
+                }
+                gestures.into_iter().for_each(|g| self.gesture(g));
+            }
+            Msg::Pan(dx, dy) => {
+                self.camera.pan(*dx, *dy);
+                self.gesture_events.push(Gesture::Pan(*dx, *dy));
+            }
+            Msg::DoubleTap(x, y) => self.gesture_events.push(Gesture::DoubleTap(*x, *y)),
+            Msg::LongPress(x, y) => self.gesture_events.push(Gesture::LongPress(*x, *y)),
+            Msg::KeyDown(key_code) => self.input.keyboard.set_pressed(*key_code, true),
+            Msg::KeyUp(key_code) => self.input.keyboard.set_pressed(*key_code, false),
+            Msg::PlayCameraPath(name) => {
+                if self.camera_paths.contains_key(name) {
+                    self.camera_path_player = Some(CameraPathPlayer::new(name.clone()));
+                } else {
+                    log::warn!("No camera path named {}", name);
+                }
+            }
+            Msg::PauseCameraPath => {
+                if let Some(p) = &mut self.camera_path_player {
+                    p.playing = false;
+                }
+            }
+            Msg::ResumeCameraPath => {
+                if let Some(p) = &mut self.camera_path_player {
+                    p.playing = true;
+                }
+            }
+            Msg::ScrubCameraPath(time) => {
+                if let Some(p) = &mut self.camera_path_player {
+                    p.time = time.max(0.0);
+                }
+            }
+            Msg::StopCameraPath => {
+                if let Some(p) = &mut self.camera_path_player {
+                    p.stopping = true;
+                }
+            }
         }
     }
 }
@@ -131,10 +374,30 @@ pub enum LuaMsg {
 }
 pub enum Msg {
     AdvanceClock(f32),
-    MouseDown(i32, i32),
-    MouseUp,
+    /// Position and `MouseEvent.button`
+    MouseDown(i32, i32, u8),
+    MouseUp(u8),
     MouseMove(i32, i32),
+    /// Relative movement in pixels while the pointer is locked
+    MouseLook(i32, i32),
+    PointerLockChanged(bool),
     KeyDown(KeyCode),
     KeyUp(KeyCode),
     Zoom(f32),
+    /// Every touch currently on the canvas
+    TouchStart(Vec<TouchPoint>),
+    TouchMove(Vec<TouchPoint>),
+    /// The touches remaining after some were lifted
+    TouchEnd(Vec<TouchPoint>),
+    /// Two finger pan in pixels
+    Pan(f32, f32),
+    DoubleTap(i32, i32),
+    LongPress(i32, i32),
+    PlayCameraPath(String),
+    PauseCameraPath,
+    ResumeCameraPath,
+    /// Jump to a time in seconds along the playing path
+    ScrubCameraPath(f32),
+    /// Blend back to the interactive camera
+    StopCameraPath,
 }
@@ -1,17 +1,34 @@
 #[derive(Default)]
 pub struct Mouse {
-    pressed: bool,
+    /// Bit per held button, indexed by `MouseEvent.button`
+    buttons: u8,
     x: u16,
     y: u16,
 }
 
 impl Mouse {
+    /// Whether any button is held
     pub fn get_pressed(&self) -> bool {
-        self.pressed
+        self.buttons != 0
     }
 
-    pub fn set_pressed(&mut self, pressed: bool) {
-        self.pressed = pressed;
+    pub fn get_button(&self, button: u8) -> bool {
+        button < 8 && self.buttons & (1 << button) != 0
+    }
+
+    pub fn set_button(&mut self, button: u8, pressed: bool) {
+        if button >= 8 {
+            return;
+        }
+        if pressed {
+            self.buttons |= 1 << button;
+        } else {
+            self.buttons &= !(1 << button);
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        self.buttons = 0;
     }
 
     pub fn set_pos(&mut self, x: i32, y: i32) {
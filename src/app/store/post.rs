@@ -0,0 +1,142 @@
+/// How the scene is drawn offscreen, and the full screen effects applied to it before it
+/// reaches the canvas in the order their fields are listed
+#[derive(Debug, Clone, Copy)]
+pub struct PostSettings {
+    /// Samples per pixel the scene and water views are drawn with, multisampling above 1.
+    /// Clamped to what the GPU supports.
+    pub msaa: i32,
+    /// Draw the scene with float colour, so lighting can go past 1 before tone mapping.
+    /// Falls back to 8 bit colour where float colour buffers are not supported.
+    pub hdr: bool,
+    pub bloom: BloomSettings,
+    pub tone_mapping: ToneMappingSettings,
+    pub grading: GradingSettings,
+    /// Fast approximate anti-aliasing
+    pub fxaa: bool,
+    pub vignette: VignetteSettings,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// Brightness above which pixels start to glow
+    pub threshold: f32,
+    /// Scale of the glow added back onto the scene
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMappingSettings {
+    pub enabled: bool,
+    /// Scale of the light before it is mapped into the displayable range
+    pub exposure: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GradingSettings {
+    pub enabled: bool,
+    /// 0 is greyscale, 1 leaves colours as they are
+    pub saturation: f32,
+    /// 1 leaves contrast as it is
+    pub contrast: f32,
+    /// Multiplied into every colour
+    pub tint: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VignetteSettings {
+    pub enabled: bool,
+    /// How dark the corners get, from 0 to 1
+    pub strength: f32,
+    /// Distance from the centre, in half screens, that darkening starts at
+    pub radius: f32,
+}
+
+/// One toggleable stage of `PostSettings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostEffect {
+    Bloom,
+    ToneMapping,
+    ColourGrading,
+    Fxaa,
+    Vignette,
+}
+
+impl PostEffect {
+    pub fn from_name(name: &str) -> Option<PostEffect> {
+        match name {
+            "bloom" => Some(PostEffect::Bloom),
+            "tone_mapping" => Some(PostEffect::ToneMapping),
+            "colour_grading" => Some(PostEffect::ColourGrading),
+            "fxaa" => Some(PostEffect::Fxaa),
+            "vignette" => Some(PostEffect::Vignette),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        PostSettings {
+            msaa: 4,
+            hdr: true,
+            bloom: BloomSettings {
+                enabled: false,
+                threshold: 0.8,
+                strength: 0.6,
+            },
+            tone_mapping: ToneMappingSettings {
+                enabled: false,
+                exposure: 1.0,
+            },
+            grading: GradingSettings {
+                enabled: false,
+                saturation: 1.0,
+                contrast: 1.0,
+                tint: [1.0, 1.0, 1.0],
+            },
+            fxaa: false,
+            vignette: VignetteSettings {
+                enabled: false,
+                strength: 0.5,
+                radius: 0.4,
+            },
+        }
+    }
+}
+
+impl PostSettings {
+    pub fn enabled(&self, effect: PostEffect) -> bool {
+        match effect {
+            PostEffect::Bloom => self.bloom.enabled,
+            PostEffect::ToneMapping => self.tone_mapping.enabled,
+            PostEffect::ColourGrading => self.grading.enabled,
+            PostEffect::Fxaa => self.fxaa,
+            PostEffect::Vignette => self.vignette.enabled,
+        }
+    }
+
+    pub fn set_enabled(&mut self, effect: PostEffect, enabled: bool) {
+        match effect {
+            PostEffect::Bloom => self.bloom.enabled = enabled,
+            PostEffect::ToneMapping => self.tone_mapping.enabled = enabled,
+            PostEffect::ColourGrading => self.grading.enabled = enabled,
+            PostEffect::Fxaa => self.fxaa = enabled,
+            PostEffect::Vignette => self.vignette.enabled = enabled,
+        }
+    }
+
+    /// The enabled effects, in the order they are applied
+    pub fn chain(&self) -> Vec<PostEffect> {
+        [
+            PostEffect::Bloom,
+            PostEffect::ToneMapping,
+            PostEffect::ColourGrading,
+            PostEffect::Fxaa,
+            PostEffect::Vignette,
+        ]
+        .into_iter()
+        .filter(|e| self.enabled(*e))
+        .collect()
+    }
+}
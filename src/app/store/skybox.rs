@@ -0,0 +1,81 @@
+use crate::app::render::{
+    graph::{RenderLayers, RenderPass},
+    render_trait::Render,
+    rgl::{shader::ShaderKind, texture::TexUnit},
+    ENVIRONMENT_UNIT,
+};
+
+use super::entity::Entity;
+use web_sys::WebGl2RenderingContext as GL;
+
+/// Where a skybox's cube map is drawn from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkyboxSource {
+    /// One texture per face, in the order +X, -X, +Y, -Y, +Z, -Z
+    Faces([usize; 6]),
+    /// A single panorama wrapped around the sphere by longitude and latitude
+    Equirect(usize),
+}
+
+impl SkyboxSource {
+    pub fn textures(&self) -> Vec<usize> {
+        match self {
+            SkyboxSource::Faces(faces) => faces.to_vec(),
+            SkyboxSource::Equirect(tex) => vec![*tex],
+        }
+    }
+}
+
+/// Sky drawn behind everything else, which also lights meshes through the renderer's
+/// environment maps
+#[derive(Debug, Clone)]
+pub struct Skybox {
+    pub source: SkyboxSource,
+    /// Width and height of each face of the cube map in texels
+    pub resolution: i32,
+    /// Scale of the ambient light meshes receive from the sky
+    pub strength: f32,
+}
+
+impl Entity for Skybox {
+    fn layers(&self) -> RenderLayers {
+        RenderLayers::SKY
+    }
+
+    fn update(&mut self, _control: &crate::app::Control) {}
+
+    fn prepare(
+        &self,
+        gl: &GL,
+        renderer: &crate::app::render::WebRenderer,
+        assets: &crate::app::Assets,
+    ) {
+        renderer.use_environment(gl, &self.source, self.resolution, self.strength, assets);
+    }
+
+    fn render(
+        &self,
+        gl: &GL,
+        renderer: &crate::app::render::WebRenderer,
+        camera: &crate::app::render::rgl::uniform_buffer::UniformBuffer<
+            crate::app::render::CameraData,
+        >,
+        _frustum: &crate::app::frustum::Frustum,
+        _pass: &RenderPass,
+        state: &super::State,
+        _assets: &crate::app::Assets,
+    ) {
+        // Nothing to draw until the source textures have loaded
+        if !renderer.bind_environment(gl, &TexUnit::new(gl, ENVIRONMENT_UNIT)) {
+            return;
+        }
+
+        let skybox_shader = renderer.shader_sys.get_shader(&ShaderKind::Skybox).unwrap();
+        renderer.shader_sys.use_program(gl, ShaderKind::Skybox);
+
+        let b = renderer.prepare_for_render(gl, self, skybox_shader, "skybox", state);
+
+        <dyn Render>::render(self, gl, &b, skybox_shader, renderer, camera, state);
+        renderer.count_draw_call();
+    }
+}
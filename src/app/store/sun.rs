@@ -0,0 +1,57 @@
+use nalgebra::{Isometry3, Matrix4, Orthographic3, Point3, Vector3};
+
+/// The directional light shared by every lit shader
+#[derive(Debug, Clone, Copy)]
+pub struct Sun {
+    /// Direction the light travels in, normalized
+    pub direction: Vector3<f32>,
+    pub shadows: ShadowSettings,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Width and height of the shadow map in texels
+    pub resolution: i32,
+    /// Depth offset to stop surfaces shadowing themselves (shadow acne)
+    pub bias: f32,
+    /// Half the width of the square area around the camera target that casts shadows
+    pub extent: f32,
+}
+
+impl Default for Sun {
+    fn default() -> Self {
+        Sun {
+            direction: Vector3::new(-1.0, -1.0, 0.5).normalize(),
+            shadows: ShadowSettings {
+                enabled: true,
+                resolution: 1024,
+                bias: 0.005,
+                extent: 30.0,
+            },
+        }
+    }
+}
+
+impl Sun {
+    /// Eye position of the light, far enough back along the light direction to see the whole area
+    pub fn eye(&self, center: Point3<f32>) -> Point3<f32> {
+        center - self.direction * self.shadows.extent * 2.0
+    }
+
+    pub fn view_mat(&self, center: Point3<f32>) -> Matrix4<f32> {
+        // look_at breaks down when looking straight along the up vector
+        let up = if self.direction.x.abs() < 1e-4 && self.direction.z.abs() < 1e-4 {
+            Vector3::z()
+        } else {
+            Vector3::y()
+        };
+
+        Isometry3::look_at_rh(&self.eye(center), &center, &up).to_homogeneous()
+    }
+
+    pub fn projection_mat(&self) -> Matrix4<f32> {
+        let e = self.shadows.extent;
+        Orthographic3::new(-e, e, -e, e, 0.1, e * 4.0).to_homogeneous()
+    }
+}
@@ -0,0 +1,103 @@
+use nalgebra::Vector3;
+use web_sys::WebGl2RenderingContext as GL;
+
+use super::entity::Entity;
+use super::State;
+use crate::app::frustum::Frustum;
+use crate::app::render::graph::{RenderLayers, RenderPass};
+use crate::app::render::rgl::uniform_buffer::UniformBuffer;
+use crate::app::render::{CameraData, WebRenderer};
+use crate::app::{Assets, Control};
+
+/// Where a text entity is drawn
+#[derive(Debug, Clone)]
+pub enum TextSpace {
+    /// Over the finished frame, at a position in pixels from the top left of the canvas
+    Screen([f32; 2]),
+    /// Facing the camera from a point in the scene, hidden by anything in front of it
+    World {
+        position: Vector3<f32>,
+        /// Name of an entity the text moves with, `position` then being relative to it
+        follow: Option<String>,
+    },
+}
+
+/// A string drawn with a signed distance field font
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub name: Option<String>,
+    pub text: String,
+    /// Font asset the text is drawn with
+    pub font: usize,
+    /// Height of a line, in pixels on screen or units in the world
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Point of the text placed on its position, as fractions of its width and height
+    /// from the top left
+    pub anchor: [f32; 2],
+    pub space: TextSpace,
+}
+
+impl Text {
+    /// Where world space text is anchored, `None` for screen space text or when the entity it
+    /// follows is missing
+    pub fn world_position(&self, state: &State) -> Option<Vector3<f32>> {
+        match &self.space {
+            TextSpace::Screen(_) => None,
+            TextSpace::World {
+                position,
+                follow: None,
+            } => Some(*position),
+            TextSpace::World {
+                position,
+                follow: Some(follow),
+            } => {
+                let e = state.find_entity(follow)?;
+                let e = e.try_borrow().ok()?;
+                e.position().map(|p| p + position)
+            }
+        }
+    }
+}
+
+impl Entity for Text {
+    fn layers(&self) -> RenderLayers {
+        match self.space {
+            TextSpace::Screen(_) => RenderLayers::HUD,
+            TextSpace::World { .. } => RenderLayers::LABEL,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn position(&self) -> Option<Vector3<f32>> {
+        match &self.space {
+            TextSpace::World {
+                position,
+                follow: None,
+            } => Some(*position),
+            _ => None,
+        }
+    }
+
+    fn text_mut(&mut self) -> Option<&mut Text> {
+        Some(self)
+    }
+
+    fn update(&mut self, _control: &Control) {}
+
+    fn render(
+        &self,
+        gl: &GL,
+        renderer: &WebRenderer,
+        camera: &UniformBuffer<CameraData>,
+        _frustum: &Frustum,
+        _pass: &RenderPass,
+        state: &State,
+        assets: &Assets,
+    ) {
+        renderer.draw_text(gl, self, camera, state, assets);
+    }
+}
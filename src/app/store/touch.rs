@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+/// Milliseconds between two taps for them to count as a double tap
+const DOUBLE_TAP_MS: f32 = 300.0;
+/// Milliseconds a finger must rest before a long press fires
+const LONG_PRESS_MS: f32 = 500.0;
+/// Pixels a finger may drift and still count as a tap or long press
+const TAP_SLOP: f32 = 10.0;
+/// Pixels of pinch spread per unit of `Msg::Zoom`
+const PINCH_ZOOM_SCALE: f32 = 20.0;
+
+/// A finger on the canvas, in canvas pixel coordinates
+#[derive(Debug, Clone, Copy)]
+pub struct TouchPoint {
+    pub id: i32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// High level gestures recognised from raw touches
+#[derive(Debug, Clone, Copy)]
+pub enum Gesture {
+    /// Positive zooms out, matching the mouse wheel
+    Pinch(f32),
+    /// Movement of the two finger centroid in pixels
+    Pan(f32, f32),
+    DoubleTap(i32, i32),
+    LongPress(i32, i32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveTouch {
+    start: (i32, i32),
+    pos: (i32, i32),
+}
+
+#[derive(Default)]
+pub struct GestureRecognizer {
+    touches: HashMap<i32, ActiveTouch>,
+    /// When the current single finger touch began
+    press_start: Option<f32>,
+    /// Time and position of the last completed tap
+    last_tap: Option<(f32, i32, i32)>,
+    long_press_fired: bool,
+    /// Whether more than one finger has been down since the last time all were lifted
+    multi_touch: bool,
+    /// Finger spread and centroid from the previous two finger update
+    prev_pair: Option<(f32, (f32, f32))>,
+}
+
+impl GestureRecognizer {
+    /// Number of fingers currently on the canvas
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    pub fn touch_start(&mut self, touches: &[TouchPoint], clock: f32) {
+        for t in touches {
+            self.touches.entry(t.id).or_insert(ActiveTouch {
+                start: (t.x, t.y),
+                pos: (t.x, t.y),
+            });
+        }
+
+        if self.touches.len() == 1 {
+            self.press_start = Some(clock);
+            self.long_press_fired = false;
+            self.multi_touch = false;
+        } else {
+            self.press_start = None;
+            self.multi_touch = true;
+            self.prev_pair = self.pair();
+        }
+    }
+
+    pub fn touch_move(&mut self, touches: &[TouchPoint]) -> Vec<Gesture> {
+        for t in touches {
+            if let Some(a) = self.touches.get_mut(&t.id) {
+                a.pos = (t.x, t.y);
+            }
+        }
+
+        let mut gestures = Vec::new();
+
+        if let Some(a) = self.single() {
+            if distance(a.start, a.pos) > TAP_SLOP {
+                self.press_start = None;
+            }
+        } else if let (Some((prev_spread, prev_center)), Some((spread, center))) =
+            (self.prev_pair, self.pair())
+        {
+            let zoom = (prev_spread - spread) / PINCH_ZOOM_SCALE;
+            if zoom != 0.0 {
+                gestures.push(Gesture::Pinch(zoom));
+            }
+
+            let (dx, dy) = (center.0 - prev_center.0, center.1 - prev_center.1);
+            if dx != 0.0 || dy != 0.0 {
+                gestures.push(Gesture::Pan(dx, dy));
+            }
+
+            self.prev_pair = Some((spread, center));
+        }
+
+        gestures
+    }
+
+    /// `remaining` is every touch still on the canvas after the event
+    pub fn touch_end(&mut self, remaining: &[TouchPoint], clock: f32) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+
+        let ended: Vec<ActiveTouch> = self
+            .touches
+            .iter()
+            .filter(|(id, _)| !remaining.iter().any(|t| t.id == **id))
+            .map(|(_, a)| *a)
+            .collect();
+        self.touches
+            .retain(|id, _| remaining.iter().any(|t| t.id == *id));
+
+        if self.touches.is_empty() {
+            if let (Some(start), [a]) = (self.press_start, &ended[..]) {
+                if !self.multi_touch && !self.long_press_fired && clock - start < LONG_PRESS_MS {
+                    let (x, y) = a.pos;
+                    match self.last_tap {
+                        Some((t, tx, ty))
+                            if clock - t < DOUBLE_TAP_MS
+                                && distance((tx, ty), (x, y)) < TAP_SLOP * 2.0 =>
+                        {
+                            gestures.push(Gesture::DoubleTap(x, y));
+                            self.last_tap = None;
+                        }
+                        _ => self.last_tap = Some((clock, x, y)),
+                    }
+                }
+            }
+            self.press_start = None;
+            self.prev_pair = None;
+        } else {
+            // Dropping from two fingers to one should not jump the pinch or pan
+            self.prev_pair = self.pair();
+        }
+
+        gestures
+    }
+
+    /// Called every frame to fire time based gestures
+    pub fn update(&mut self, clock: f32) -> Option<Gesture> {
+        let start = self.press_start?;
+
+        if !self.long_press_fired && clock - start >= LONG_PRESS_MS {
+            self.long_press_fired = true;
+            let (x, y) = self.single()?.pos;
+            return Some(Gesture::LongPress(x, y));
+        }
+        None
+    }
+
+    fn single(&self) -> Option<ActiveTouch> {
+        match self.touches.len() {
+            1 => self.touches.values().next().copied(),
+            _ => None,
+        }
+    }
+
+    /// Spread and centroid of the first two fingers
+    fn pair(&self) -> Option<(f32, (f32, f32))> {
+        let mut ids: Vec<&i32> = self.touches.keys().collect();
+        ids.sort();
+
+        match &ids[..] {
+            [a, b, ..] => {
+                let (a, b) = (self.touches[*a].pos, self.touches[*b].pos);
+                let center = ((a.0 + b.0) as f32 * 0.5, (a.1 + b.1) as f32 * 0.5);
+                Some((distance(a, b), center))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn distance(a: (i32, i32), b: (i32, i32)) -> f32 {
+    let (dx, dy) = ((a.0 - b.0) as f32, (a.1 - b.1) as f32);
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(id: i32, x: i32, y: i32) -> TouchPoint {
+        TouchPoint { id, x, y }
+    }
+
+    fn tap(g: &mut GestureRecognizer, x: i32, y: i32, clock: f32) -> Vec<Gesture> {
+        g.touch_start(&[touch(0, x, y)], clock);
+        g.touch_end(&[], clock + 50.0)
+    }
+
+    fn pans(gestures: &[Gesture]) -> Vec<(f32, f32)> {
+        gestures
+            .iter()
+            .filter_map(|g| match g {
+                Gesture::Pan(dx, dy) => Some((*dx, *dy)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spreading_fingers_zooms_in() {
+        let mut g = GestureRecognizer::default();
+        g.touch_start(&[touch(0, 100, 100), touch(1, 200, 100)], 0.0);
+
+        let gestures = g.touch_move(&[touch(0, 50, 100), touch(1, 250, 100)]);
+        assert!(matches!(gestures[..], [Gesture::Pinch(z)] if z < 0.0));
+
+        let gestures = g.touch_move(&[touch(0, 140, 100), touch(1, 160, 100)]);
+        assert!(matches!(gestures[..], [Gesture::Pinch(z)] if z > 0.0));
+    }
+
+    #[test]
+    fn quick_taps_double_tap() {
+        let mut g = GestureRecognizer::default();
+        assert!(tap(&mut g, 50, 50, 0.0).is_empty());
+
+        let gestures = tap(&mut g, 52, 51, 200.0);
+        assert!(matches!(gestures[..], [Gesture::DoubleTap(52, 51)]));
+
+        // The pair is used up, so a third tap starts over
+        assert!(tap(&mut g, 52, 51, 400.0).is_empty());
+    }
+
+    #[test]
+    fn slow_taps_are_not_a_double_tap() {
+        let mut g = GestureRecognizer::default();
+        assert!(tap(&mut g, 50, 50, 0.0).is_empty());
+        assert!(tap(&mut g, 50, 50, 50.0 + DOUBLE_TAP_MS).is_empty());
+    }
+
+    #[test]
+    fn long_press_fires_once() {
+        let mut g = GestureRecognizer::default();
+        g.touch_start(&[touch(0, 30, 40)], 0.0);
+
+        assert!(g.update(LONG_PRESS_MS - 1.0).is_none());
+        assert!(matches!(
+            g.update(LONG_PRESS_MS),
+            Some(Gesture::LongPress(30, 40))
+        ));
+        assert!(g.update(LONG_PRESS_MS + 100.0).is_none());
+
+        // Lifting after a long press is not also a tap
+        assert!(g.touch_end(&[], LONG_PRESS_MS + 200.0).is_empty());
+        assert!(tap(&mut g, 30, 40, LONG_PRESS_MS + 300.0).is_empty());
+    }
+
+    #[test]
+    fn dragged_finger_does_not_long_press() {
+        let mut g = GestureRecognizer::default();
+        g.touch_start(&[touch(0, 0, 0)], 0.0);
+        g.touch_move(&[touch(0, 50, 0)]);
+        assert!(g.update(LONG_PRESS_MS).is_none());
+    }
+
+    #[test]
+    fn lifting_one_of_two_fingers_does_not_pan() {
+        let mut g = GestureRecognizer::default();
+        g.touch_start(&[touch(0, 0, 0), touch(1, 100, 0)], 0.0);
+        g.touch_move(&[touch(0, 10, 0), touch(1, 110, 0)]);
+
+        // The centroid would jump 55 pixels if the remaining finger were still paired
+        assert!(g.touch_end(&[touch(1, 110, 0)], 100.0).is_empty());
+        assert!(g.touch_move(&[touch(1, 112, 0)]).is_empty());
+    }
+
+    #[test]
+    fn lifting_one_of_three_fingers_re_anchors_the_pan() {
+        let mut g = GestureRecognizer::default();
+        g.touch_start(&[touch(0, 0, 0), touch(1, 100, 0), touch(2, 300, 0)], 0.0);
+
+        // The pair moves from fingers 0 and 1 to 1 and 2, whose centroid is far away
+        g.touch_end(&[touch(1, 100, 0), touch(2, 300, 0)], 100.0);
+        let gestures = g.touch_move(&[touch(1, 104, 0), touch(2, 304, 0)]);
+        assert_eq!(pans(&gestures), vec![(4.0, 0.0)]);
+    }
+}
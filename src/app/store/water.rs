@@ -1,12 +1,11 @@
 use crate::app::render::{
+    graph::{RenderLayers, RenderPass, REFLECTION, REFRACTION},
     material::{MatWater, Material},
     render_trait::Render,
     rgl::shader::ShaderKind,
-    CameraData, RenderStage,
 };
 
 use super::entity::Entity;
-use nalgebra::Point4;
 use web_sys::WebGl2RenderingContext as GL;
 #[derive(Debug, Clone)]
 pub struct Water {
@@ -20,10 +19,22 @@ pub struct Water {
 }
 
 impl Entity for Water {
-    fn should_render(&self, stage: &RenderStage) -> bool {
-        *stage == RenderStage::Water
+    fn layers(&self) -> RenderLayers {
+        RenderLayers::WATER
     }
-    fn update(&mut self, control: &crate::app::Control) {}
+
+    fn inputs(&self) -> Vec<&'static str> {
+        let mut inputs = vec![];
+        if self.use_reflection {
+            inputs.push(REFLECTION);
+        }
+        if self.use_refraction {
+            inputs.push(REFRACTION);
+        }
+        inputs
+    }
+
+    fn update(&mut self, _control: &crate::app::Control) {}
 
     fn render(
         &self,
@@ -32,55 +43,11 @@ impl Entity for Water {
         camera: &crate::app::render::rgl::uniform_buffer::UniformBuffer<
             crate::app::render::CameraData,
         >,
-        clip_plane: [f32; 4],
-        stage: RenderStage,
+        _frustum: &crate::app::frustum::Frustum,
+        _pass: &RenderPass,
         state: &super::State,
         assets: &crate::app::Assets,
     ) {
-        let water_tile_y = 0.0;
-
-        if self.use_reflection {
-            let p = state.camera().get_eye_pos();
-            let flipped_y_camera = CameraData {
-                view: state.camera().view_flipped_y_mat(),
-                projection: state.camera().projection_mat().clone(),
-                pos: Point4::new(p.x, p.y, p.z, 0.0),
-            };
-
-            renderer
-                .flipped_y_camera_buffer
-                .buffer(gl, &flipped_y_camera);
-            renderer.render_reflection_fbo(
-                gl,
-                self,
-                &renderer.flipped_y_camera_buffer,
-                state,
-                assets,
-                water_tile_y,
-            );
-        }
-        if self.use_refraction {
-            renderer.render_refraction_fbo(
-                gl,
-                self,
-                &renderer.camera_buffer,
-                state,
-                assets,
-                water_tile_y,
-            );
-        }
-
-        if self.use_reflection || self.use_refraction {
-            gl.viewport(
-                0,
-                0,
-                state.display.width as i32,
-                state.display.height as i32,
-            );
-
-            gl.bind_framebuffer(GL::FRAMEBUFFER, None);
-        }
-
         let water_shader = renderer.shader_sys.get_shader(&ShaderKind::Water).unwrap();
         renderer.shader_sys.use_program(gl, ShaderKind::Water);
 
@@ -103,5 +70,6 @@ impl Entity for Water {
         let b = renderer.prepare_for_render(gl, self, water_shader, "water", state);
 
         <dyn Render>::render(self, gl, &b, water_shader, renderer, camera, state);
+        renderer.count_draw_call();
     }
 }
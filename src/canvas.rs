@@ -1,3 +1,4 @@
+use crate::app::store::touch::TouchPoint;
 use crate::app::store::State;
 use crate::app::App;
 use crate::app::Msg;
@@ -8,7 +9,7 @@ use wasm_bindgen::JsValue;
 use web_sys::WebGl2RenderingContext as GL;
 use web_sys::*;
 
-pub static APP_DIV_ID: &'static str = "webgl-water-tutorial";
+pub static APP_DIV_ID: &str = "webgl-water-tutorial";
 
 //pub static CANVAS_WIDTH: i32 = 720;
 //pub static CANVAS_HEIGHT: i32 = 512;
@@ -41,6 +42,7 @@ fn init_canvas(app: Rc<App>) -> Result<HtmlCanvasElement, JsValue> {
     attach_mouse_up_handler(&canvas, Rc::clone(&app))?;
     attach_mouse_move_handler(&canvas, Rc::clone(&app))?;
     attach_mouse_wheel_handler(&canvas, Rc::clone(&app))?;
+    attach_pointer_lock_change_handler(&document, &canvas, Rc::clone(&app))?;
 
     //attach_key_up_handler(&canvas, Rc::clone(&app))?;
     //attach_key_down_handler(&canvas, Rc::clone(&app))?;
@@ -77,6 +79,7 @@ fn init_canvas(app: Rc<App>) -> Result<HtmlCanvasElement, JsValue> {
 pub fn update_display(canvas: &HtmlCanvasElement, state: &mut State) {
     let window = window().unwrap();
     let dpr: f64 = window.device_pixel_ratio();
+    state.display.pixel_ratio = dpr as f32;
     let display_width = std::cmp::min(1920, (canvas.client_width() as f64 * dpr).round() as u32);
     let display_height = std::cmp::min(1080, (canvas.client_height() as f64 * dpr).round() as u32);
 
@@ -102,10 +105,18 @@ pub fn update_display(canvas: &HtmlCanvasElement, state: &mut State) {
 }
 
 fn attach_mouse_down_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
+    let c = canvas.clone();
     let handler = move |event: web_sys::MouseEvent| {
-        let x = event.client_x();
-        let y = event.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseDown(x, y));
+        let x = event.offset_x();
+        let y = event.offset_y();
+        let button = event.button() as u8;
+
+        let mut store = app.store.borrow_mut();
+        // Pointer lock can only be requested from inside a user gesture like this one
+        if store.state.camera().wants_pointer_lock() && !store.state.pointer_locked() {
+            c.request_pointer_lock();
+        }
+        store.msg(&Msg::MouseDown(x, y, button));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -118,8 +129,9 @@ fn attach_mouse_down_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result
 }
 
 fn attach_mouse_up_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
-    let handler = move |_event: web_sys::MouseEvent| {
-        app.store.borrow_mut().msg(&Msg::MouseUp);
+    let handler = move |event: web_sys::MouseEvent| {
+        let button = event.button() as u8;
+        app.store.borrow_mut().msg(&Msg::MouseUp(button));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -132,9 +144,13 @@ fn attach_mouse_up_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(
 fn attach_mouse_move_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
     let handler = move |event: web_sys::MouseEvent| {
         event.prevent_default();
-        let x = event.client_x();
-        let y = event.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseMove(x, y));
+
+        let mut store = app.store.borrow_mut();
+        if store.state.pointer_locked() {
+            store.msg(&Msg::MouseLook(event.movement_x(), event.movement_y()));
+        } else {
+            store.msg(&Msg::MouseMove(event.offset_x(), event.offset_y()));
+        }
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -160,12 +176,56 @@ fn attach_mouse_wheel_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Resul
     Ok(())
 }
 
+fn attach_pointer_lock_change_handler(
+    document: &Document,
+    canvas: &HtmlCanvasElement,
+    app: Rc<App>,
+) -> Result<(), JsValue> {
+    let d = document.clone();
+    let c = canvas.clone();
+    let handler = move |_event: web_sys::Event| {
+        let locked = d
+            .pointer_lock_element()
+            .map_or(false, |e| e.is_same_node(Some(&c)));
+        app.store.borrow_mut().msg(&Msg::PointerLockChanged(locked));
+    };
+
+    let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+    document
+        .add_event_listener_with_callback("pointerlockchange", handler.as_ref().unchecked_ref())?;
+    handler.forget();
+
+    Ok(())
+}
+
+/// Give the pointer back if the canvas has it locked
+pub fn exit_pointer_lock() {
+    if let Some(document) = window().and_then(|w| w.document()) {
+        document.exit_pointer_lock();
+    }
+}
+
+/// All touches in a list, relative to the top left of the canvas
+fn touch_points(canvas: &HtmlCanvasElement, touches: TouchList) -> Vec<TouchPoint> {
+    let rect = canvas.get_bounding_client_rect();
+
+    (0..touches.length())
+        .filter_map(|i| touches.item(i))
+        .map(|t| TouchPoint {
+            id: t.identifier(),
+            x: t.client_x() - rect.left() as i32,
+            y: t.client_y() - rect.top() as i32,
+        })
+        .collect()
+}
+
 fn attach_touch_start_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
+    let c = canvas.clone();
     let handler = move |event: web_sys::TouchEvent| {
-        let touch = event.touches().item(0).expect("First Touch");
-        let x = touch.client_x();
-        let y = touch.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseDown(x, y));
+        // Stop the browser from zooming or scrolling the page instead
+        event.prevent_default();
+        let touches = touch_points(&c, event.touches());
+        app.store.borrow_mut().msg(&Msg::TouchStart(touches));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -176,12 +236,11 @@ fn attach_touch_start_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Resul
 }
 
 fn attach_touch_move_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
+    let c = canvas.clone();
     let handler = move |event: web_sys::TouchEvent| {
         event.prevent_default();
-        let touch = event.touches().item(0).expect("First Touch");
-        let x = touch.client_x();
-        let y = touch.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseMove(x, y));
+        let touches = touch_points(&c, event.touches());
+        app.store.borrow_mut().msg(&Msg::TouchMove(touches));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -192,13 +251,16 @@ fn attach_touch_move_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result
 }
 
 fn attach_touch_end_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
-    let handler = move |_event: web_sys::TouchEvent| {
-        app.store.borrow_mut().msg(&Msg::MouseUp);
+    let c = canvas.clone();
+    let handler = move |event: web_sys::TouchEvent| {
+        let remaining = touch_points(&c, event.touches());
+        app.store.borrow_mut().msg(&Msg::TouchEnd(remaining));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
 
     canvas.add_event_listener_with_callback("touchend", handler.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("touchcancel", handler.as_ref().unchecked_ref())?;
 
     handler.forget();
 
@@ -0,0 +1,32 @@
+use crate::app::store::gamepad::{GamepadSource, GamepadState};
+use wasm_bindgen::JsCast;
+use web_sys::{Gamepad, GamepadButton};
+
+/// Reads controllers through `navigator.getGamepads()`
+pub struct WebGamepads;
+
+impl GamepadSource for WebGamepads {
+    fn poll(&mut self) -> Option<GamepadState> {
+        let pads = web_sys::window()?.navigator().get_gamepads().ok()?;
+
+        // Disconnected slots are null, so take the first real controller
+        let pad = pads
+            .iter()
+            .filter_map(|p| p.dyn_into::<Gamepad>().ok())
+            .find(|p| p.connected())?;
+
+        Some(GamepadState {
+            buttons: pad
+                .buttons()
+                .iter()
+                .filter_map(|b| b.dyn_into::<GamepadButton>().ok())
+                .map(|b| b.value() as f32)
+                .collect(),
+            axes: pad
+                .axes()
+                .iter()
+                .map(|a| a.as_f64().unwrap_or(0.0) as f32)
+                .collect(),
+        })
+    }
+}
@@ -22,9 +22,13 @@ extern crate wasm_bindgen;
 
 pub(crate) use self::app::*;
 use self::canvas::*;
+use self::gamepad::WebGamepads;
 use self::render::*;
+use app::camera::MouseLook;
+use app::debug::DebugView;
+use app::input::Binding;
 use app::keyboard::KeyCode;
-use console_error_panic_hook;
+
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::*;
@@ -34,6 +38,7 @@ use crate::num::FromPrimitive;
 mod app;
 mod canvas;
 mod fetch;
+mod gamepad;
 
 /// Used to run the application from the web
 #[wasm_bindgen]
@@ -47,6 +52,7 @@ pub struct WebClient {
 impl WebClient {
     /// Create a new web client
     #[wasm_bindgen(constructor)]
+    #[allow(clippy::new_without_default)]
     pub fn new() -> WebClient {
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
         wasm_logger::init(wasm_logger::Config::default());
@@ -55,6 +61,12 @@ impl WebClient {
 
         let (gl, canvas) = create_webgl_context(Rc::clone(&app)).unwrap();
 
+        app.store
+            .borrow_mut()
+            .state
+            .input
+            .set_gamepad_source(Box::new(WebGamepads));
+
         let renderer = WebRenderer::new(&gl);
 
         WebClient {
@@ -75,28 +87,40 @@ impl WebClient {
 
     /// Update our simulation
     pub fn update(&self, dt: f32) {
-        let mut store = self.app.store.borrow_mut();
-        store.msg(&Msg::AdvanceClock(dt));
-
-        match self.app.control.try_borrow_mut() {
-            Ok(mut c) => {
-                //TODO: fix updates
-                // for e in &mut store.state.entities {
-                //     if let Entity::EntMesh(m) = &**e {
-                //         let f = { m.borrow().update.as_ref().map(|f| f.clone()) };
-
-                //         if let Some(f) = f {
-                //             c.run_func(&f, m.clone());
-                //         }
-                //     }
-                // }
+        let gestures = {
+            let mut store = self.app.store.borrow_mut();
+            store.msg(&Msg::AdvanceClock(dt));
+            if let Ok(assets) = self.app.assets.try_borrow() {
+                store.state.animate(dt, &assets);
+            }
+            store.state.take_gesture_events()
+        };
+
+        if let Ok(mut c) = self.app.control.try_borrow_mut() {
+            c.on_update(dt);
+
+            for g in &gestures {
+                c.on_gesture(g);
             }
-            Err(_) => (),
+
+            //TODO: fix updates
+            // for e in &mut store.state.entities {
+            //     if let Entity::EntMesh(m) = &**e {
+            //         let f = { m.borrow().update.as_ref().map(|f| f.clone()) };
+
+            //         if let Some(f) = f {
+            //             c.run_func(&f, m.clone());
+            //         }
+            //     }
+            // }
         }
     }
     /// To be called on the 'keydown' event
     pub fn on_key_down(&mut self, key_code: u32) {
         if let Some(k) = KeyCode::from_u32(key_code) {
+            if k == KeyCode::Escape {
+                exit_pointer_lock();
+            }
             self.app.store.borrow_mut().msg(&Msg::KeyDown(k));
         }
     }
@@ -107,6 +131,50 @@ impl WebClient {
         }
     }
 
+    /// Add an input to a named action, such as `bind_action("move_forward", "ArrowUp")`.
+    /// Keys are named like `W`, `Space` or `Escape`, mouse buttons `Mouse0` to `Mouse2`,
+    /// gamepad buttons `Pad0` to `Pad16` and gamepad axis directions like `Axis1-`.
+    pub fn bind_action(&self, action: &str, binding: &str) -> Result<(), JsValue> {
+        let binding = Binding::from_name(binding)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown input {}", binding)))?;
+
+        self.app
+            .store
+            .borrow_mut()
+            .state
+            .input
+            .map
+            .bind(action, binding);
+        Ok(())
+    }
+    /// Configure pointer-lock mouse look for the FPS camera. `sensitivity` is radians per pixel.
+    pub fn set_mouse_look(&self, sensitivity: f32, invert_y: bool, pointer_lock: bool) {
+        let mut store = self.app.store.borrow_mut();
+        store.state.camera_mut().mouse_look = MouseLook {
+            sensitivity,
+            invert_y,
+            pointer_lock,
+        };
+
+        if !pointer_lock && store.state.pointer_locked() {
+            exit_pointer_lock();
+        }
+    }
+
+    /// Set how far sticks and triggers must move, from 0 to 1, before they register
+    pub fn set_gamepad_dead_zones(&self, stick: f32, trigger: f32) {
+        let dead_zones = &mut self.app.store.borrow_mut().state.input.dead_zones;
+        dead_zones.stick = stick.clamp(0.0, 0.99);
+        dead_zones.trigger = trigger.clamp(0.0, 0.99);
+    }
+    /// Remove every binding from an action
+    pub fn unbind_action(&self, action: &str) {
+        self.app.store.borrow_mut().state.input.map.unbind(action);
+    }
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.app.store.borrow().state.input.pressed(action)
+    }
+
     /// Update our simulation
     pub async fn restart(&self, onload: String) -> String {
         let s = match self.app.control.try_borrow_mut() {
@@ -131,9 +199,58 @@ impl WebClient {
 
         Assets::load_requirements(self.app.assets.clone(), self.gl.clone()).await;
 
+        self.app
+            .store
+            .borrow_mut()
+            .state
+            .resolve_camera_paths(&self.app.assets.borrow());
+
+        // The new scene may not use mouse look
+        let state = &self.app.store.borrow().state;
+        if state.pointer_locked() && !state.camera().wants_pointer_lock() {
+            exit_pointer_lock();
+        }
+
         s
     }
 
+    /// Start a camera path defined by the loaded script, blending away from the interactive camera
+    pub fn play_camera_path(&self, name: String) {
+        self.app.store.borrow_mut().msg(&Msg::PlayCameraPath(name));
+    }
+    pub fn pause_camera_path(&self) {
+        self.app.store.borrow_mut().msg(&Msg::PauseCameraPath);
+    }
+    pub fn resume_camera_path(&self) {
+        self.app.store.borrow_mut().msg(&Msg::ResumeCameraPath);
+    }
+    /// Jump to `seconds` into the playing camera path
+    pub fn scrub_camera_path(&self, seconds: f32) {
+        self.app
+            .store
+            .borrow_mut()
+            .msg(&Msg::ScrubCameraPath(seconds));
+    }
+    /// Blend back to the interactive camera
+    pub fn stop_camera_path(&self) {
+        self.app.store.borrow_mut().msg(&Msg::StopCameraPath);
+    }
+
+    /// Turn a debug view on or off. Views are `textures`, `framebuffers`, `bounds`,
+    /// `normals`, `wireframe` and `overdraw`.
+    pub fn set_debug_view(&self, view: &str, enabled: bool) -> Result<(), JsValue> {
+        let view = DebugView::from_name(view)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown debug view {}", view)))?;
+
+        self.app
+            .store
+            .borrow_mut()
+            .state
+            .debug
+            .set_enabled(view, enabled);
+        Ok(())
+    }
+
     /// Render the scene. `index.html` will call this once every requestAnimationFrame
     pub fn render(&self) {
         update_display(&self.canvas, &mut self.app.store.borrow_mut().state);
@@ -144,4 +261,9 @@ impl WebClient {
             &self.app.assets.borrow(),
         );
     }
+
+    /// Culling, draw call and state change counters from the last rendered frame
+    pub fn render_stats(&self) -> RenderStats {
+        self.renderer.stats.get()
+    }
 }